@@ -0,0 +1,139 @@
+//! `faucet bench`: times each stage of the scoring pipeline over sample inputs.
+//!
+//! ```sh
+//! faucet bench --iterations 50 samples/url.txt samples/image.png
+//! faucet bench --cached samples/url.txt
+//! ```
+//!
+//! Nothing is executed; the report shows p50/p99 latency per stage so the
+//! keypress-to-menu budget can be kept in check as the config grows. `--cached`
+//! shares one probe cache across iterations, as a long-running process would.
+
+use crate::cache::ProbeCache;
+use crate::{Config, Data, Plumb, Validation, check_config, decide, diagnostic, rank_commands, score_commands};
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const STAGES: [&str; 7] = ["config", "load", "write", "detect", "derive", "score", "decide"];
+
+pub fn run(config: &Config, config_source: &str, config_path: &Path, args: &[String]) -> Result<()> {
+    let mut iterations = 20;
+    let mut cached = false;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" | "-n" => {
+                iterations = args
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing value for '{arg}'"))?
+                    .parse()?;
+            }
+            "--cached" => cached = true,
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    if inputs.is_empty() {
+        bail!("bench requires at least one sample input file");
+    }
+    if iterations == 0 {
+        bail!("bench requires at least one iteration");
+    }
+
+    for input in &inputs {
+        let shared_cache = ProbeCache::new(config.options.probe_cache_size);
+        let mut timings: Vec<[Duration; STAGES.len()]> = Vec::with_capacity(iterations);
+        for _ in 0..iterations {
+            let fresh_cache;
+            let cache = if cached {
+                &shared_cache
+            } else {
+                fresh_cache = ProbeCache::new(config.options.probe_cache_size);
+                &fresh_cache
+            };
+            timings.push(time_pipeline(config, cache, config_source, config_path, input)?);
+        }
+        report(input, &timings);
+    }
+    Ok(())
+}
+
+fn time_pipeline(
+    config: &Config,
+    cache: &ProbeCache,
+    config_source: &str,
+    config_path: &Path,
+    input: &Path,
+) -> Result<[Duration; STAGES.len()]> {
+    let mut durations = [Duration::ZERO; STAGES.len()];
+    let mut stage = 0;
+    let mut lap = Instant::now();
+    let mut mark = |durations: &mut [Duration; STAGES.len()]| {
+        durations[stage] = lap.elapsed();
+        stage += 1;
+        lap = Instant::now();
+    };
+
+    let mut reparsed: Config = diagnostic::parse_config(config_source, config_path)?;
+    check_config(&mut reparsed, config_source, config_path, Validation::Full)?;
+    mark(&mut durations);
+
+    let data = Data::from_bytes(std::fs::read(input)?);
+    mark(&mut durations);
+
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    let temp_file_path = temp_file_handle
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+    data.write_to_temp_file(temp_file_path)?;
+    mark(&mut durations);
+
+    let mut plumb = Plumb::new(
+        &config.options,
+        &data,
+        temp_file_path,
+        "clipboard",
+        data.get_text_for_matching(&crate::SystemRunner, temp_file_path)?,
+    );
+    mark(&mut durations);
+
+    if let Some(derive_command) = &config.options.derive_command {
+        plumb.derive(derive_command)?;
+    }
+    mark(&mut durations);
+
+    let scored_commands = score_commands(config, &plumb, cache, false)?;
+    mark(&mut durations);
+
+    decide(&config.options, rank_commands(&scored_commands));
+    mark(&mut durations);
+
+    Ok(durations)
+}
+
+fn percentile(sorted: &[Duration], percentile: f64) -> Duration {
+    let rank = ((sorted.len() - 1) as f64 * percentile).ceil() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+fn report(input: &Path, timings: &[[Duration; STAGES.len()]]) {
+    println!("{} ({} iterations)", input.display(), timings.len());
+    println!("  {:<8} {:>10} {:>10}", "stage", "p50", "p99");
+    let totals: Vec<Duration> = timings.iter().map(|stages| stages.iter().sum()).collect();
+    let columns = STAGES
+        .iter()
+        .enumerate()
+        .map(|(stage, name)| (*name, timings.iter().map(|stages| stages[stage]).collect::<Vec<_>>()))
+        .chain(std::iter::once(("total", totals)));
+    for (name, mut durations) in columns {
+        durations.sort();
+        println!(
+            "  {:<8} {:>8.3}ms {:>8.3}ms",
+            name,
+            percentile(&durations, 0.5).as_secs_f64() * 1000.0,
+            percentile(&durations, 0.99).as_secs_f64() * 1000.0
+        );
+    }
+}
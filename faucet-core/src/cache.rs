@@ -0,0 +1,57 @@
+//! Bounded LRU of scorer condition outcomes, keyed by (condition, data) fingerprints.
+//!
+//! A one-shot plumb only sees each key once; the cache pays off when one process scores
+//! repeatedly (`faucet bench --cached`, long-running modes), e.g. plumbing the same link
+//! several times in a browsing session.
+
+use indexmap::IndexMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use tracing::trace;
+
+pub struct ProbeCache {
+    capacity: usize,
+    entries: Mutex<IndexMap<(u64, u64), bool>>,
+}
+
+impl ProbeCache {
+    /// A capacity of 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(IndexMap::new()),
+        }
+    }
+
+    /// Returns the cached outcome for `key`, or runs `probe` (without holding the lock) and caches
+    /// it. A probe that fails, or can't tell (`None`, counted as not matched), isn't cached, so the
+    /// next one runs it again
+    pub fn get_or_probe<E>(&self, key: (u64, u64), probe: impl FnOnce() -> Result<Option<bool>, E>) -> Result<bool, E> {
+        if self.capacity == 0 {
+            return probe().map(|outcome| outcome.unwrap_or(false));
+        }
+        {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(outcome) = entries.shift_remove(&key) {
+                trace!("Probe cache hit for {key:?}: {outcome}");
+                entries.insert(key, outcome);
+                return Ok(outcome);
+            }
+        }
+        let Some(outcome) = probe()? else {
+            return Ok(false);
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key, outcome);
+        while entries.len() > self.capacity {
+            entries.shift_remove_index(0);
+        }
+        Ok(outcome)
+    }
+}
+
+pub fn fingerprint(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,132 @@
+//! Cancelling a plumb nobody wants any more. Probes (command scorers, the derive command, `file`,
+//! `wl-paste`, ...) started through a `CancellableRunner` run in process groups of their own,
+//! registered with the plumb's `CancelToken`: cancelling kills the groups still running and fails
+//! every probe started after, so none is left to finish pointlessly.
+//!
+//! `faucet` cancels its plumb on `SIGINT` or `SIGTERM`. With `on_busy: replace`, a second `faucet`
+//! (the keybinding pressed again) sends `SIGTERM` to the plumbs still scoring, which list
+//! themselves in `scoring/` in the runtime dir. The long-running modes cancel the request in hand
+//! on `SIGUSR2`, see `lifecycle`.
+
+use anyhow::Result;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::collections::HashSet;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::debug;
+
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    cancelled: bool,
+    /// Process groups of the probes running
+    groups: HashSet<u32>,
+    /// This plumb's entry in `scoring/`, while it scores
+    scoring: Option<Scoring>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills the probes running and fails those started from now on
+    pub fn cancel(&self) {
+        let mut state = self.state();
+        state.cancelled = true;
+        state.scoring = None;
+        for group in state.groups.drain() {
+            debug!("Killing probe process group {group}");
+            let _ = Command::new("kill").args(["-TERM", "--", &format!("-{group}")]).status();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state().cancelled
+    }
+
+    /// Starts `command` in a process group of its own and hands it to `wait`, killing the group
+    /// should the token be cancelled before `wait` returns
+    pub fn run<T>(&self, command: &mut Command, wait: impl FnOnce(Child) -> std::io::Result<T>) -> std::io::Result<T> {
+        let child = {
+            let mut state = self.state();
+            if state.cancelled {
+                return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "The plumb was cancelled"));
+            }
+            let child = command.process_group(0).spawn()?;
+            state.groups.insert(child.id());
+            child
+        };
+        let group = child.id();
+        let result = wait(child);
+        self.state().groups.remove(&group);
+        result
+    }
+
+    /// Cancels the plumbs an earlier `faucet` is still scoring, and lists this one until `scored`
+    pub fn supersede(&self) -> Result<()> {
+        let dir = crate::runtime::dir()?.join("scoring");
+        std::fs::create_dir_all(&dir)?;
+        let own = std::process::id().to_string();
+        for entry in std::fs::read_dir(&dir)?.filter_map(|entry| entry.ok()) {
+            let pid = entry.file_name().to_string_lossy().into_owned();
+            if pid == own {
+                continue;
+            }
+            // A pid reused since its plumb died must not be signalled, and without /proc there is no telling
+            if !is_faucet(&pid) {
+                let _ = std::fs::remove_file(entry.path());
+                continue;
+            }
+            debug!("Cancelling the plumb {pid} is still scoring");
+            Command::new("kill").args(["-TERM", &pid]).status()?;
+        }
+        let path = dir.join(own);
+        std::fs::File::create(&path)?;
+        self.state().scoring = Some(Scoring(path));
+        Ok(())
+    }
+
+    /// Scoring is over: from here on a replacing `faucet` closes the picker instead
+    pub fn scored(&self) {
+        self.state().scoring = None;
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Removes the entry in `scoring/` when dropped
+struct Scoring(PathBuf);
+
+impl Drop for Scoring {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn is_faucet(pid: &str) -> bool {
+    !pid.is_empty()
+        && pid.bytes().all(|b| b.is_ascii_digit())
+        && std::fs::read_link(Path::new("/proc").join(pid).join("exe"))
+            .is_ok_and(|exe| std::env::current_exe().is_ok_and(|own| own == exe))
+}
+
+/// Cancels `token` on `SIGINT` or `SIGTERM`, then exits as the signal would have
+pub fn on_signals(token: CancelToken) -> Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            debug!("Signal {signal}: cancelling the plumb");
+            token.cancel();
+            std::process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
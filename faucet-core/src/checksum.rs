@@ -0,0 +1,58 @@
+//! The `verify_checksum` builtin: checks a detected checksum against the named file, or the most
+//! recent download when only a bare hash was plumbed.
+
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_DIR: &str = "~/Downloads";
+
+/// A bare hash is only matched against downloads finished this recently
+const RECENT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Hashes the file with coreutils' `<algorithm>sum`, returning the file and whether it matched
+pub fn verify(algorithm: &str, expected: &str, file: &str, dir: &str) -> Result<(PathBuf, bool)> {
+    let dir = crate::expand_home(dir)?;
+    let path = if file.is_empty() {
+        latest_download(&dir)?
+    } else {
+        let file = Path::new(file.trim_start_matches("./"));
+        if file.is_absolute() { file.to_path_buf() } else { dir.join(file) }
+    };
+    if !path.is_file() {
+        bail!("'{}' does not exist", path.display());
+    }
+    let output = std::process::Command::new(format!("{algorithm}sum"))
+        .arg("--")
+        .arg(&path)
+        .output()?;
+    if !output.status.success() {
+        bail!("{algorithm}sum failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let stdout = String::from_utf8(output.stdout)?;
+    let actual = stdout
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("{algorithm}sum printed no hash"))?;
+    Ok((path, actual.eq_ignore_ascii_case(expected)))
+}
+
+/// Newest regular file in `dir`, skipping hidden files and in-progress downloads
+fn latest_download(dir: &Path) -> Result<PathBuf> {
+    let now = SystemTime::now();
+    std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            !name.starts_with('.') && ![".part", ".crdownload", ".download"].iter().any(|suffix| name.ends_with(suffix))
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            (metadata.is_file() && now.duration_since(modified).unwrap_or_default() < RECENT)
+                .then(|| (modified, entry.path()))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+        .ok_or_else(|| anyhow!("No recent download in '{}' to verify", dir.display()))
+}
@@ -0,0 +1,90 @@
+//! Global flags accepted ahead of the input mode (`faucet --no-exec sel`).
+
+use anyhow::{Result, anyhow};
+
+#[derive(Clone, Default)]
+pub struct Flags {
+    /// Decide and print, but never run the chosen command
+    pub no_exec: bool,
+    pub min_threshold: Option<i32>,
+    pub max_threshold: Option<i32>,
+    /// Always show the picker, even when a command would auto-select
+    pub no_auto: bool,
+    /// Replaces the picker's prompt, e.g. to say what a key binding plumbs
+    pub prompt: Option<String>,
+    /// Print how every command scored, and run nothing
+    pub explain: bool,
+    /// Print the ranking as JSON in place of the picker, and run nothing
+    pub json: bool,
+    /// Never show the picker: auto-select or print the candidates as JSON
+    pub headless: bool,
+    /// Config file in place of `$FAUCET_CONFIG` or the one in the config dir; `-` reads it from stdin
+    pub config: Option<std::path::PathBuf>,
+    /// An inherited file descriptor to read the data from, in place of an input mode
+    pub fd: Option<u32>,
+}
+
+/// Removes leading flags from `args` (after the program name), leaving the input mode behind.
+/// Parsing stops at the first non-flag or `--`, so plumbed text can still start with dashes.
+pub fn take_flags(args: &mut Vec<String>) -> Result<Flags> {
+    let mut flags = Flags::default();
+    while let Some(arg) = args.get(1).cloned() {
+        let mut value = || -> Result<String> {
+            if args.len() < 3 {
+                return Err(anyhow!("Missing value for '{arg}'"));
+            }
+            Ok(args.remove(2))
+        };
+        match arg.as_str() {
+            "--no-exec" => flags.no_exec = true,
+            "--min-threshold" => flags.min_threshold = Some(parse_threshold(&arg, &value()?)?),
+            "--max-threshold" => flags.max_threshold = Some(parse_threshold(&arg, &value()?)?),
+            "--no-auto" => flags.no_auto = true,
+            "--prompt" => flags.prompt = Some(value()?),
+            "--explain" => flags.explain = true,
+            "--json" => flags.json = true,
+            "--headless" => flags.headless = true,
+            "--config" => flags.config = Some(value()?.into()),
+            "--fd" => {
+                let fd = value()?;
+                flags.fd = Some(fd.parse().map_err(|e| anyhow!("Bad value '{fd}' for '{arg}': {e}"))?);
+            }
+            "--" => {
+                args.remove(1);
+                break;
+            }
+            _ => break,
+        }
+        args.remove(1);
+    }
+    Ok(flags)
+}
+
+/// Removes a leading `port <name>` (as in `faucet port web sel`), which offers only the commands
+/// registered under that port, plan 9 plumber style
+pub fn take_port(args: &mut Vec<String>) -> Result<Option<String>> {
+    if args.get(1).is_none_or(|arg| arg != "port") {
+        return Ok(None);
+    }
+    if args.len() < 3 {
+        return Err(anyhow!("port requires a port name"));
+    }
+    args.remove(1);
+    Ok(Some(args.remove(1)))
+}
+
+/// Whether the config is `-`, read from stdin (once, so reloading sees the same) rather than a file
+pub fn is_stdin(config_path: &std::path::Path) -> bool {
+    config_path.as_os_str() == "-"
+}
+
+/// The descriptor a `/dev/fd/N` (or `/proc/self/fd/N`) path names, which is read like `--fd N`
+pub fn fd_path(path: &str) -> Option<u32> {
+    path.strip_prefix("/dev/fd/").or_else(|| path.strip_prefix("/proc/self/fd/"))?.parse().ok()
+}
+
+fn parse_threshold(flag: &str, value: &str) -> Result<i32> {
+    value
+        .parse()
+        .map_err(|e| anyhow!("Bad value '{value}' for '{flag}': {e}"))
+}
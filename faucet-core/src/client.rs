@@ -0,0 +1,93 @@
+//! `faucet client`: hands a plumb to a running `faucet serve --socket`, so a key binding skips
+//! faucet's startup (reading and checking the config, compiling its regexes). It never loads the
+//! config itself; the server plumbs as `faucet` would and the client waits until it is done.
+//!
+//! ```sh
+//! faucet client                      # the clipboard, read by the server
+//! faucet client sel                  # the primary selection
+//! faucet client file notes.txt
+//! echo https://example.com | faucet client
+//! faucet client --socket /tmp/faucet.sock some text
+//! ```
+
+use crate::protocol::{self, ClientMessage, VERSION};
+use anyhow::{Result, anyhow, bail};
+use base64::Engine;
+use serde::Deserialize;
+use std::io::{IsTerminal, Read};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+/// The server's replies a client acts on
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Welcome {
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
+    Plumbed,
+    Error {
+        message: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut args = args.to_vec();
+    let socket = match args.first().map(String::as_str) {
+        Some("--socket") => {
+            if args.len() < 2 {
+                bail!("Missing value for '--socket'");
+            }
+            args.remove(0);
+            PathBuf::from(args.remove(0))
+        }
+        _ => protocol::socket_path()?,
+    };
+    let (data, source, path) = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => match piped_stdin()? {
+            Some(bytes) => (Some(bytes), "stdin", None),
+            None => (None, "clipboard", None),
+        },
+        ["sel"] => (None, "selection", None),
+        ["file", path] => (Some(std::fs::read(path)?), "file", Some(std::path::absolute(path)?.display().to_string())),
+        words => (Some(words.join(" ").into_bytes()), "command line", None),
+    };
+
+    let mut stream = UnixStream::connect(&socket)
+        .map_err(|e| anyhow!("No faucet is serving on {} ({e}), start 'faucet serve --socket'", socket.display()))?;
+    let hello = ClientMessage::Hello { version: VERSION, capabilities: vec!["plumb".to_string()] };
+    protocol::write_frame(&mut stream, &hello)?;
+    match reply(&mut stream)? {
+        Reply::Welcome { capabilities } if capabilities.iter().any(|capability| capability == "plumb") => {}
+        Reply::Welcome { .. } => bail!("The faucet serving on {} can't plumb", socket.display()),
+        _ => bail!("Expected a welcome"),
+    }
+    let data = data.map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes));
+    protocol::write_frame(&mut stream, &ClientMessage::Plumb { id: 1, data, source: Some(source.to_string()), path })?;
+    match reply(&mut stream)? {
+        Reply::Plumbed => Ok(()),
+        _ => bail!("Expected the plumb to be done"),
+    }
+}
+
+/// The next reply, failing on an `error`
+fn reply(stream: &mut UnixStream) -> Result<Reply> {
+    let frame = protocol::read_frame(stream)?.ok_or_else(|| anyhow!("The server hung up"))?;
+    match serde_json::from_slice(&frame)? {
+        Reply::Error { message } => bail!("{message}"),
+        reply => Ok(reply),
+    }
+}
+
+/// What was piped in, if anything
+fn piped_stdin() -> Result<Option<Vec<u8>>> {
+    if std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    std::io::stdin().read_to_end(&mut bytes)?;
+    Ok(Some(bytes).filter(|bytes| !bytes.is_empty()))
+}
@@ -0,0 +1,202 @@
+//! Reading the clipboard and primary selection, behind `ClipboardProvider` so each display server
+//! (and later platform) brings its own: X11 is read natively, Wayland through wl-paste, and the
+//! Windows clipboard (faucet under WSL) through powershell.exe.
+
+use crate::{DisplayServer, Runner, SystemRunner};
+use anyhow::{Result, bail};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Selection {
+    Primary,
+    Clipboard,
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Selection::Primary => "primary selection",
+            Selection::Clipboard => "clipboard",
+        })
+    }
+}
+
+/// Image types a selection may offer, best first
+#[cfg(any(feature = "x11", feature = "wayland"))]
+const IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Text types a Wayland client may offer, for telling whether the clipboard has text beside an
+/// image
+#[cfg(feature = "wayland")]
+const TEXT_TYPES: [&str; 5] = ["text/plain;charset=utf-8", "text/plain", "UTF8_STRING", "STRING", "TEXT"];
+
+/// Whether an image in `selection` is taken over text offered beside it. Only the primary
+/// selection, which `sel` reads, does: what's copied to the clipboard with an image (a page's
+/// alt text or link, say) is usually what was meant to be plumbed.
+#[cfg(any(feature = "x11", feature = "wayland"))]
+fn prefers_images(selection: Selection) -> bool {
+    selection == Selection::Primary
+}
+
+pub trait ClipboardProvider {
+    /// The selection's contents and, when an image type was taken, its MIME type. Fails when
+    /// nothing holds the selection.
+    fn read(&self, selection: Selection) -> Result<(Vec<u8>, Option<String>)>;
+}
+
+pub fn provider(display_server: &DisplayServer) -> Result<Box<dyn ClipboardProvider>> {
+    with_runner(display_server, &SystemRunner)
+}
+
+/// A provider starting the programs it reads with (wl-paste) through `runner`
+fn with_runner<'a>(display_server: &DisplayServer, runner: &'a dyn Runner) -> Result<Box<dyn ClipboardProvider + 'a>> {
+    Ok(match display_server {
+        #[cfg(feature = "x11")]
+        DisplayServer::X11 => Box::new(X11::connect()?),
+        #[cfg(not(feature = "x11"))]
+        DisplayServer::X11 => bail!("This faucet was built without the 'x11' feature"),
+        #[cfg(feature = "wayland")]
+        DisplayServer::Wayland => Box::new(WlPaste(runner)),
+        #[cfg(not(feature = "wayland"))]
+        DisplayServer::Wayland => bail!("This faucet was built without the 'wayland' feature"),
+        DisplayServer::Windows => Box::new(Windows(runner)),
+    })
+}
+
+/// Reads the selection once, for callers that don't keep a provider around
+pub fn read(display_server: &DisplayServer, selection: Selection, runner: &dyn Runner) -> Result<(Vec<u8>, Option<String>)> {
+    with_runner(display_server, runner)?.read(selection)
+}
+
+#[cfg(feature = "x11")]
+struct X11 {
+    clipboard: x11_clipboard::Clipboard,
+}
+
+#[cfg(feature = "x11")]
+impl X11 {
+    /// How long the selection's owner gets to answer
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+    fn connect() -> Result<Self> {
+        let clipboard = x11_clipboard::Clipboard::new()
+            .map_err(|e| anyhow::anyhow!("Could not connect to the X server: {e}"))?;
+        Ok(Self { clipboard })
+    }
+}
+
+#[cfg(feature = "x11")]
+impl ClipboardProvider for X11 {
+    fn read(&self, selection: Selection) -> Result<(Vec<u8>, Option<String>)> {
+        use x11rb::protocol::xproto::ConnectionExt;
+        let getter = &self.clipboard.getter;
+        let atom = match selection {
+            Selection::Primary => getter.atoms.primary,
+            Selection::Clipboard => getter.atoms.clipboard,
+        };
+        if getter.connection.get_selection_owner(atom)?.reply()?.owner == x11rb::NONE {
+            bail!("Nothing is in the {selection}");
+        }
+        // A type the owner can't convert to comes back empty
+        let load = |target| self.clipboard.load(atom, target, getter.atoms.property, Self::TIMEOUT);
+        let image = || -> Result<Option<(Vec<u8>, Option<String>)>> {
+            for mime in IMAGE_TYPES {
+                let bytes = load(getter.get_atom(mime)?)?;
+                if !bytes.is_empty() {
+                    return Ok(Some((bytes, Some(mime.to_string()))));
+                }
+            }
+            Ok(None)
+        };
+        if prefers_images(selection)
+            && let Some(image) = image()?
+        {
+            return Ok(image);
+        }
+        let mut bytes = load(getter.atoms.utf8_string)?;
+        if bytes.is_empty() {
+            bytes = load(getter.atoms.string)?;
+        }
+        if bytes.is_empty()
+            && !prefers_images(selection)
+            && let Some(image) = image()?
+        {
+            return Ok(image);
+        }
+        Ok((bytes, None))
+    }
+}
+
+#[cfg(feature = "wayland")]
+struct WlPaste<'a>(&'a dyn Runner);
+
+#[cfg(feature = "wayland")]
+impl ClipboardProvider for WlPaste<'_> {
+    fn read(&self, selection: Selection) -> Result<(Vec<u8>, Option<String>)> {
+        let command = || {
+            let mut command = std::process::Command::new("wl-paste");
+            if selection == Selection::Primary {
+                command.arg("--primary");
+            }
+            command
+        };
+        let listing = self.0.output(command().arg("--list-types"))?;
+        if !listing.status.success() {
+            bail!("Nothing is in the {selection}");
+        }
+        let types = String::from_utf8_lossy(&listing.stdout).into_owned();
+        let offers = |mime: &str| types.lines().any(|offered| offered.trim() == mime);
+        let mime = IMAGE_TYPES
+            .into_iter()
+            .find(|mime| offers(mime))
+            .filter(|_| prefers_images(selection) || !TEXT_TYPES.into_iter().any(offers))
+            .map(str::to_string);
+        let mut get = command();
+        get.arg("--no-newline");
+        if let Some(mime) = &mime {
+            get.args(["-t", mime]);
+        }
+        let output = self.0.output(&mut get)?;
+        if !output.status.success() {
+            bail!("Reading the {selection} failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok((output.stdout, mime))
+    }
+}
+
+/// Whether faucet runs under WSL, where Windows programs like powershell.exe can be started
+pub fn is_wsl() -> bool {
+    std::path::Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+        || std::fs::read_to_string("/proc/sys/kernel/osrelease").is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+}
+
+/// Runs `script` in Windows PowerShell, skipping the user's profile
+pub fn powershell(script: &str) -> std::process::Command {
+    let mut command = std::process::Command::new("powershell.exe");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+    command
+}
+
+/// Windows has one clipboard, which stands in for the primary selection too. Only its text is
+/// read, with the CRLF line endings Windows apps write turned into LF.
+struct Windows<'a>(&'a dyn Runner);
+
+impl ClipboardProvider for Windows<'_> {
+    fn read(&self, selection: Selection) -> Result<(Vec<u8>, Option<String>)> {
+        if selection == Selection::Primary {
+            tracing::debug!("Windows has no primary selection, reading the clipboard");
+        }
+        let output = self.0.output(&mut powershell(
+            "[Console]::OutputEncoding = [Text.Encoding]::UTF8; Get-Clipboard -Raw",
+        ))?;
+        if !output.status.success() {
+            bail!("Reading the Windows clipboard failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let text = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+        // PowerShell ends what it prints with a newline of its own
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        if text.is_empty() {
+            bail!("Nothing is in the clipboard");
+        }
+        Ok((text.as_bytes().to_vec(), None))
+    }
+}
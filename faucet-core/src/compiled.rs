@@ -0,0 +1,86 @@
+//! The config as a plumb loads it (parsed, scores resolved, essentials checked), kept as JSON in
+//! `~/.cache/faucet/config/` so a plumb started on a keypress skips parsing the YAML or TOML and
+//! checking it again. An entry is keyed by the config's contents and this faucet's version and
+//! features: editing the config, or installing another faucet, simply misses.
+//!
+//! Only `Validation::Lazy` loads use it; `faucet validate` and the other subcommands check the
+//! config in full every time.
+
+use crate::Config;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The features that change what a config loads as
+const FEATURES: [(&str, bool); 8] = [
+    ("x11", cfg!(feature = "x11")),
+    ("wayland", cfg!(feature = "wayland")),
+    ("ocr", cfg!(feature = "ocr")),
+    ("gui", cfg!(feature = "gui")),
+    ("daemon", cfg!(feature = "daemon")),
+    ("lua", cfg!(feature = "lua")),
+    ("wasm", cfg!(feature = "wasm")),
+    ("wsl", cfg!(feature = "wsl")),
+];
+
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    key: u64,
+    config: &'a Config,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    key: u64,
+    config: Config,
+}
+
+/// The cached config for `content`, read from `config_path`, if one was stored for it
+pub fn load(config_path: &Path, content: &str) -> Option<Config> {
+    load_from(&dir()?, config_path, content)
+}
+
+/// Stores `config`, loaded from `content`, for the next plumb; failing to is only logged
+pub fn store(config_path: &Path, content: &str, config: &Config) {
+    let Some(dir) = dir() else {
+        return;
+    };
+    if let Err(e) = store_in(&dir, config_path, content, config) {
+        debug!("Failed to cache the config: {e}");
+    }
+}
+
+fn dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("faucet").join("config"))
+}
+
+fn key(content: &str) -> u64 {
+    crate::cache::fingerprint((content, env!("CARGO_PKG_VERSION"), FEATURES))
+}
+
+/// One entry per config file, replaced when the file changes
+fn entry_path(dir: &Path, config_path: &Path) -> PathBuf {
+    dir.join(format!("{:016x}.json", crate::cache::fingerprint(config_path)))
+}
+
+pub fn load_from(dir: &Path, config_path: &Path, content: &str) -> Option<Config> {
+    let bytes = std::fs::read(entry_path(dir, config_path)).ok()?;
+    let entry: Entry = serde_json::from_slice(&bytes)
+        .inspect_err(|e| debug!("Ignoring the cached config: {e}"))
+        .ok()?;
+    if entry.key != key(content) {
+        debug!("The cached config is stale");
+        return None;
+    }
+    debug!("Using the cached config for {}", config_path.display());
+    Some(entry.config)
+}
+
+pub fn store_in(dir: &Path, config_path: &Path, content: &str, config: &Config) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer(&mut file, &EntryRef { key: key(content), config })?;
+    // Renamed into place, so a plumb starting meanwhile never reads half an entry
+    file.persist(entry_path(dir, config_path))?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Facts about the user's situation (focused window, network, time, host, battery), gathered
+//! when a `command` scorer first needs them and exposed to it as `FAUCET_CTX_<PROVIDER>_<FIELD>`.
+//! A new kind of context is one more `ContextProvider` in `PROVIDERS`; scoring doesn't change.
+
+use crate::Runner;
+use std::process::{Command, Stdio};
+
+pub trait ContextProvider: Sync {
+    /// Upper-case name the provider's vars are prefixed with, e.g. `WINDOW`
+    fn name(&self) -> &'static str;
+    /// The provider's fields, e.g. `("CLASS", "firefox")`; empty when it can't tell
+    fn gather(&self, runner: &dyn Runner) -> Vec<(&'static str, String)>;
+}
+
+pub const PROVIDERS: &[&dyn ContextProvider] = &[&FocusedWindow, &Network, &Time, &Host, &Battery];
+
+/// Every provider's fields, as `FAUCET_CTX_*` env vars
+pub fn gather(runner: &dyn Runner) -> Vec<(String, String)> {
+    PROVIDERS
+        .iter()
+        .flat_map(|provider| {
+            provider
+                .gather(runner)
+                .into_iter()
+                .map(|(field, value)| (format!("FAUCET_CTX_{}_{field}", provider.name()), value))
+        })
+        .collect()
+}
+
+/// Trimmed stdout of a successful run of `program`
+fn output(runner: &dyn Runner, program: &str, args: &[&str]) -> Option<String> {
+    let output = runner.output(Command::new(program).args(args).stdin(Stdio::null()).stderr(Stdio::null())).ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn read(path: impl AsRef<std::path::Path>) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|content| content.trim().to_string())
+}
+
+/// `CLASS` and `TITLE` of the focused window, through xdotool on X11 or hyprctl on Hyprland
+struct FocusedWindow;
+
+impl ContextProvider for FocusedWindow {
+    fn name(&self) -> &'static str {
+        "WINDOW"
+    }
+
+    fn gather(&self, runner: &dyn Runner) -> Vec<(&'static str, String)> {
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            let Some(window) = output(runner, "hyprctl", &["activewindow"]) else {
+                return Vec::new();
+            };
+            let field = |name: &str| {
+                window
+                    .lines()
+                    .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(": "))
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            return vec![("CLASS", field("class")), ("TITLE", field("title"))];
+        }
+        let Some(class) = output(runner, "xdotool", &["getactivewindow", "getwindowclassname"]) else {
+            return Vec::new();
+        };
+        let title = output(runner, "xdotool", &["getactivewindow", "getwindowname"]).unwrap_or_default();
+        vec![("CLASS", class), ("TITLE", title)]
+    }
+}
+
+/// `ONLINE` (`1` when an interface besides loopback is up) and the up `INTERFACES`
+struct Network;
+
+impl ContextProvider for Network {
+    fn name(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    fn gather(&self, _runner: &dyn Runner) -> Vec<(&'static str, String)> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+            return Vec::new();
+        };
+        let mut interfaces: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != "lo")
+            .filter(|entry| read(entry.path().join("operstate")).is_some_and(|state| state == "up"))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        interfaces.sort();
+        vec![
+            ("ONLINE", if interfaces.is_empty() { "0" } else { "1" }.to_string()),
+            ("INTERFACES", interfaces.join(" ")),
+        ]
+    }
+}
+
+/// Local `HOUR` and `MINUTE` (zero-padded) and `WEEKDAY` (1 is Monday)
+struct Time;
+
+impl ContextProvider for Time {
+    fn name(&self) -> &'static str {
+        "TIME"
+    }
+
+    fn gather(&self, runner: &dyn Runner) -> Vec<(&'static str, String)> {
+        let Some(now) = output(runner, "date", &["+%H %M %u"]) else {
+            return Vec::new();
+        };
+        ["HOUR", "MINUTE", "WEEKDAY"]
+            .into_iter()
+            .zip(now.split(' ').map(str::to_string))
+            .collect()
+    }
+}
+
+/// The machine's `NAME`, so one config can behave differently per host
+struct Host;
+
+impl ContextProvider for Host {
+    fn name(&self) -> &'static str {
+        "HOST"
+    }
+
+    fn gather(&self, _runner: &dyn Runner) -> Vec<(&'static str, String)> {
+        read("/proc/sys/kernel/hostname")
+            .or_else(|| read("/etc/hostname"))
+            .map(|name| vec![("NAME", name)])
+            .unwrap_or_default()
+    }
+}
+
+/// `LEVEL` (percent) and `STATUS` (`Charging`, `Discharging`, `Full`, ...) of the first battery
+struct Battery;
+
+impl ContextProvider for Battery {
+    fn name(&self) -> &'static str {
+        "BATTERY"
+    }
+
+    fn gather(&self, _runner: &dyn Runner) -> Vec<(&'static str, String)> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+            return Vec::new();
+        };
+        let mut batteries: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| read(path.join("type")).is_some_and(|kind| kind == "Battery"))
+            .collect();
+        batteries.sort();
+        let Some(battery) = batteries.first() else {
+            return Vec::new();
+        };
+        vec![
+            ("LEVEL", read(battery.join("capacity")).unwrap_or_default()),
+            ("STATUS", read(battery.join("status")).unwrap_or_default()),
+        ]
+    }
+}
@@ -0,0 +1,45 @@
+//! `cooldown_secs`: an auto-selected command won't fire again for the same data until its
+//! cooldown has passed, so a double keypress doesn't download a link twice. Firings are kept in
+//! the runtime dir's `cooldowns` as `<data fingerprint> <expiry (unix secs)> <label>` lines, the
+//! label last as it may hold spaces.
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Records the firing and returns true, unless `label` already fired for this data within its
+/// cooldown. Check and record happen under a lock, so racing plumbs can't both fire.
+pub fn claim(label: &str, fingerprint: u64, cooldown_secs: u64) -> Result<bool> {
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(crate::runtime::dir()?.join("cooldowns"))?;
+    file.lock()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut entries: Vec<(String, u64, u64)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let (fingerprint, expiry) = (fields.next()?.parse().ok()?, fields.next()?.parse().ok()?);
+            Some((fields.next()?.to_string(), fingerprint, expiry))
+        })
+        .filter(|(_, _, expiry)| *expiry > now)
+        .collect();
+    let cooling = entries
+        .iter()
+        .any(|(entry_label, entry_fingerprint, _)| entry_label == label && *entry_fingerprint == fingerprint);
+    if !cooling {
+        entries.push((label.to_string(), fingerprint, now + cooldown_secs));
+    }
+    file.set_len(0)?;
+    file.rewind()?;
+    for (label, fingerprint, expiry) in entries {
+        writeln!(file, "{fingerprint} {expiry} {label}")?;
+    }
+    Ok(!cooling)
+}
@@ -0,0 +1,186 @@
+//! `faucet daemon`: stays resident and plumbs every new clipboard entry as `faucet --headless`
+//! would, so a command that auto-selects runs as soon as its data is copied. Clips are plumbed
+//! in-process, so probes answered for one clip are cached for the next, as under `serve`. A clip no command
+//! auto-selects for is left alone, or with `daemon_notify` its candidates are shown in a
+//! notification.
+//!
+//! On X11 the daemon waits for XFIXES selection events, elsewhere (or when the X server lacks
+//! XFIXES) it polls the clipboard. One clip is plumbed at a time; whatever is copied meanwhile,
+//! including by the command run, isn't plumbed. See `lifecycle` for the signals it handles:
+//! `SIGUSR2` gives up on the clip in hand, killing the probes it started.
+
+use crate::cache::ProbeCache;
+use crate::cancel::CancelToken;
+use crate::cli::Flags;
+use crate::clipboard::{ClipboardProvider, Selection};
+use crate::lifecycle::Lifecycle;
+use crate::{Config, Data, DisplayServer, i18n};
+use anyhow::{Result, bail};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Candidates listed in a notification
+const NOTIFIED_CANDIDATES: usize = 5;
+
+pub fn run(config_path: &Path, flags: &Flags, args: &[String]) -> Result<()> {
+    if let Some(arg) = args.first() {
+        bail!("Unknown daemon argument '{arg}'");
+    }
+    // Reloading reads the config again, which stdin can't give twice
+    if crate::cli::is_stdin(config_path) {
+        bail!("faucet daemon needs a config file, not --config -");
+    }
+    let lifecycle = Lifecycle::install("daemon", config_path.to_path_buf(), flags.clone())?;
+    let mut config = lifecycle.load_config()?;
+    i18n::init(config.options.locale.as_deref());
+    let mut cache = ProbeCache::new(config.options.probe_cache_size);
+    let mut provider = crate::clipboard::provider(&config.options.display_server)?;
+    let mut events = selection_events(&config.options.display_server);
+    info!(
+        "Plumbing new clips ({})",
+        if events.is_some() { "on XFIXES selection events" } else { "polling the clipboard" }
+    );
+    // What is on the clipboard already was copied before the daemon started
+    let mut last = fingerprint(provider.as_ref());
+    loop {
+        if lifecycle.take_reload() {
+            match lifecycle.load_config() {
+                Ok(reloaded) => {
+                    provider = crate::clipboard::provider(&reloaded.options.display_server)?;
+                    events = selection_events(&reloaded.options.display_server);
+                    cache = ProbeCache::new(reloaded.options.probe_cache_size);
+                    config = reloaded;
+                }
+                Err(e) => error!("Keeping the previous config: {e}"),
+            }
+        }
+        let changed = match &events {
+            Some(receiver) => match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(()) => {
+                    // An application taking the selection often does so more than once
+                    while receiver.try_recv().is_ok() {}
+                    true
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("Lost the X connection, polling the clipboard instead");
+                    events = None;
+                    true
+                }
+            },
+            None => {
+                std::thread::sleep(POLL_INTERVAL);
+                true
+            }
+        };
+        if !changed {
+            continue;
+        }
+        let Some(clip) = read(provider.as_ref()) else {
+            continue;
+        };
+        let seen = Some(crate::cache::fingerprint(&clip));
+        if seen == last {
+            continue;
+        }
+        let _busy = lifecycle.busy();
+        debug!("The clipboard changed, plumbing it");
+        let cancel = lifecycle.cancellable();
+        match plumb(&config, flags, &cache, &cancel, clip) {
+            Err(_) if cancel.is_cancelled() => info!("Cancelled plumbing the clip"),
+            Err(e) => error!("{e}"),
+            Ok(()) => {}
+        }
+        lifecycle.handled();
+        last = fingerprint(provider.as_ref());
+        if let Some(receiver) = &events {
+            while receiver.try_recv().is_ok() {}
+        }
+    }
+}
+
+/// Plumbs `clip` as `faucet --headless` would, notifying the candidates it leaves
+fn plumb(config: &Config, flags: &Flags, cache: &ProbeCache, cancel: &CancelToken, clip: Vec<u8>) -> Result<()> {
+    if crate::disabled_by_env() {
+        debug!("FAUCET_DISABLE is set, not plumbing anything");
+        return Ok(());
+    }
+    if !config.options.enabled {
+        debug!("Faucet is disabled in config, not plumbing anything");
+        return Ok(());
+    }
+    // A report runs nothing, so it needs none of the programs
+    if !flags.no_exec {
+        crate::validate_environment(config)?;
+    }
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    let flags = Flags { explain: false, json: false, headless: true, ..flags.clone() };
+    let data = Data::from_bytes(clip);
+    let Some(candidates) = crate::plumb_data(config, &flags, cache, cancel, data, "clipboard", None, temp_file_handle)? else {
+        return Ok(());
+    };
+    debug!("No command auto-selected, {} candidates", candidates.len());
+    if config.options.daemon_notify && !candidates.is_empty() {
+        let count = candidates.len().to_string();
+        let body = candidates
+            .iter()
+            .take(NOTIFIED_CANDIDATES)
+            .map(|candidate| candidate.display.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        crate::notify(&config.options, &i18n::tr("daemon_summary", &[("count", &count)]), &body)?;
+    }
+    Ok(())
+}
+
+/// The clip on the clipboard, when there is one
+fn read(provider: &dyn ClipboardProvider) -> Option<Vec<u8>> {
+    let (bytes, _) = provider.read(Selection::Clipboard).ok()?;
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+/// Tells clips apart without keeping them around
+fn fingerprint(provider: &dyn ClipboardProvider) -> Option<u64> {
+    read(provider).map(|clip| crate::cache::fingerprint(&clip))
+}
+
+/// Sends on every change of the clipboard's owner, when the X server can tell
+fn selection_events(display_server: &DisplayServer) -> Option<Receiver<()>> {
+    match display_server {
+        #[cfg(feature = "x11")]
+        DisplayServer::X11 => xfixes().inspect_err(|e| warn!("No XFIXES selection events: {e}")).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "x11")]
+fn xfixes() -> Result<Receiver<()>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    let (connection, screen) = x11rb::connect(None)?;
+    connection.xfixes_query_version(5, 0)?.reply()?;
+    let root = connection.setup().roots[screen].root;
+    let clipboard = connection.intern_atom(false, b"CLIPBOARD")?.reply()?.atom;
+    connection.xfixes_select_selection_input(
+        root,
+        clipboard,
+        SelectionEventMask::SET_SELECTION_OWNER
+            | SelectionEventMask::SELECTION_WINDOW_DESTROY
+            | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+    )?;
+    connection.flush()?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        while connection.wait_for_event().is_ok() {
+            if sender.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
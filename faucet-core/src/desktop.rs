@@ -0,0 +1,130 @@
+//! Faucet as a desktop entry's handler: file managers and browsers run `faucet handle %u` with a
+//! path or URI, and `faucet desktop-entry` writes the `faucet.desktop` they find it by.
+//!
+//! ```sh
+//! faucet desktop-entry --install --mime application/pdf --mime x-scheme-handler/magnet
+//! xdg-mime default faucet.desktop application/pdf
+//! ```
+
+use crate::Config;
+use anyhow::{Result, anyhow, bail};
+use itertools::Itertools;
+use std::path::Path;
+
+/// The input mode `faucet handle <target>` stands for: a path or `file://` URI is plumbed as
+/// `faucet file <path>`, any other URI (`https:`, `mailto:`, `magnet:`, ...) as text
+pub fn input(args: &[String]) -> Result<Vec<String>> {
+    let [target] = args else {
+        bail!("handle takes one path or URI, as a desktop entry's %f or %u passes it");
+    };
+    match scheme(target) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("file") => Ok(vec!["file".to_string(), file_uri_path(target)?]),
+        Some(_) => Ok(vec![target.clone()]),
+        None => Ok(vec!["file".to_string(), target.clone()]),
+    }
+}
+
+/// The scheme of a URI like `https://…` or `mailto:…`; a path has none
+fn scheme(target: &str) -> Option<&str> {
+    let (scheme, _) = target.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// The local path of `file:///path` or `file://localhost/path`, percent-decoded
+fn file_uri_path(uri: &str) -> Result<String> {
+    let rest = &uri["file:".len()..];
+    let path = match rest.strip_prefix("//") {
+        Some(authority_and_path) => {
+            let (host, path) = authority_and_path.split_at(authority_and_path.find('/').unwrap_or(authority_and_path.len()));
+            if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                bail!("'{uri}' is on another host ({host})");
+            }
+            path
+        }
+        None => rest,
+    };
+    if !path.starts_with('/') {
+        bail!("'{uri}' names no local path");
+    }
+    Ok(crate::detect::percent_decode(path, false))
+}
+
+/// `faucet desktop-entry [--install] [--mime <type>]...`: prints the entry, or installs it in
+/// `$XDG_DATA_HOME/applications`. Without `--mime` it lists the MIME types commands accept by name.
+pub fn run(config: &Config, config_flag: Option<&Path>, args: &[String]) -> Result<()> {
+    let mut install = false;
+    let mut mime_types = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--install" => install = true,
+            "--mime" => mime_types.push(args.next().cloned().ok_or_else(|| anyhow!("Missing value for '{arg}'"))?),
+            _ => bail!("Unknown desktop-entry argument '{arg}'"),
+        }
+    }
+    if mime_types.is_empty() {
+        mime_types = config
+            .commands
+            .values()
+            .flat_map(|command| &command.accepts)
+            .filter(|pattern| !pattern.contains('*'))
+            .cloned()
+            .collect();
+    }
+    let entry = entry(config_flag, &mime_types)?;
+    if !install {
+        print!("{entry}");
+        return Ok(());
+    }
+    let dir = dirs::data_dir().ok_or_else(|| anyhow!("Could not find data directory"))?.join("applications");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("faucet.desktop");
+    std::fs::write(&path, entry)?;
+    println!("Installed {}; make faucet the default with: xdg-mime default faucet.desktop <type>", path.display());
+    Ok(())
+}
+
+fn entry(config_flag: Option<&Path>, mime_types: &[String]) -> Result<String> {
+    let mut exec = vec![std::env::current_exe()?];
+    // A config given by flag has to be found again from wherever the launcher runs faucet
+    if let Some(config) = config_flag {
+        if crate::cli::is_stdin(config) {
+            anyhow::bail!("The launcher can't give faucet a config on stdin, so leave out --config -");
+        }
+        exec.extend(["--config".into(), std::path::absolute(config)?]);
+    }
+    let exec = exec.iter().map(|arg| quote(&arg.to_string_lossy())).join(" ");
+    let mut entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Faucet\n\
+         Comment=Offer the commands faucet has for a file or link\n\
+         Exec={exec} handle %u\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         Categories=Utility;\n"
+    );
+    // Without a MimeType the entry is still there for `xdg-mime default` to name
+    if !mime_types.is_empty() {
+        entry += &format!("MimeType={}\n", mime_types.iter().unique().map(|mime| format!("{mime};")).join(""));
+    }
+    Ok(entry)
+}
+
+/// `arg` as an `Exec` key argument: double-quoted when it has reserved characters, with `%`
+/// doubled and backslashes escaped again for the key's string value
+fn quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    let reserved = |c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c);
+    if !arg.contains(reserved) {
+        return arg;
+    }
+    let escaped: String = arg
+        .chars()
+        .map(|c| if matches!(c, '"' | '`' | '$' | '\\') { format!("\\{c}") } else { c.to_string() })
+        .collect();
+    format!("\"{}\"", escaped.replace('\\', "\\\\"))
+}
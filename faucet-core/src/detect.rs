@@ -0,0 +1,163 @@
+//! Built-in detectors for structured payloads. A detector that recognises the text (or, for
+//! binary detectors, the bytes) adds its name to `$DETECTED`, exposes the fields it parsed as
+//! env vars, and can be matched by a `detected: <name>` scorer.
+
+use crate::Runner;
+use itertools::Itertools;
+use Detector::{Probe, Pure};
+
+pub mod ansi;
+mod checksum;
+mod container;
+mod database;
+mod email;
+mod image;
+mod log;
+mod manual;
+pub mod math;
+mod media;
+mod otp;
+mod patch;
+mod quantity;
+mod remote;
+mod table;
+mod torrent;
+mod trace;
+mod unicode;
+mod url;
+mod vobject;
+mod word;
+
+pub struct Detection {
+    pub name: &'static str,
+    pub vars: Vec<(String, String)>,
+}
+
+type Fields = Option<Vec<(String, String)>>;
+
+/// Recognises text (or bytes); a `Probe` runs a program to decide, through the plumb's runner
+enum Detector<T: ?Sized> {
+    Pure(fn(&T) -> Fields),
+    Probe(fn(&T, &dyn Runner) -> Fields),
+}
+
+impl<T: ?Sized> Detector<T> {
+    fn detect(&self, input: &T, runner: &dyn Runner) -> Fields {
+        match self {
+            Pure(detector) => detector(input),
+            Probe(detector) => detector(input, runner),
+        }
+    }
+}
+
+const DETECTORS: &[(&str, Detector<str>)] = &[
+    ("email", Pure(email::detect_message)),
+    ("mailto", Pure(email::detect_mailto)),
+    ("ical", Pure(vobject::detect_calendar)),
+    ("vcard", Pure(vobject::detect_card)),
+    ("csv", Pure(table::detect)),
+    ("sql", Pure(database::detect_sql)),
+    ("db_uri", Pure(database::detect_connection)),
+    ("oci_image", Pure(container::detect_image)),
+    ("compose", Pure(container::detect_compose)),
+    ("kubernetes", Pure(container::detect_kubernetes)),
+    ("stacktrace", Pure(trace::detect)),
+    ("diff", Pure(patch::detect)),
+    ("log", Pure(log::detect)),
+    ("quantity", Pure(quantity::detect)),
+    ("math", Pure(math::detect)),
+    ("checksum", Pure(checksum::detect)),
+    ("otpauth", Pure(otp::detect)),
+    ("word", Pure(word::detect)),
+    ("emoji", Pure(unicode::detect_emoji)),
+    ("unicode", Pure(unicode::detect)),
+    ("ansi", Pure(ansi::detect)),
+    ("svg", Pure(image::detect_svg)),
+    ("media", Probe(media::detect_path)),
+    ("man_page", Pure(manual::detect)),
+    ("remote_path", Pure(remote::detect)),
+    ("url", Pure(url::detect)),
+    ("suspicious_url", Pure(url::detect_suspicious)),
+];
+
+const BINARY_DETECTORS: &[(&str, Detector<[u8]>)] = &[
+    ("animated_image", Pure(image::detect_animated)),
+    ("media", Probe(media::detect_bytes)),
+    ("torrent", Pure(torrent::detect)),
+];
+
+/// Prefixes of the env vars detectors expose (`EMAIL_SUBJECT`, `QUANTITY`, ...)
+const VAR_PREFIXES: &[&str] = &[
+    "EMAIL_", "MAILTO_", "ICAL_", "VCARD_", "CSV_", "SQL_", "DB_", "OCI_", "COMPOSE_", "K8S_", "TRACE_", "DIFF_",
+    "LOG_", "QUANTITY", "CHECKSUM_", "OTP_", "ANSI_", "IMAGE_", "MEDIA_", "TORRENT_", "URL_", "REMOTE_", "MAN_",
+    "WORD", "EMOJI", "UNICODE_", "MATH_",
+];
+
+/// Whether `name` (case-insensitive) is a variable some detector can expose
+pub fn is_var(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    VAR_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    DETECTORS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(BINARY_DETECTORS.iter().map(|(name, _)| *name))
+        .unique()
+}
+
+/// The value a detector gave `name`, for the detectors' tests
+#[cfg(test)]
+fn field(fields: &Fields, name: &str) -> String {
+    let fields = fields.as_ref().unwrap_or_else(|| panic!("nothing detected, expected {name}"));
+    fields
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_else(|| panic!("no {name} in {fields:?}"))
+}
+
+/// Runs every detector over `text`, skipping those already in `known`
+pub fn detect(text: &str, known: &[Detection], runner: &dyn Runner) -> Vec<Detection> {
+    DETECTORS
+        .iter()
+        .filter(|(name, _)| !known.iter().any(|detection| detection.name == *name))
+        .filter_map(|(name, detector)| detector.detect(text, runner).map(|vars| Detection { name, vars }))
+        .collect()
+}
+
+/// Runs every binary detector over `bytes`
+pub fn detect_binary(bytes: &[u8], runner: &dyn Runner) -> Vec<Detection> {
+    BINARY_DETECTORS
+        .iter()
+        .filter_map(|(name, detector)| detector.detect(bytes, runner).map(|vars| Detection { name, vars }))
+        .collect()
+}
+
+/// Decodes `%XX` escapes (and `+` as space when `plus_as_space`), keeping malformed escapes as-is
+pub fn percent_decode(text: &str, plus_as_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => match text
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+                None => decoded.push(b'%'),
+            },
+            b'+' if plus_as_space => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
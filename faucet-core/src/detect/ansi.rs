@@ -0,0 +1,51 @@
+//! Terminal escape sequences, as found in text copied out of a terminal or a colourised log.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// CSI (colours, cursor movement), OSC (titles, hyperlinks) and the remaining two-byte escapes
+static ESCAPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap()
+});
+
+/// A stray escape or two is noise; terminal output carries many
+const MIN_SEQUENCES: usize = 2;
+
+/// `HAS_ANSI` (`1`) and `ANSI_SEQUENCES` (a count)
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    if !text.contains('\x1b') {
+        return None;
+    }
+    let sequences = ESCAPE.find_iter(text).count();
+    (sequences >= MIN_SEQUENCES).then(|| {
+        vec![
+            ("HAS_ANSI".to_string(), "1".to_string()),
+            ("ANSI_SEQUENCES".to_string(), sequences.to_string()),
+        ]
+    })
+}
+
+pub fn strip(text: &str) -> String {
+    ESCAPE.replace_all(text, "").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn counts_escape_sequences_once_there_are_a_few() {
+        let fields = detect("\x1b[31mred\x1b[0m plain");
+        assert_eq!(field(&fields, "HAS_ANSI"), "1");
+        assert_eq!(field(&fields, "ANSI_SEQUENCES"), "2");
+        assert!(detect("\x1b[1m a lone escape").is_none());
+        assert!(detect("plain text").is_none());
+    }
+
+    #[test]
+    fn strip_removes_colours_and_hyperlinks() {
+        assert_eq!(strip("\x1b[1;32mok\x1b[0m done"), "ok done");
+        assert_eq!(strip("\x1b]8;;https://example.com\x07link\x1b]8;;\x07"), "link");
+    }
+}
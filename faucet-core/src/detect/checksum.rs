@@ -0,0 +1,82 @@
+//! Checksums: `sha256sum`-style `<hash>  <file>` lines, BSD `SHA256 (file) = <hash>`, or a bare hash.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static GNU: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([0-9a-fA-F]+) [ *](.+)$").unwrap());
+static BSD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(MD5|SHA1|SHA224|SHA256|SHA384|SHA512) ?\((.+)\) ?= ?([0-9a-fA-F]+)$").unwrap());
+
+/// `CHECKSUM_ALGORITHM` (`md5`, `sha1`, `sha224`, `sha256`, `sha384` or `sha512`), `CHECKSUM_HASH`
+/// (lower case) and `CHECKSUM_FILE` (empty for a bare hash). Only the first line is considered.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let line = text.trim().lines().next()?.trim();
+    let (algorithm, hash, file) = if let Some(captures) = BSD.captures(line) {
+        let algorithm = captures[1].to_ascii_lowercase();
+        let hash = captures[3].to_ascii_lowercase();
+        (algorithm_for(&hash).filter(|expected| *expected == algorithm)?, hash, captures[2].to_string())
+    } else if let Some(captures) = GNU.captures(line) {
+        let hash = captures[1].to_ascii_lowercase();
+        (algorithm_for(&hash)?, hash, captures[2].trim().to_string())
+    } else if line.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        let hash = line.to_ascii_lowercase();
+        // All-digit strings of these lengths are far more likely to be numbers
+        if hash.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        (algorithm_for(&hash)?, hash, String::new())
+    } else {
+        return None;
+    };
+    Some(vec![
+        ("CHECKSUM_ALGORITHM".to_string(), algorithm.to_string()),
+        ("CHECKSUM_HASH".to_string(), hash),
+        ("CHECKSUM_FILE".to_string(), file),
+    ])
+}
+
+/// Digest lengths are distinct, so the hex length identifies the algorithm
+fn algorithm_for(hash: &str) -> Option<&'static str> {
+    Some(match hash.len() {
+        32 => "md5",
+        40 => "sha1",
+        56 => "sha224",
+        64 => "sha256",
+        96 => "sha384",
+        128 => "sha512",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_gnu_and_bsd_lines() {
+        let gnu = detect("d41d8cd98f00b204e9800998ecf8427e  empty.txt");
+        assert_eq!(field(&gnu, "CHECKSUM_ALGORITHM"), "md5");
+        assert_eq!(field(&gnu, "CHECKSUM_FILE"), "empty.txt");
+        let bsd = detect("SHA1 (image.iso) = DA39A3EE5E6B4B0D3255BFEF95601890AFD80709");
+        assert_eq!(field(&bsd, "CHECKSUM_ALGORITHM"), "sha1");
+        assert_eq!(field(&bsd, "CHECKSUM_HASH"), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(field(&bsd, "CHECKSUM_FILE"), "image.iso");
+    }
+
+    #[test]
+    fn a_bare_hash_is_known_by_its_length() {
+        let fields = detect(&"ab".repeat(32));
+        assert_eq!(field(&fields, "CHECKSUM_ALGORITHM"), "sha256");
+        assert_eq!(field(&fields, "CHECKSUM_FILE"), "");
+        assert!(detect(&"ab".repeat(15)).is_none());
+    }
+
+    #[test]
+    fn rejects_mislabelled_hashes_and_numbers() {
+        // The length is SHA-1's, not SHA-256's
+        assert!(detect("SHA256 (a) = da39a3ee5e6b4b0d3255bfef95601890afd80709").is_none());
+        assert!(detect("12345678901234567890123456789012").is_none());
+        assert!(detect("not a checksum").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+//! Container image references and the manifests that use them (docker-compose, Kubernetes).
+
+use serde::Deserialize;
+use serde_yaml::Value;
+
+/// `OCI_REGISTRY` (`docker.io` when implicit), `OCI_REPOSITORY`, `OCI_TAG`, `OCI_DIGEST`, `OCI_IMAGE`
+/// (the normalised reference) and `OCI_WEB_URL`, the registry's page for the repository
+pub fn detect_image(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    let reference = ["docker pull ", "podman pull ", "docker run ", "FROM "]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix))
+        .unwrap_or(text)
+        .trim();
+    if reference.contains(char::is_whitespace) || reference.contains("://") {
+        return None;
+    }
+    let (name, digest) = reference.split_once('@').unwrap_or((reference, ""));
+    let (name, tag) = match name.rsplit_once(':') {
+        Some((name, tag)) if !tag.contains('/') => (name, tag),
+        _ => (name, ""),
+    };
+    let (registry, repository) = match name.split_once('/') {
+        Some((first, rest)) if first.contains(['.', ':']) || first == "localhost" => (first, rest.to_string()),
+        _ => ("docker.io", name.to_string()),
+    };
+    let explicit_registry = registry != "docker.io" || name.starts_with("docker.io/");
+    let valid_repository = !repository.is_empty()
+        && repository.split('/').all(|component| {
+            !component.is_empty()
+                && component
+                    .bytes()
+                    .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || b"._-".contains(&byte))
+        });
+    let valid_tag = tag.is_empty()
+        || (tag.len() <= 128 && tag.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"._-".contains(&byte)));
+    let valid_digest = digest.is_empty()
+        || digest
+            .split_once(':')
+            .is_some_and(|(algorithm, hex)| !algorithm.is_empty() && hex.len() >= 32 && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+    // A bare word like `nginx` is too ambiguous; require a registry, tag or digest
+    if !valid_repository || !valid_tag || !valid_digest || (!explicit_registry && tag.is_empty() && digest.is_empty()) {
+        return None;
+    }
+    // Without a registry `a:b` reads more like a time, host:port or key/value than an image
+    // unless the tag looks like a version
+    if !explicit_registry
+    && digest.is_empty()
+    && (repository.bytes().all(|byte| byte.is_ascii_digit())
+        || (!repository.contains('/') && repository.contains('.'))
+        || !(tag == "latest" || tag.bytes().any(|byte| byte.is_ascii_digit())))
+    {
+        return None;
+    }
+    let repository = if registry == "docker.io" && !repository.contains('/') {
+        format!("library/{repository}")
+    } else {
+        repository
+    };
+    let web_url = match registry {
+        "docker.io" => match repository.strip_prefix("library/") {
+            Some(official) => format!("https://hub.docker.com/_/{official}"),
+            None => format!("https://hub.docker.com/r/{repository}"),
+        },
+        "quay.io" => format!("https://quay.io/repository/{repository}"),
+        registry => format!("https://{registry}/{repository}"),
+    };
+    let mut image = format!("{registry}/{repository}");
+    if !tag.is_empty() {
+        image.push(':');
+        image.push_str(tag);
+    }
+    if !digest.is_empty() {
+        image.push('@');
+        image.push_str(digest);
+    }
+    Some(vec![
+        ("OCI_REGISTRY".to_string(), registry.to_string()),
+        ("OCI_REPOSITORY".to_string(), repository),
+        ("OCI_TAG".to_string(), tag.to_string()),
+        ("OCI_DIGEST".to_string(), digest.to_string()),
+        ("OCI_IMAGE".to_string(), image),
+        ("OCI_WEB_URL".to_string(), web_url),
+    ])
+}
+
+/// `COMPOSE_SERVICES` and `COMPOSE_IMAGES` (space separated) from a docker-compose file
+pub fn detect_compose(text: &str) -> Option<Vec<(String, String)>> {
+    if !text.lines().any(|line| line.starts_with("services:")) {
+        return None;
+    }
+    let document: Value = serde_yaml::from_str(text).ok()?;
+    let services = document.get("services")?.as_mapping()?;
+    if services.is_empty() || !services.values().all(|service| service.get("image").is_some() || service.get("build").is_some()) {
+        return None;
+    }
+    let names: Vec<&str> = services.keys().filter_map(Value::as_str).collect();
+    let images: Vec<&str> = services
+        .values()
+        .filter_map(|service| service.get("image").and_then(Value::as_str))
+        .collect();
+    Some(vec![
+        ("COMPOSE_SERVICES".to_string(), names.join(" ")),
+        ("COMPOSE_IMAGES".to_string(), images.join(" ")),
+    ])
+}
+
+/// `K8S_KINDS` (e.g. `Deployment Service`), `K8S_NAMES`, `K8S_NAMESPACE` (of the first object
+/// that sets one) and `K8S_IMAGES` from one or more `---` separated Kubernetes manifests
+pub fn detect_kubernetes(text: &str) -> Option<Vec<(String, String)>> {
+    if !text.lines().any(|line| line.starts_with("apiVersion:")) {
+        return None;
+    }
+    let mut kinds = Vec::new();
+    let mut names = Vec::new();
+    let mut namespace = None;
+    let mut images = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let Ok(document) = Value::deserialize(document) else {
+            return None;
+        };
+        if document.is_null() {
+            continue;
+        }
+        let (Some(_), Some(kind)) = (
+            document.get("apiVersion").and_then(Value::as_str),
+            document.get("kind").and_then(Value::as_str),
+        ) else {
+            return None;
+        };
+        kinds.push(kind.to_string());
+        let metadata = document.get("metadata");
+        if let Some(name) = metadata.and_then(|metadata| metadata.get("name")).and_then(Value::as_str) {
+            names.push(name.to_string());
+        }
+        if namespace.is_none() {
+            namespace = metadata
+                .and_then(|metadata| metadata.get("namespace"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+        }
+        collect_images(&document, &mut images);
+    }
+    if kinds.is_empty() {
+        return None;
+    }
+    Some(vec![
+        ("K8S_KINDS".to_string(), kinds.join(" ")),
+        ("K8S_NAMES".to_string(), names.join(" ")),
+        ("K8S_NAMESPACE".to_string(), namespace.unwrap_or_default()),
+        ("K8S_IMAGES".to_string(), images.join(" ")),
+    ])
+}
+
+/// Every `image:` under a `containers`/`initContainers` list, however deeply nested in the spec
+fn collect_images(value: &Value, images: &mut Vec<String>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                if matches!(key.as_str(), Some("containers" | "initContainers"))
+                && let Some(containers) = value.as_sequence()
+                {
+                    images.extend(
+                        containers
+                            .iter()
+                            .filter_map(|container| container.get("image").and_then(Value::as_str))
+                            .filter(|image| !images.iter().any(|known| known == image))
+                            .map(str::to_string)
+                            .collect::<Vec<_>>(),
+                    );
+                } else {
+                    collect_images(value, images);
+                }
+            }
+        }
+        Value::Sequence(sequence) => sequence.iter().for_each(|value| collect_images(value, images)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn normalises_image_references() {
+        let official = detect_image("nginx:1.25");
+        assert_eq!(field(&official, "OCI_REGISTRY"), "docker.io");
+        assert_eq!(field(&official, "OCI_IMAGE"), "docker.io/library/nginx:1.25");
+        assert_eq!(field(&official, "OCI_WEB_URL"), "https://hub.docker.com/_/nginx");
+        let pulled = detect_image("docker pull ghcr.io/owner/app:latest");
+        assert_eq!(field(&pulled, "OCI_REGISTRY"), "ghcr.io");
+        assert_eq!(field(&pulled, "OCI_REPOSITORY"), "owner/app");
+        assert_eq!(field(&pulled, "OCI_WEB_URL"), "https://ghcr.io/owner/app");
+        let digest = format!("sha256:{}", "0".repeat(64));
+        let pinned = detect_image(&format!("quay.io/prometheus/node-exporter@{digest}"));
+        assert_eq!(field(&pinned, "OCI_TAG"), "");
+        assert_eq!(field(&pinned, "OCI_DIGEST"), digest);
+    }
+
+    #[test]
+    fn rejects_what_only_looks_like_an_image() {
+        for text in ["nginx", "12:30", "example.com:443", "https://example.com/a:1", "two words:1"] {
+            assert!(detect_image(text).is_none(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn lists_compose_services_and_images() {
+        let fields = detect_compose("services:\n  web:\n    image: nginx:1.25\n  db:\n    build: .\n");
+        assert_eq!(field(&fields, "COMPOSE_SERVICES"), "web db");
+        assert_eq!(field(&fields, "COMPOSE_IMAGES"), "nginx:1.25");
+        assert!(detect_compose("services:\n  web:\n    ports: [80]\n").is_none());
+    }
+
+    #[test]
+    fn reads_every_kubernetes_document() {
+        let manifests = "apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  namespace: prod
+spec:
+  template:
+    spec:
+      initContainers:
+        - {name: migrate, image: 'app:2'}
+      containers:
+        - {name: web, image: 'nginx:1.25'}
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: web-svc
+";
+        let fields = detect_kubernetes(manifests);
+        assert_eq!(field(&fields, "K8S_KINDS"), "Deployment Service");
+        assert_eq!(field(&fields, "K8S_NAMES"), "web web-svc");
+        assert_eq!(field(&fields, "K8S_NAMESPACE"), "prod");
+        assert_eq!(field(&fields, "K8S_IMAGES"), "app:2 nginx:1.25");
+        assert!(detect_kubernetes("apiVersion: v1\nfoo: bar\n").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! RFC 822 messages (`.eml`, `mbox` excerpts) and `mailto:` URIs.
+
+use super::percent_decode;
+use base64::Engine;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static ENCODED_WORD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"=\?([^?\s]+)\?([BbQq])\?([^?\s]*)\?=").unwrap());
+static ATTACHMENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^content-disposition:\s*attachment").unwrap());
+
+/// `EMAIL_FROM`, `EMAIL_TO`, `EMAIL_SUBJECT`, `EMAIL_DATE` and `EMAIL_ATTACHMENTS` (a count)
+pub fn detect_message(text: &str) -> Option<Vec<(String, String)>> {
+    let mut text = text.trim_start();
+    // An mbox excerpt starts with an envelope line ahead of the headers
+    if text.starts_with("From ") {
+        text = text.split_once('\n')?.1;
+    }
+    let headers = headers(text)?;
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| decode_words(value))
+    };
+    let from = header("From")?;
+    if !["Subject", "Date", "To", "Message-ID"].iter().any(|name| header(name).is_some()) {
+        return None;
+    }
+    Some(vec![
+        ("EMAIL_FROM".to_string(), from),
+        ("EMAIL_TO".to_string(), header("To").unwrap_or_default()),
+        ("EMAIL_SUBJECT".to_string(), header("Subject").unwrap_or_default()),
+        ("EMAIL_DATE".to_string(), header("Date").unwrap_or_default()),
+        ("EMAIL_ATTACHMENTS".to_string(), ATTACHMENT.find_iter(text).count().to_string()),
+    ])
+}
+
+/// Unfolded header lines up to the first blank line; None if anything else comes first
+fn headers(text: &str) -> Option<Vec<(&str, String)>> {
+    let mut headers: Vec<(&str, String)> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            let (_, value) = headers.last_mut()?;
+            value.push(' ');
+            value.push_str(line.trim());
+            continue;
+        }
+        let (name, value) = line.split_once(':')?;
+        if name.is_empty() || !name.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-') {
+            return None;
+        }
+        headers.push((name, value.trim().to_string()));
+    }
+    (!headers.is_empty()).then_some(headers)
+}
+
+/// Decodes RFC 2047 encoded words (`=?UTF-8?B?...?=`), dropping the whitespace between adjacent ones
+fn decode_words(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut last_end = 0;
+    for captures in ENCODED_WORD.captures_iter(value) {
+        let whole = captures.get(0).unwrap();
+        let gap = &value[last_end..whole.start()];
+        if last_end == 0 || !gap.trim().is_empty() {
+            decoded.push_str(gap);
+        }
+        let bytes = match &captures[2] {
+            "B" | "b" => base64::engine::general_purpose::STANDARD
+                .decode(&captures[3])
+                .unwrap_or_else(|_| captures[3].as_bytes().to_vec()),
+            _ => percent_decode(&captures[3].replace('_', " ").replace('=', "%"), false).into_bytes(),
+        };
+        let charset = captures[1].to_ascii_lowercase();
+        if charset == "iso-8859-1" || charset == "latin1" {
+            decoded.extend(bytes.iter().map(|&byte| byte as char));
+        } else {
+            decoded.push_str(&String::from_utf8_lossy(&bytes));
+        }
+        last_end = whole.end();
+    }
+    decoded.push_str(&value[last_end..]);
+    decoded
+}
+
+/// `MAILTO_TO`, plus `MAILTO_CC`, `MAILTO_BCC`, `MAILTO_SUBJECT` and `MAILTO_BODY` when given
+pub fn detect_mailto(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    let scheme = text.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("mailto:") || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let (to, query) = text[7..].split_once('?').unwrap_or((&text[7..], ""));
+    let mut recipients: Vec<String> = Some(percent_decode(to, false)).filter(|to| !to.is_empty()).into_iter().collect();
+    let mut vars = Vec::new();
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let value = percent_decode(value, false);
+        match key.to_ascii_lowercase().as_str() {
+            "to" => recipients.push(value),
+            key @ ("cc" | "bcc" | "subject" | "body") => {
+                vars.push((format!("MAILTO_{}", key.to_ascii_uppercase()), value));
+            }
+            _ => {}
+        }
+    }
+    vars.insert(0, ("MAILTO_TO".to_string(), recipients.join(", ")));
+    Some(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_headers_decoding_encoded_words() {
+        let message = "From: Alice <alice@example.com>
+To: bob@example.com
+Subject: =?UTF-8?B?SGVsbG8=?= world,
+ continued
+Date: Wed, 1 May 2024 10:00:00 +0000
+Content-Type: multipart/mixed
+
+Content-Disposition: attachment; filename=a.pdf
+";
+        let fields = detect_message(message);
+        assert_eq!(field(&fields, "EMAIL_FROM"), "Alice <alice@example.com>");
+        assert_eq!(field(&fields, "EMAIL_TO"), "bob@example.com");
+        assert_eq!(field(&fields, "EMAIL_SUBJECT"), "Hello world, continued");
+        assert_eq!(field(&fields, "EMAIL_ATTACHMENTS"), "1");
+        let quoted = detect_message("From: a@example.com\nSubject: =?UTF-8?Q?Caf=C3=A9_au_lait?=\n\n");
+        assert_eq!(field(&quoted, "EMAIL_SUBJECT"), "Café au lait");
+    }
+
+    #[test]
+    fn skips_an_mbox_envelope_line() {
+        let fields = detect_message("From alice@example.com Wed May  1 10:00:00 2024\nFrom: alice@example.com\nSubject: x\n\nbody");
+        assert_eq!(field(&fields, "EMAIL_FROM"), "alice@example.com");
+    }
+
+    #[test]
+    fn rejects_text_that_only_starts_like_mail() {
+        assert!(detect_message("From: someone\n\nbody").is_none());
+        assert!(detect_message("Dear Bob,\nFrom: me").is_none());
+    }
+
+    #[test]
+    fn reads_mailto_recipients_and_fields() {
+        let fields = detect_mailto("mailto:alice@example.com?subject=Hi%20there&cc=bob@example.com&to=carol@example.com");
+        assert_eq!(field(&fields, "MAILTO_TO"), "alice@example.com, carol@example.com");
+        assert_eq!(field(&fields, "MAILTO_SUBJECT"), "Hi there");
+        assert_eq!(field(&fields, "MAILTO_CC"), "bob@example.com");
+        assert!(detect_mailto("mailto: alice").is_none());
+        assert!(detect_mailto("alice@example.com").is_none());
+    }
+}
@@ -0,0 +1,152 @@
+//! Images that a still-image viewer handles badly: SVG (vector, often scripted or animated) and
+//! animated GIF, APNG and WebP.
+
+/// `IMAGE_FORMAT` (`svg`) and `IMAGE_ANIMATED` (`1` when it uses SMIL animation)
+pub fn detect_svg(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim_start();
+    let prologue = text.starts_with("<?xml") || text.starts_with("<!DOCTYPE svg") || text.starts_with("<!--");
+    let head = &text[..text.floor_char_boundary(1024)];
+    let is_svg = text.starts_with("<svg") || prologue && head.contains("<svg");
+    if !is_svg {
+        return None;
+    }
+    // Also covers <animateTransform> and <animateMotion>
+    let animated = ["<animate", "<set "].iter().any(|tag| text.contains(tag));
+    Some(vec![
+        ("IMAGE_FORMAT".to_string(), "svg".to_string()),
+        ("IMAGE_ANIMATED".to_string(), if animated { "1" } else { "0" }.to_string()),
+    ])
+}
+
+/// `IMAGE_FORMAT` (`gif`, `png` or `webp`) and `IMAGE_FRAMES`, for images with more than one frame
+pub fn detect_animated(bytes: &[u8]) -> Option<Vec<(String, String)>> {
+    let (format, frames) = if bytes.starts_with(b"GIF8") {
+        ("gif", gif_frames(bytes)?)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("png", apng_frames(bytes)?)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        ("webp", webp_frames(bytes)?)
+    } else {
+        return None;
+    };
+    (frames > 1).then(|| {
+        vec![
+            ("IMAGE_FORMAT".to_string(), format.to_string()),
+            ("IMAGE_FRAMES".to_string(), frames.to_string()),
+        ]
+    })
+}
+
+/// Counts image descriptors, walking the block structure so pixel data can't be mistaken for one
+fn gif_frames(bytes: &[u8]) -> Option<usize> {
+    let color_table = |packed: u8| if packed & 0x80 != 0 { 3 << ((packed & 0x07) + 1) } else { 0 };
+    let skip_sub_blocks = |mut at: usize| -> Option<usize> {
+        loop {
+            let len = *bytes.get(at)? as usize;
+            at += 1 + len;
+            if len == 0 {
+                return Some(at);
+            }
+        }
+    };
+    let mut at = 13 + color_table(*bytes.get(10)?);
+    let mut frames = 0;
+    while let Some(&block) = bytes.get(at) {
+        match block {
+            // Extension: label, then sub-blocks
+            0x21 => at = skip_sub_blocks(at + 2)?,
+            // Image descriptor, optional local colour table, LZW code size, then sub-blocks
+            0x2c => {
+                frames += 1;
+                at = skip_sub_blocks(at + 10 + color_table(*bytes.get(at + 9)?) + 1)?;
+            }
+            _ => break,
+        }
+    }
+    Some(frames)
+}
+
+/// An APNG announces its frame count in an `acTL` chunk before the first `IDAT`
+fn apng_frames(bytes: &[u8]) -> Option<usize> {
+    let mut at = 8;
+    while at + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[at..at + 4].try_into().ok()?) as usize;
+        match &bytes[at + 4..at + 8] {
+            b"acTL" => return Some(u32::from_be_bytes(bytes.get(at + 8..at + 12)?.try_into().ok()?) as usize),
+            b"IDAT" => return Some(1),
+            _ => at += 12 + len,
+        }
+    }
+    Some(1)
+}
+
+/// Animated WebP sets the animation flag in `VP8X` and stores one `ANMF` chunk per frame
+fn webp_frames(bytes: &[u8]) -> Option<usize> {
+    let mut at = 12;
+    let mut animated = false;
+    let mut frames = 0;
+    while at + 8 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[at + 4..at + 8].try_into().ok()?) as usize;
+        match &bytes[at..at + 4] {
+            b"VP8X" => animated = bytes.get(at + 8).is_some_and(|flags| flags & 0x02 != 0),
+            b"ANMF" => frames += 1,
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        at += 8 + len + (len & 1);
+    }
+    Some(if animated { frames.max(2) } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    /// One image descriptor with no colour table and a single data sub-block
+    const GIF_FRAME: [u8; 14] = [0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 1, 0, 0];
+
+    fn gif(frames: usize) -> Vec<u8> {
+        let mut bytes = b"GIF89a\x01\x00\x01\x00\x00\x00\x00".to_vec();
+        for _ in 0..frames {
+            bytes.extend(GIF_FRAME);
+        }
+        bytes.push(0x3b);
+        bytes
+    }
+
+    #[test]
+    fn recognises_svg_and_its_animation() {
+        let still = detect_svg("<svg xmlns='http://www.w3.org/2000/svg'><circle r='1'/></svg>");
+        assert_eq!(field(&still, "IMAGE_FORMAT"), "svg");
+        assert_eq!(field(&still, "IMAGE_ANIMATED"), "0");
+        let animated = detect_svg("<?xml version='1.0'?>\n<svg><animate attributeName='r'/></svg>");
+        assert_eq!(field(&animated, "IMAGE_ANIMATED"), "1");
+        assert!(detect_svg("<html><svg></svg></html>").is_none());
+    }
+
+    #[test]
+    fn counts_gif_frames() {
+        let fields = detect_animated(&gif(3));
+        assert_eq!(field(&fields, "IMAGE_FORMAT"), "gif");
+        assert_eq!(field(&fields, "IMAGE_FRAMES"), "3");
+        assert!(detect_animated(&gif(1)).is_none());
+    }
+
+    #[test]
+    fn reads_apng_and_webp_frame_counts() {
+        let mut apng = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x08acTL".to_vec();
+        apng.extend([0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(field(&detect_animated(&apng), "IMAGE_FRAMES"), "4");
+        let still = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x00IDAT\x00\x00\x00\x00";
+        assert!(detect_animated(still).is_none());
+
+        let mut webp = b"RIFF\x00\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00\x02".to_vec();
+        webp.extend([0; 9]);
+        webp.extend(b"ANMF\x00\x00\x00\x00ANMF\x00\x00\x00\x00");
+        let fields = detect_animated(&webp);
+        assert_eq!(field(&fields, "IMAGE_FORMAT"), "webp");
+        assert_eq!(field(&fields, "IMAGE_FRAMES"), "2");
+        assert!(detect_animated(b"not an image").is_none());
+    }
+}
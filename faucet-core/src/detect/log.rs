@@ -0,0 +1,201 @@
+//! Log excerpts: syslog, JSON lines, and ISO 8601 timestamp + level formats.
+
+use regex::Regex;
+use serde_yaml::Value;
+use std::sync::LazyLock;
+
+static SYSLOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec) +(\d{1,2}) (\d\d:\d\d:\d\d) (\S+) ([^\s:\[]+)(?:\[\d+\])?: ").unwrap()
+});
+static ISO: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\[?(\d{4}-\d\d-\d\d)[T ](\d\d:\d\d:\d\d)(?:[.,]\d+)?(?:Z|[+-]\d\d:?\d\d)?\]?\s+\[?\s*(trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit)\b").unwrap()
+});
+static LEVEL_WORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit|emerg|alert|panic)\b").unwrap()
+});
+
+const SAMPLE_LINES: usize = 50;
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+struct Entry {
+    /// `YYYY-MM-DD HH:MM:SS`, the form `journalctl --since` takes
+    time: Option<String>,
+    level: Option<String>,
+    host: Option<String>,
+    process: Option<String>,
+}
+
+/// `LOG_FORMAT` (`syslog`, `json` or `iso`), `LOG_LINES`, `LOG_START` and `LOG_END` (first and last
+/// timestamps as `YYYY-MM-DD HH:MM:SS`), `LOG_LEVEL` (the most severe level present), and
+/// `LOG_HOST`/`LOG_PROCESS` of the first syslog line
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    let sample = &lines[..lines.len().min(SAMPLE_LINES)];
+    let (format, parse): (&str, fn(&str) -> Option<Entry>) = [
+        ("syslog", syslog as fn(&str) -> Option<Entry>),
+        ("json", json),
+        ("iso", iso),
+    ]
+    .into_iter()
+    .find(|(_, parse)| {
+        let parsed = sample.iter().filter(|line| parse(line).is_some()).count();
+        // Continuation lines (stack traces, wrapped messages) are allowed between entries
+        parsed > 0 && parsed * 5 >= sample.len() * 3
+    })?;
+    let entries: Vec<Entry> = lines.iter().filter_map(|line| parse(line)).collect();
+    let level = entries
+        .iter()
+        .filter_map(|entry| entry.level.as_deref())
+        .max_by_key(|level| severity(level))
+        .unwrap_or_default();
+    let first = entries.first()?;
+    Some(vec![
+        ("LOG_FORMAT".to_string(), format.to_string()),
+        ("LOG_LINES".to_string(), lines.len().to_string()),
+        ("LOG_START".to_string(), entries.iter().find_map(|entry| entry.time.clone()).unwrap_or_default()),
+        ("LOG_END".to_string(), entries.iter().rev().find_map(|entry| entry.time.clone()).unwrap_or_default()),
+        ("LOG_LEVEL".to_string(), level.to_string()),
+        ("LOG_HOST".to_string(), first.host.clone().unwrap_or_default()),
+        ("LOG_PROCESS".to_string(), first.process.clone().unwrap_or_default()),
+    ])
+}
+
+fn syslog(line: &str) -> Option<Entry> {
+    let captures = SYSLOG.captures(line)?;
+    let month = MONTHS.iter().position(|month| *month == &captures[1])? + 1;
+    let day: u32 = captures[2].parse().ok()?;
+    let message = &line[captures.get(0)?.end()..];
+    Some(Entry {
+        // Syslog omits the year; assume the current one
+        time: Some(format!("{}-{month:02}-{day:02} {}", current_year(), &captures[3])),
+        level: LEVEL_WORD.find(message).map(|level| normalise_level(level.as_str())),
+        host: Some(captures[4].to_string()),
+        process: Some(captures[5].to_string()),
+    })
+}
+
+fn json(line: &str) -> Option<Entry> {
+    let line = line.trim();
+    if !line.starts_with('{') || !line.ends_with('}') {
+        return None;
+    }
+    let Value::Mapping(object) = serde_yaml::from_str(line).ok()? else {
+        return None;
+    };
+    let field = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| object.get(*key))
+            .and_then(|value| match value {
+                Value::String(value) => Some(value.clone()),
+                Value::Number(value) => Some(value.to_string()),
+                _ => None,
+            })
+    };
+    let time = field(&["time", "timestamp", "ts", "@timestamp", "date"]);
+    let level = field(&["level", "severity", "lvl", "log.level", "levelname"]);
+    if time.is_none() && level.is_none() {
+        return None;
+    }
+    Some(Entry {
+        time: time.and_then(|time| iso_time(&time)),
+        level: level.map(|level| normalise_level(&level)),
+        host: field(&["host", "hostname"]),
+        process: field(&["service", "logger", "name", "target"]),
+    })
+}
+
+fn iso(line: &str) -> Option<Entry> {
+    let captures = ISO.captures(line)?;
+    Some(Entry {
+        time: Some(format!("{} {}", &captures[1], &captures[2])),
+        level: Some(normalise_level(&captures[3])),
+        host: None,
+        process: None,
+    })
+}
+
+/// `2024-05-01T10:00:00.123Z` -> `2024-05-01 10:00:00`; numeric Unix times are left out
+fn iso_time(time: &str) -> Option<String> {
+    let date = time.get(..10)?;
+    let clock = time.get(11..19)?;
+    let valid = date.bytes().enumerate().all(|(i, byte)| if i == 4 || i == 7 { byte == b'-' } else { byte.is_ascii_digit() })
+        && matches!(time.as_bytes()[10], b'T' | b' ');
+    valid.then(|| format!("{date} {clock}"))
+}
+
+fn normalise_level(level: &str) -> String {
+    match level.to_ascii_lowercase().as_str() {
+        "warning" => "warn".to_string(),
+        "err" => "error".to_string(),
+        "crit" | "critical" | "emerg" | "alert" | "panic" => "fatal".to_string(),
+        level => level.to_string(),
+    }
+}
+
+fn severity(level: &str) -> u8 {
+    match level {
+        "trace" => 0,
+        "debug" => 1,
+        "info" | "notice" => 2,
+        "warn" => 3,
+        "error" => 4,
+        "fatal" => 5,
+        _ => 0,
+    }
+}
+
+/// The current year in UTC, close enough for dating syslog lines
+fn current_year() -> i64 {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+    // Civil-from-days (Howard Hinnant), just far enough to get the year
+    let era_days = days + 719_468;
+    let era = era_days.div_euclid(146_097);
+    let day_of_era = era_days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    year_of_era + era * 400 + i64::from(month_index >= 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_syslog_lines() {
+        let fields = detect(
+            "May  1 10:00:00 box sshd[123]: Accepted publickey for me\nMay  1 10:00:05 box sshd[123]: error: connection reset\n",
+        );
+        assert_eq!(field(&fields, "LOG_FORMAT"), "syslog");
+        assert_eq!(field(&fields, "LOG_LINES"), "2");
+        assert!(field(&fields, "LOG_START").ends_with("-05-01 10:00:00"));
+        assert!(field(&fields, "LOG_END").ends_with("-05-01 10:00:05"));
+        assert_eq!(field(&fields, "LOG_LEVEL"), "error");
+        assert_eq!(field(&fields, "LOG_HOST"), "box");
+        assert_eq!(field(&fields, "LOG_PROCESS"), "sshd");
+    }
+
+    #[test]
+    fn reads_json_lines() {
+        let fields = detect(
+            r#"{"time":"2024-05-01T10:00:00.123Z","level":"info","msg":"up"}
+{"time":"2024-05-01T10:01:00Z","level":"WARNING","msg":"slow","service":"api"}"#,
+        );
+        assert_eq!(field(&fields, "LOG_FORMAT"), "json");
+        assert_eq!(field(&fields, "LOG_START"), "2024-05-01 10:00:00");
+        assert_eq!(field(&fields, "LOG_END"), "2024-05-01 10:01:00");
+        assert_eq!(field(&fields, "LOG_LEVEL"), "warn");
+    }
+
+    #[test]
+    fn iso_entries_may_have_continuation_lines() {
+        let fields = detect("2024-05-01 10:00:00,123 ERROR something broke\n  at frame\n2024-05-01 10:00:02 INFO recovered\n");
+        assert_eq!(field(&fields, "LOG_FORMAT"), "iso");
+        assert_eq!(field(&fields, "LOG_LINES"), "3");
+        assert_eq!(field(&fields, "LOG_LEVEL"), "error");
+        assert!(detect("just some\nordinary prose\n").is_none());
+    }
+}
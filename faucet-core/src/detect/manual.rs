@@ -0,0 +1,75 @@
+//! Manual page references: `printf(3)`, `man 5 crontab`, and `--help` snippets like
+//! `tar --help` or `git commit -h`.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[\s`'(])([A-Za-z_][\w.:+-]*)\(([1-9][a-z]*|n)\)").unwrap());
+static MAN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\$?\s*man\s+(?:-s\s*)?(?:([1-9][a-z]*|n)\s+)?([A-Za-z_][\w.:+-]*)$").unwrap());
+static HELP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\$?\s*([A-Za-z_][\w.+-]*)(?:\s+([a-z][\w-]*))?\s+(?:--help|-h)$").unwrap()
+});
+
+/// `MAN_PAGE`, `MAN_SECTION` (empty unless given) and, for a `--help` snippet, `MAN_SUBCOMMAND`
+/// (`commit` in `git commit --help`, else empty) with `MAN_HELP` set to 1 (else 0). Only a short
+/// single line is considered, as a reference copied out of running text.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let line = text.trim();
+    if line.is_empty() || line.contains('\n') || line.chars().count() > 120 {
+        return None;
+    }
+    let (page, section, subcommand, help) = if let Some(captures) = MAN.captures(line) {
+        (captures[2].to_string(), captures.get(1).map(|section| section.as_str()), "", false)
+    } else if let Some(captures) = HELP.captures(line) {
+        let subcommand = captures.get(2).map_or("", |subcommand| subcommand.as_str());
+        (captures[1].to_string(), None, subcommand, true)
+    } else {
+        // `len(3) + 2` is code, not a reference
+        if line.contains(['=', ';', '{', '}', '+', '*']) {
+            return None;
+        }
+        let captures = REFERENCE.captures(line)?;
+        (captures[1].to_string(), Some(captures.get(2)?.as_str()), "", false)
+    };
+    Some(vec![
+        ("MAN_PAGE".to_string(), page),
+        ("MAN_SECTION".to_string(), section.unwrap_or_default().to_string()),
+        ("MAN_SUBCOMMAND".to_string(), subcommand.to_string()),
+        ("MAN_HELP".to_string(), if help { "1" } else { "0" }.to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_references_and_man_commands() {
+        let reference = detect("see printf(3)");
+        assert_eq!(field(&reference, "MAN_PAGE"), "printf");
+        assert_eq!(field(&reference, "MAN_SECTION"), "3");
+        assert_eq!(field(&reference, "MAN_HELP"), "0");
+        let man = detect("$ man 5 crontab");
+        assert_eq!(field(&man, "MAN_PAGE"), "crontab");
+        assert_eq!(field(&man, "MAN_SECTION"), "5");
+    }
+
+    #[test]
+    fn help_snippets_keep_their_subcommand() {
+        let fields = detect("git commit --help");
+        assert_eq!(field(&fields, "MAN_PAGE"), "git");
+        assert_eq!(field(&fields, "MAN_SECTION"), "");
+        assert_eq!(field(&fields, "MAN_SUBCOMMAND"), "commit");
+        assert_eq!(field(&fields, "MAN_HELP"), "1");
+    }
+
+    #[test]
+    fn rejects_code_and_prose() {
+        for text in ["len(3) + 2", "x = f(1);", "hello world", "printf(3)\nputs(3)"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Arithmetic like `(3 + 4) * 2`, `2^10`, `sqrt(2) / 3` or `1.5e3 × 4 =`, evaluated in process.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+type Function = fn(f64) -> f64;
+
+/// Names a plumbed expression may call
+const FUNCTIONS: &[(&str, Function)] = &[
+    ("sqrt", f64::sqrt),
+    ("cbrt", f64::cbrt),
+    ("abs", f64::abs),
+    ("ln", f64::ln),
+    ("log", f64::log10),
+    ("log2", f64::log2),
+    ("exp", f64::exp),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("floor", f64::floor),
+    ("ceil", f64::ceil),
+    ("round", f64::round),
+];
+
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("π", std::f64::consts::PI),
+    ("tau", std::f64::consts::TAU),
+    ("e", std::f64::consts::E),
+];
+
+/// `MATH_EXPRESSION` (without a trailing `=`) and `MATH_RESULT`. Needs a number and an operator
+/// or function; digits joined only by `-` (`2024-05-01`, `555-1234`) or by two or more `/`
+/// (`1/2/2024`) are dates and phone numbers.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let expression = text.trim().trim_end_matches('=').trim_end();
+    if expression.is_empty() || expression.contains('\n') || expression.len() > 200 {
+        return None;
+    }
+    if !expression.contains(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let only = |separator: char| expression.chars().all(|c| c.is_ascii_digit() || c == separator);
+    if only('-') || only('/') && expression.matches('/').count() > 1 {
+        return None;
+    }
+    let (value, operations) = evaluate(expression)?;
+    if operations == 0 {
+        return None;
+    }
+    Some(vec![
+        ("MATH_EXPRESSION".to_string(), expression.to_string()),
+        ("MATH_RESULT".to_string(), format(value)),
+    ])
+}
+
+/// The value of `expression` as `MATH_RESULT` has it, if it is one and comes out finite
+pub fn eval(expression: &str) -> Option<String> {
+    evaluate(expression.trim().trim_end_matches('=')).map(|(value, _)| format(value))
+}
+
+/// The value and how many operators and function calls it took
+fn evaluate(expression: &str) -> Option<(f64, usize)> {
+    let mut parser = Parser { chars: expression.chars().peekable(), operations: 0 };
+    let value = parser.sum()?;
+    parser.skip_whitespace();
+    (parser.chars.peek().is_none() && value.is_finite()).then_some((value, parser.operations))
+}
+
+/// Whole numbers as such, else up to 10 decimals, or scientific notation when very large or small
+fn format(value: f64) -> String {
+    let trim = |text: String| text.trim_end_matches('0').trim_end_matches('.').to_string();
+    if value == 0.0 {
+        "0".to_string()
+    } else if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.0}")
+    } else if value.abs() >= 1e15 || value.abs() < 1e-6 {
+        let scientific = format!("{value:.10e}");
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+        format!("{}e{exponent}", trim(mantissa.to_string()))
+    } else {
+        trim(format!("{value:.10}"))
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    operations: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn sum(&mut self) -> Option<f64> {
+        let mut value = self.product()?;
+        loop {
+            self.skip_whitespace();
+            let op = self.chars.next_if(|c| matches!(c, '+' | '-' | '−'));
+            let Some(op) = op else {
+                return Some(value);
+            };
+            self.operations += 1;
+            let rhs = self.product()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+    }
+
+    fn product(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            let op = self.chars.next_if(|c| matches!(c, '*' | '×' | '·' | '/' | '÷' | '%'));
+            let Some(op) = op else {
+                return Some(value);
+            };
+            self.operations += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '/' | '÷' => value / rhs,
+                '%' => value % rhs,
+                _ => value * rhs,
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+        match self.chars.next_if(|c| matches!(c, '+' | '-' | '−')) {
+            Some('+') => self.unary(),
+            Some(_) => self.unary().map(|value| -value),
+            None => self.power(),
+        }
+    }
+
+    /// `^` or `**`, right-associative and binding tighter than a leading minus: `-2^2` is -4
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        let is_power = lookahead.next_if_eq(&'^').is_some()
+            || lookahead.next_if_eq(&'*').is_some() && lookahead.next_if_eq(&'*').is_some();
+        if !is_power {
+            return Some(base);
+        }
+        self.chars = lookahead;
+        self.operations += 1;
+        Some(base.powf(self.unary()?))
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+        match *self.chars.peek()? {
+            '(' => {
+                self.chars.next();
+                let value = self.sum()?;
+                self.skip_whitespace();
+                self.chars.next_if_eq(&')')?;
+                Some(value)
+            }
+            c if c.is_ascii_digit() || c == '.' => self.number(),
+            c if c.is_alphabetic() => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric()) {
+                    name.push(c);
+                }
+                if let Some((_, value)) = CONSTANTS.iter().find(|(constant, _)| *constant == name) {
+                    return Some(*value);
+                }
+                let (_, function) = FUNCTIONS.iter().find(|(function, _)| *function == name)?;
+                self.skip_whitespace();
+                self.chars.next_if_eq(&'(')?;
+                let argument = self.sum()?;
+                self.skip_whitespace();
+                self.chars.next_if_eq(&')')?;
+                self.operations += 1;
+                Some(function(argument))
+            }
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let mut number = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+            number.push(c);
+        }
+        // `1.5e3`, but an `e` with no digits after it isn't an exponent
+        let mut lookahead = self.chars.clone();
+        if lookahead.next_if(|c| matches!(c, 'e' | 'E')).is_some() {
+            let sign = lookahead.next_if(|c| matches!(c, '+' | '-'));
+            if lookahead.peek().is_some_and(char::is_ascii_digit) {
+                self.chars = lookahead;
+                number.push('e');
+                number.extend(sign);
+                while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+                    number.push(c);
+                }
+            }
+        }
+        number.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    fn result(text: &str) -> String {
+        field(&detect(text), "MATH_RESULT")
+    }
+
+    #[test]
+    fn evaluates_with_precedence() {
+        assert_eq!(result("(3 + 4) * 2"), "14");
+        assert_eq!(result("2 + 3 * 4"), "14");
+        assert_eq!(result("10 / 4"), "2.5");
+        assert_eq!(result("2^3^2"), "512");
+        // A leading minus binds looser than a power
+        assert_eq!(result("-2^2"), "-4");
+        assert_eq!(result("1.5e3 × 4"), "6000");
+        assert_eq!(result("sqrt(2) / 2"), "0.7071067812");
+        assert_eq!(result("10^20"), "1e20");
+    }
+
+    #[test]
+    fn a_trailing_equals_sign_is_dropped() {
+        let fields = detect("2**10 =");
+        assert_eq!(field(&fields, "MATH_EXPRESSION"), "2**10");
+        assert_eq!(field(&fields, "MATH_RESULT"), "1024");
+    }
+
+    #[test]
+    fn dates_numbers_and_broken_expressions_are_not_maths() {
+        for text in ["2024-05-01", "555-1234", "1/2/2024", "42", "hello", "1 / 0", "3 +", "foo(2)"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn eval_takes_a_lone_constant() {
+        assert_eq!(eval("2 * pi").as_deref(), Some("6.2831853072"));
+        assert_eq!(eval("e").as_deref(), Some("2.7182818285"));
+        assert_eq!(eval("(1"), None);
+    }
+}
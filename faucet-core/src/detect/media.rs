@@ -0,0 +1,153 @@
+//! Audio and video, either as data or as a path to a local file, described by `ffprobe`.
+
+use crate::Runner;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mkv", "webm", "mov", "avi", "wmv", "flv", "mpg", "mpeg", "ts", "mp3", "m4a", "aac", "flac",
+    "ogg", "oga", "opus", "wav", "wma",
+];
+
+/// A path (or `file://` URI) to a local media file
+pub fn detect_path(text: &str, runner: &dyn Runner) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    let text = text.strip_prefix("file://").unwrap_or(text);
+    if text.contains('\n') || !text.starts_with(['/', '~']) {
+        return None;
+    }
+    let path = crate::expand_home(text).ok()?;
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !EXTENSIONS.contains(&extension.as_str()) || !path.is_file() {
+        return None;
+    }
+    probe(runner, &path)
+}
+
+/// Media data, recognised by its container's magic bytes before `ffprobe` is run on it
+pub fn detect_bytes(bytes: &[u8], runner: &dyn Runner) -> Option<Vec<(String, String)>> {
+    let container = bytes.get(4..8) == Some(b"ftyp")
+        || bytes.starts_with(b"\x1a\x45\xdf\xa3")
+        || bytes.starts_with(b"OggS")
+        || bytes.starts_with(b"fLaC")
+        || bytes.starts_with(b"ID3")
+        || bytes.len() >= 12 && &bytes[..4] == b"RIFF" && matches!(&bytes[8..12], b"WAVE" | b"AVI ");
+    if !container {
+        return None;
+    }
+    let mut file = tempfile::Builder::new().prefix("faucet_media_").tempfile().ok()?;
+    std::io::Write::write_all(&mut file, bytes).ok()?;
+    probe(runner, file.path())
+}
+
+/// `MEDIA_KIND` (`video` or `audio`), `MEDIA_DURATION` (seconds), `MEDIA_CODEC`, and for video
+/// `MEDIA_WIDTH` and `MEDIA_HEIGHT`. `None` when ffprobe is missing or finds no streams.
+fn probe(runner: &dyn Runner, input: &Path) -> Option<Vec<(String, String)>> {
+    let output = runner
+        .output(
+            Command::new("ffprobe")
+                .args(["-v", "error", "-show_entries", "format=duration:stream=codec_type,codec_name,width,height", "-of", "compact"])
+                .arg(input)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null()),
+        )
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // `stream|codec_name=h264|codec_type=video|width=1920|height=1080` and `format|duration=12.5`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let records: Vec<(&str, Vec<(&str, &str)>)> = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('|');
+            let kind = fields.next()?;
+            Some((kind, fields.filter_map(|field| field.split_once('=')).collect()))
+        })
+        .collect();
+    let field = |fields: &[(&str, &str)], name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+            .filter(|value| value != "N/A")
+    };
+    let stream = |codec_type: &str| {
+        records
+            .iter()
+            .find(|(kind, fields)| *kind == "stream" && field(fields, "codec_type").as_deref() == Some(codec_type))
+            .map(|(_, fields)| fields)
+    };
+    let duration = records
+        .iter()
+        .find(|(kind, _)| *kind == "format")
+        .and_then(|(_, fields)| field(fields, "duration"))
+        .unwrap_or_default();
+    let mut vars = vec![("MEDIA_DURATION".to_string(), duration)];
+    // Cover art shows up as a video stream with an image codec
+    match stream("video").filter(|fields| !matches!(field(fields, "codec_name").as_deref(), Some("mjpeg" | "png"))) {
+        Some(video) => vars.extend([
+            ("MEDIA_KIND".to_string(), "video".to_string()),
+            ("MEDIA_CODEC".to_string(), field(video, "codec_name").unwrap_or_default()),
+            ("MEDIA_WIDTH".to_string(), field(video, "width").unwrap_or_default()),
+            ("MEDIA_HEIGHT".to_string(), field(video, "height").unwrap_or_default()),
+        ]),
+        None => vars.extend([
+            ("MEDIA_KIND".to_string(), "audio".to_string()),
+            ("MEDIA_CODEC".to_string(), field(stream("audio")?, "codec_name").unwrap_or_default()),
+        ]),
+    }
+    Some(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockRunner;
+    use crate::detect::field;
+
+    const VIDEO: &str = "stream|codec_name=h264|codec_type=video|width=1920|height=1080
+stream|codec_name=aac|codec_type=audio
+format|duration=12.5
+";
+
+    #[test]
+    fn describes_media_data_with_ffprobe() {
+        let runner = MockRunner::new().on("ffprobe", 0, VIDEO);
+        let fields = detect_bytes(b"\0\0\0\x18ftypisom", &runner);
+        assert_eq!(field(&fields, "MEDIA_KIND"), "video");
+        assert_eq!(field(&fields, "MEDIA_CODEC"), "h264");
+        assert_eq!(field(&fields, "MEDIA_WIDTH"), "1920");
+        assert_eq!(field(&fields, "MEDIA_HEIGHT"), "1080");
+        assert_eq!(field(&fields, "MEDIA_DURATION"), "12.5");
+    }
+
+    #[test]
+    fn cover_art_is_not_video() {
+        let probed = "stream|codec_name=mjpeg|codec_type=video|width=500|height=500\nstream|codec_name=flac|codec_type=audio\nformat|duration=N/A\n";
+        let runner = MockRunner::new().on("ffprobe", 0, probed);
+        let fields = detect_bytes(b"fLaC\0\0\0\x22", &runner);
+        assert_eq!(field(&fields, "MEDIA_KIND"), "audio");
+        assert_eq!(field(&fields, "MEDIA_CODEC"), "flac");
+        assert_eq!(field(&fields, "MEDIA_DURATION"), "");
+    }
+
+    #[test]
+    fn only_media_is_probed() {
+        let runner = MockRunner::new().on("ffprobe", 0, VIDEO);
+        assert!(detect_bytes(b"plain bytes", &runner).is_none());
+        assert!(detect_path("/no/such/clip.mp4", &runner).is_none());
+        assert!(detect_path("clip.mp4", &runner).is_none());
+        assert!(runner.calls().is_empty());
+        // Without ffprobe there is nothing to say
+        assert!(detect_bytes(b"OggS\0\x02", &MockRunner::new()).is_none());
+    }
+
+    #[test]
+    fn probes_local_media_files() {
+        let file = tempfile::Builder::new().suffix(".MKV").tempfile().unwrap();
+        let runner = MockRunner::new().on("ffprobe", 0, VIDEO);
+        let uri = format!("file://{}", file.path().display());
+        assert_eq!(field(&detect_path(&uri, &runner), "MEDIA_KIND"), "video");
+    }
+}
@@ -0,0 +1,83 @@
+//! `otpauth://` two-factor URIs, usually decoded from a QR code.
+
+use super::percent_decode;
+
+/// `OTP_TYPE` (`totp`, `hotp`, or `migration` for Google Authenticator exports), `OTP_ISSUER`,
+/// `OTP_ACCOUNT`, `OTP_DIGITS`, `OTP_PERIOD` and `OTP_ALGORITHM`. The secret itself is not split
+/// out; commands that import it (e.g. `pass otp insert`) should read the whole URI from `$TEXT`.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    if text.starts_with("otpauth-migration://") {
+        return Some(vec![("OTP_TYPE".to_string(), "migration".to_string())]);
+    }
+    let rest = text.strip_prefix("otpauth://")?;
+    let (otp_type, rest) = rest.split_once('/')?;
+    let otp_type = otp_type.to_ascii_lowercase();
+    if otp_type != "totp" && otp_type != "hotp" {
+        return None;
+    }
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let params: Vec<(&str, String)> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key, percent_decode(value, true)))
+        .collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+    param("secret")?;
+    // The label is `Issuer:account` or just `account`
+    let label = percent_decode(label, false);
+    let (label_issuer, account) = match label.split_once(':') {
+        Some((issuer, account)) => (issuer.trim().to_string(), account.trim().to_string()),
+        None => (String::new(), label.trim().to_string()),
+    };
+    Some(vec![
+        ("OTP_TYPE".to_string(), otp_type),
+        ("OTP_ISSUER".to_string(), param("issuer").unwrap_or(label_issuer)),
+        ("OTP_ACCOUNT".to_string(), account),
+        ("OTP_DIGITS".to_string(), param("digits").unwrap_or_else(|| "6".to_string())),
+        ("OTP_PERIOD".to_string(), param("period").unwrap_or_else(|| "30".to_string())),
+        ("OTP_ALGORITHM".to_string(), param("algorithm").unwrap_or_else(|| "SHA1".to_string())),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn splits_out_everything_but_the_secret() {
+        let fields = detect("otpauth://totp/Example:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example&digits=8");
+        assert_eq!(field(&fields, "OTP_TYPE"), "totp");
+        assert_eq!(field(&fields, "OTP_ISSUER"), "Example");
+        assert_eq!(field(&fields, "OTP_ACCOUNT"), "alice@example.com");
+        assert_eq!(field(&fields, "OTP_DIGITS"), "8");
+        assert_eq!(field(&fields, "OTP_PERIOD"), "30");
+        assert_eq!(field(&fields, "OTP_ALGORITHM"), "SHA1");
+        assert!(!fields.unwrap().iter().any(|(_, value)| value.contains("JBSWY3DPEHPK3PXP")));
+    }
+
+    #[test]
+    fn the_issuer_can_come_from_the_label_alone() {
+        let fields = detect("otpauth://hotp/Forge:bob?secret=ABC");
+        assert_eq!(field(&fields, "OTP_TYPE"), "hotp");
+        assert_eq!(field(&fields, "OTP_ISSUER"), "Forge");
+        assert_eq!(field(&fields, "OTP_ACCOUNT"), "bob");
+        assert_eq!(field(&detect("otpauth-migration://offline?data=abc"), "OTP_TYPE"), "migration");
+    }
+
+    #[test]
+    fn rejects_uris_without_a_secret_or_type() {
+        assert!(detect("otpauth://totp/bob?issuer=Forge").is_none());
+        assert!(detect("otpauth://sms/bob?secret=ABC").is_none());
+        assert!(detect("https://example.com/?secret=ABC").is_none());
+    }
+}
@@ -0,0 +1,97 @@
+//! Unified diffs and `git format-patch` mails.
+
+/// `DIFF_FORMAT` (`git` or `unified`), `DIFF_FILES` (space separated target paths with the `b/`
+/// prefix stripped), `DIFF_FILE_COUNT`, `DIFF_ADDED` and `DIFF_REMOVED` (line counts)
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let mut format = "unified";
+    let mut files: Vec<String> = Vec::new();
+    let mut hunks = 0;
+    let mut added = 0;
+    let mut removed = 0;
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        if line.starts_with("diff --git ") {
+            format = "git";
+        } else if let Some(old) = line.strip_prefix("--- ")
+        && let Some(new) = lines.peek().and_then(|next| next.strip_prefix("+++ "))
+        {
+            // A `---`/`+++` pair starts a file; new files diff against /dev/null, deletions target it
+            let path = match path_of(new) {
+                path if path == "/dev/null" => path_of(old),
+                path => path,
+            };
+            if !files.contains(&path) {
+                files.push(path);
+            }
+            lines.next();
+        } else if line.starts_with("@@ ") && line[3..].contains(" @@") {
+            hunks += 1;
+        } else if hunks > 0 && line.starts_with('+') {
+            added += 1;
+        } else if hunks > 0 && line.starts_with('-') {
+            removed += 1;
+        }
+    }
+    if files.is_empty() || hunks == 0 {
+        return None;
+    }
+    Some(vec![
+        ("DIFF_FORMAT".to_string(), format.to_string()),
+        ("DIFF_FILES".to_string(), files.join(" ")),
+        ("DIFF_FILE_COUNT".to_string(), files.len().to_string()),
+        ("DIFF_ADDED".to_string(), added.to_string()),
+        ("DIFF_REMOVED".to_string(), removed.to_string()),
+    ])
+}
+
+/// `a/src/main.rs\t2024-05-01 ...` -> `src/main.rs`
+fn path_of(header: &str) -> String {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    match path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")) {
+        Some(stripped) => stripped.to_string(),
+        None => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn counts_a_git_diff() {
+        let fields = detect(
+            "diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,2 +1,3 @@
+-old
++new
++more
+ context
+",
+        );
+        assert_eq!(field(&fields, "DIFF_FORMAT"), "git");
+        assert_eq!(field(&fields, "DIFF_FILES"), "src/main.rs");
+        assert_eq!(field(&fields, "DIFF_FILE_COUNT"), "1");
+        assert_eq!(field(&fields, "DIFF_ADDED"), "2");
+        assert_eq!(field(&fields, "DIFF_REMOVED"), "1");
+    }
+
+    #[test]
+    fn new_and_deleted_files_are_named_by_their_real_side() {
+        let fields = detect(
+            "--- /dev/null\n+++ b/new.txt\t2024-05-01\n@@ -0,0 +1 @@\n+hello\n--- a/old.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye\n",
+        );
+        assert_eq!(field(&fields, "DIFF_FORMAT"), "unified");
+        assert_eq!(field(&fields, "DIFF_FILES"), "new.txt old.txt");
+    }
+
+    #[test]
+    fn needs_a_file_and_a_hunk() {
+        assert!(detect("--- a\n+++ b\n").is_none());
+        assert!(detect("- item\n+ item\n").is_none());
+        assert!(detect("@@ -1 +1 @@\n-a\n+b\n").is_none());
+    }
+}
@@ -0,0 +1,192 @@
+//! Money amounts and physical measurements, normalised for `qalc`/`units`.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+const NUMBER: &str = r"[-+]?\d{1,3}(?:[ ,.'\u{a0}]\d{3})*(?:[.,]\d+)?|[-+]?\d+(?:[.,]\d+)?";
+
+static AMOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r#"^(?:(?P<prefix>US\$|A\$|C\$|NZ\$|HK\$|R\$|[$€£¥₹₩₽₺₿₪₫฿]|[A-Z]{{3}}\s)\s*(?P<n1>{NUMBER})|(?P<n2>{NUMBER})\s*(?P<suffix>[$€£¥₹₩₽₺₿₪₫฿"]|[A-Za-z°º²/.]+[23]?))$"#
+    ))
+    .unwrap()
+});
+
+/// `QUANTITY_KIND` (`currency` or `unit`), `QUANTITY_VALUE` (a plain decimal like `1234.5`),
+/// `QUANTITY_UNIT` (ISO 4217 code or unit symbol) and `QUANTITY`, both together (`1234.5 EUR`)
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    if text.len() > 64 {
+        return None;
+    }
+    let captures = AMOUNT.captures(text)?;
+    let number = captures.name("n1").or_else(|| captures.name("n2"))?.as_str();
+    let symbol = captures.name("prefix").or_else(|| captures.name("suffix"))?.as_str().trim();
+    let (kind, unit) = match currency(symbol) {
+        Some(code) => ("currency", code),
+        None => ("unit", unit(symbol)?),
+    };
+    let value = normalise_number(number)?;
+    Some(vec![
+        ("QUANTITY_KIND".to_string(), kind.to_string()),
+        ("QUANTITY_VALUE".to_string(), value.clone()),
+        ("QUANTITY_UNIT".to_string(), unit.to_string()),
+        ("QUANTITY".to_string(), format!("{value} {unit}")),
+    ])
+}
+
+fn currency(symbol: &str) -> Option<&'static str> {
+    Some(match symbol {
+        "$" | "US$" | "USD" => "USD",
+        "€" | "EUR" => "EUR",
+        "£" | "GBP" => "GBP",
+        "¥" | "JPY" => "JPY",
+        "₹" | "INR" => "INR",
+        "₩" | "KRW" => "KRW",
+        "₽" | "RUB" => "RUB",
+        "₺" | "TRY" => "TRY",
+        "₿" | "BTC" => "BTC",
+        "₪" | "ILS" => "ILS",
+        "₫" | "VND" => "VND",
+        "฿" | "THB" => "THB",
+        "A$" | "AUD" => "AUD",
+        "C$" | "CAD" => "CAD",
+        "NZ$" | "NZD" => "NZD",
+        "HK$" | "HKD" => "HKD",
+        "R$" | "BRL" => "BRL",
+        "CHF" => "CHF",
+        "CNY" | "RMB" => "CNY",
+        "SEK" => "SEK",
+        "NOK" => "NOK",
+        "DKK" => "DKK",
+        "PLN" => "PLN",
+        "CZK" => "CZK",
+        "HUF" => "HUF",
+        "MXN" => "MXN",
+        "SGD" => "SGD",
+        "ZAR" => "ZAR",
+        "ETH" => "ETH",
+        _ => return None,
+    })
+}
+
+fn unit(symbol: &str) -> Option<&'static str> {
+    Some(match symbol {
+        "mm" => "mm",
+        "cm" => "cm",
+        "m" => "m",
+        "km" => "km",
+        "in" | "inch" | "inches" | "\"" => "in",
+        "ft" | "foot" | "feet" => "ft",
+        "yd" | "yard" | "yards" => "yd",
+        "mi" | "mile" | "miles" => "mi",
+        "nmi" => "nmi",
+        "mg" => "mg",
+        "g" => "g",
+        "kg" | "kilo" | "kilos" => "kg",
+        "t" | "tonne" | "tonnes" => "t",
+        "oz" => "oz",
+        "lb" | "lbs" | "pound" | "pounds" => "lb",
+        "st" | "stone" => "st",
+        "ml" | "mL" => "mL",
+        "l" | "L" | "litre" | "liter" | "litres" | "liters" => "L",
+        "gal" | "gallon" | "gallons" => "gal",
+        "floz" | "fl.oz" => "floz",
+        "cup" | "cups" => "cup",
+        "°C" | "ºC" | "degC" => "°C",
+        "°F" | "ºF" | "degF" => "°F",
+        "K" => "K",
+        "km/h" | "kph" | "kmh" => "km/h",
+        "mph" => "mph",
+        "m/s" => "m/s",
+        "kn" | "knots" | "kt" => "kn",
+        "B" => "B",
+        "kB" | "KB" => "kB",
+        "MB" => "MB",
+        "GB" => "GB",
+        "TB" => "TB",
+        "KiB" => "KiB",
+        "MiB" => "MiB",
+        "GiB" => "GiB",
+        "TiB" => "TiB",
+        "m²" | "m2" | "sqm" => "m^2",
+        "km²" | "km2" => "km^2",
+        "ft²" | "ft2" | "sqft" => "ft^2",
+        "acre" | "acres" => "acre",
+        "ha" => "ha",
+        "J" => "J",
+        "kJ" => "kJ",
+        "cal" => "cal",
+        "kcal" => "kcal",
+        "Wh" => "Wh",
+        "kWh" => "kWh",
+        "W" => "W",
+        "kW" => "kW",
+        "hp" => "hp",
+        "Pa" => "Pa",
+        "hPa" => "hPa",
+        "kPa" => "kPa",
+        "bar" => "bar",
+        "psi" => "psi",
+        "atm" => "atm",
+        _ => return None,
+    })
+}
+
+/// Resolves thousands/decimal separators: `1,234.5`, `1.234,5` and `1 234,5` all become `1234.5`
+fn normalise_number(number: &str) -> Option<String> {
+    let number: String = number.chars().filter(|c| !matches!(c, ' ' | '\'' | '\u{a0}')).collect();
+    let decimal = match (number.rfind('.'), number.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some(position), None) | (None, Some(position)) => {
+            let separator = number.as_bytes()[position] as char;
+            let digits_after = number.len() - position - 1;
+            // A single separator followed by exactly three digits is a thousands separator (`1,000`)
+            let repeated = number.matches(separator).count() > 1;
+            (!repeated && digits_after != 3).then_some(position)
+        }
+        (None, None) => None,
+    };
+    let normalised: String = number
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            '.' | ',' if Some(i) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    normalised.parse::<f64>().ok().map(|_| normalised.trim_start_matches('+').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_currency_amounts_either_side() {
+        let dollars = detect("$1,234.50");
+        assert_eq!(field(&dollars, "QUANTITY_KIND"), "currency");
+        assert_eq!(field(&dollars, "QUANTITY_VALUE"), "1234.50");
+        assert_eq!(field(&dollars, "QUANTITY"), "1234.50 USD");
+        assert_eq!(field(&detect("1.234,5 €"), "QUANTITY"), "1234.5 EUR");
+        assert_eq!(field(&detect("EUR 12"), "QUANTITY"), "12 EUR");
+    }
+
+    #[test]
+    fn reads_measurements() {
+        let distance = detect("1,000 km");
+        assert_eq!(field(&distance, "QUANTITY_KIND"), "unit");
+        assert_eq!(field(&distance, "QUANTITY"), "1000 km");
+        assert_eq!(field(&detect("72°F"), "QUANTITY_UNIT"), "°F");
+        assert_eq!(field(&detect("0,5 L"), "QUANTITY_VALUE"), "0.5");
+        assert_eq!(field(&detect("5 lbs"), "QUANTITY_UNIT"), "lb");
+    }
+
+    #[test]
+    fn a_number_needs_a_known_unit() {
+        for text in ["12 apples", "1,5", "hello", "km", "5 kg of flour"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Files on other machines: `ssh://`, `sftp://` and `scp://` URLs, and scp-style
+//! `[user@]host:path` targets.
+
+/// `REMOTE_HOST`, `REMOTE_USER` and `REMOTE_PORT` (empty when not given), `REMOTE_PATH` as written
+/// (absolute, `~/...` or relative to the login directory) and `REMOTE_TARGET` (`user@host`, or the
+/// host alone), which is what `ssh` and `scp` take
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let (user, host, port, path) = match text.split_once("://") {
+        Some((scheme, rest)) => {
+            if !["ssh", "sftp", "scp"].iter().any(|known| scheme.eq_ignore_ascii_case(known)) {
+                return None;
+            }
+            let (authority, path) = match rest.find('/') {
+                Some(slash) => rest.split_at(slash),
+                None => (rest, ""),
+            };
+            let (user, host_port) = split_user(authority);
+            let (host, port) = match host_port.rsplit_once(':') {
+                Some((host, port)) if !port.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()) => {
+                    (host, port)
+                }
+                _ => (host_port, ""),
+            };
+            // `ssh://host/~/notes` is relative to the home directory
+            (user, host, port, path.strip_prefix('/').filter(|path| path.starts_with('~')).unwrap_or(path))
+        }
+        None => {
+            // An IPv6 host has colons of its own
+            let (user_host, path) = match text.find("]:") {
+                Some(end) => (&text[..=end], &text[end + 2..]),
+                None => text.split_once(':')?,
+            };
+            let (user, host) = split_user(user_host);
+            // Without a user, only a path that can't be a port or a `key:value` makes it a target
+            let path_like = path.starts_with(['/', '~']) && !path.starts_with("//");
+            if path.is_empty() || !(user.is_some() || path_like) || host.eq_ignore_ascii_case("file") {
+                return None;
+            }
+            (user, host, "", path)
+        }
+    };
+    if !is_host(host) {
+        return None;
+    }
+    let target = match user {
+        Some(user) => format!("{user}@{host}"),
+        None => host.to_string(),
+    };
+    Some(vec![
+        ("REMOTE_HOST".to_string(), host.to_string()),
+        ("REMOTE_USER".to_string(), user.unwrap_or_default().to_string()),
+        ("REMOTE_PORT".to_string(), port.to_string()),
+        ("REMOTE_PATH".to_string(), super::percent_decode(path, false)),
+        ("REMOTE_TARGET".to_string(), target),
+    ])
+}
+
+fn split_user(authority: &str) -> (Option<&str>, &str) {
+    match authority.rsplit_once('@') {
+        Some((user, host)) if !user.is_empty() => (Some(user), host),
+        _ => (None, authority),
+    }
+}
+
+/// A host name or IP address, but not a single letter, which is more likely a drive (`C:/...`)
+fn is_host(host: &str) -> bool {
+    if let Some(ipv6) = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')) {
+        return ipv6.parse::<std::net::Ipv6Addr>().is_ok();
+    }
+    host.len() > 1
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_ssh_urls() {
+        let fields = detect("ssh://alice@example.com:2222/~/notes.txt");
+        assert_eq!(field(&fields, "REMOTE_HOST"), "example.com");
+        assert_eq!(field(&fields, "REMOTE_USER"), "alice");
+        assert_eq!(field(&fields, "REMOTE_PORT"), "2222");
+        assert_eq!(field(&fields, "REMOTE_PATH"), "~/notes.txt");
+        assert_eq!(field(&fields, "REMOTE_TARGET"), "alice@example.com");
+    }
+
+    #[test]
+    fn reads_scp_targets() {
+        let fields = detect("bob@host.example:/var/log/syslog");
+        assert_eq!(field(&fields, "REMOTE_TARGET"), "bob@host.example");
+        assert_eq!(field(&fields, "REMOTE_PATH"), "/var/log/syslog");
+        assert_eq!(field(&detect("server:~/backup"), "REMOTE_TARGET"), "server");
+        assert_eq!(field(&detect("[::1]:/tmp/x"), "REMOTE_HOST"), "[::1]");
+    }
+
+    #[test]
+    fn drives_ports_and_other_schemes_are_not_remote() {
+        for text in ["C:/Users/me", "localhost:8080", "key:value", "file:///etc/hosts", "https://example.com/", "a b:/c"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Delimiter-separated tables (CSV, TSV, semicolon or pipe separated).
+
+const DELIMITERS: [char; 4] = ['\t', ',', ';', '|'];
+const SAMPLE_ROWS: usize = 20;
+const PREVIEW_ROWS: usize = 6;
+const PREVIEW_WIDTH: usize = 24;
+
+/// `CSV_DELIMITER`, `CSV_COLUMNS`, `CSV_ROWS` (including the header), `CSV_HEADER` (the first
+/// row, comma separated) and `CSV_PREVIEW`, the first rows aligned for a picker preview
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let (delimiter, columns) = DELIMITERS.iter().find_map(|&delimiter| {
+        let sample: Vec<Vec<String>> = records(text, delimiter).take(SAMPLE_ROWS).collect();
+        let columns = sample.first()?.len();
+        // Two lines of prose can easily share a comma count; tabs are a stronger signal
+        let min_rows = if delimiter == '\t' { 2 } else { 3 };
+        (sample.len() >= min_rows && columns >= 2 && sample.iter().all(|record| record.len() == columns))
+            .then_some((delimiter, columns))
+    })?;
+    let rows: Vec<Vec<String>> = records(text, delimiter).collect();
+    Some(vec![
+        ("CSV_DELIMITER".to_string(), delimiter.to_string()),
+        ("CSV_COLUMNS".to_string(), columns.to_string()),
+        ("CSV_ROWS".to_string(), rows.len().to_string()),
+        ("CSV_HEADER".to_string(), rows[0].join(",")),
+        ("CSV_PREVIEW".to_string(), preview(&rows[..rows.len().min(PREVIEW_ROWS)], columns)),
+    ])
+}
+
+fn preview(rows: &[Vec<String>], columns: usize) -> String {
+    let cell = |value: &str| -> String {
+        let value = value.replace(['\n', '\t'], " ");
+        match value.char_indices().nth(PREVIEW_WIDTH - 1) {
+            Some((end, _)) => format!("{}…", &value[..end]),
+            None => value,
+        }
+    };
+    let cells: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(|value| cell(value)).collect()).collect();
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| cells.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{value:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Records of the table, honouring `"quoted"` fields (with `""` escapes and embedded newlines)
+/// and skipping blank lines
+fn records(text: &str, delimiter: char) -> impl Iterator<Item = Vec<String>> + '_ {
+    let mut chars = text.chars().peekable();
+    std::iter::from_fn(move || {
+        loop {
+            chars.peek()?;
+            let mut record = Vec::new();
+            let mut field = String::new();
+            let mut quoted = false;
+            let mut at_field_start = true;
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quoted && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' if quoted => quoted = false,
+                    '"' if at_field_start => quoted = true,
+                    c if c == delimiter && !quoted => {
+                        record.push(std::mem::take(&mut field));
+                        at_field_start = true;
+                        continue;
+                    }
+                    '\r' if !quoted => {}
+                    '\n' if !quoted => break,
+                    c => field.push(c),
+                }
+                at_field_start = false;
+            }
+            if record.is_empty() && field.trim().is_empty() {
+                continue;
+            }
+            record.push(field);
+            return Some(record);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_csv_with_a_preview() {
+        let fields = detect("name,age\nalice,30\nbob,25\n");
+        assert_eq!(field(&fields, "CSV_DELIMITER"), ",");
+        assert_eq!(field(&fields, "CSV_COLUMNS"), "2");
+        assert_eq!(field(&fields, "CSV_ROWS"), "3");
+        assert_eq!(field(&fields, "CSV_HEADER"), "name,age");
+        assert_eq!(field(&fields, "CSV_PREVIEW"), "name   age\nalice  30\nbob    25");
+    }
+
+    #[test]
+    fn quoted_fields_may_hold_delimiters_and_quotes() {
+        let fields = detect("name,quote\r\nalice,\"hi, there\"\r\nbob,\"say \"\"yo\"\"\nthen go\"\r\n");
+        assert_eq!(field(&fields, "CSV_COLUMNS"), "2");
+        assert_eq!(field(&fields, "CSV_ROWS"), "3");
+        assert!(field(&fields, "CSV_PREVIEW").contains("say \"yo\" then go"));
+    }
+
+    #[test]
+    fn two_rows_do_only_for_tabs() {
+        assert_eq!(field(&detect("a\tb\n1\t2"), "CSV_DELIMITER"), "\t");
+        assert!(detect("a,b\n1,2").is_none());
+    }
+
+    #[test]
+    fn prose_and_ragged_rows_are_not_tables() {
+        assert!(detect("Hello, world.\nNice, day.\nNo commas here").is_none());
+        assert!(detect("a,b\nc\nd,e\n").is_none());
+    }
+}
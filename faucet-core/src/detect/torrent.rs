@@ -0,0 +1,177 @@
+//! `.torrent` metainfo files (bencoded).
+
+/// Nesting deeper than any real torrent, so hostile input can't exhaust the stack
+const MAX_DEPTH: usize = 32;
+
+enum Value<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Value<'a>>),
+    Dict(Vec<(&'a [u8], Value<'a>)>),
+}
+
+impl<'a> Value<'a> {
+    fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| *entry_key == key.as_bytes())
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn text(&self) -> Option<String> {
+        match self {
+            Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Value::Int(int) => Some(*int),
+            _ => None,
+        }
+    }
+}
+
+/// Parses one value at the start of `bytes`, returning it and the rest
+fn parse(bytes: &[u8], depth: usize) -> Option<(Value<'_>, &[u8])> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    match bytes.first()? {
+        b'i' => {
+            let end = bytes.iter().position(|&b| b == b'e')?;
+            let int = std::str::from_utf8(&bytes[1..end]).ok()?.parse().ok()?;
+            Some((Value::Int(int), &bytes[end + 1..]))
+        }
+        b'l' => {
+            let mut rest = &bytes[1..];
+            let mut items = Vec::new();
+            while *rest.first()? != b'e' {
+                let (item, after) = parse(rest, depth + 1)?;
+                items.push(item);
+                rest = after;
+            }
+            Some((Value::List(items), &rest[1..]))
+        }
+        b'd' => {
+            let mut rest = &bytes[1..];
+            let mut entries = Vec::new();
+            while *rest.first()? != b'e' {
+                let (Value::Bytes(key), after) = parse(rest, depth + 1)? else {
+                    return None;
+                };
+                let (value, after) = parse(after, depth + 1)?;
+                entries.push((key, value));
+                rest = after;
+            }
+            Some((Value::Dict(entries), &rest[1..]))
+        }
+        b'0'..=b'9' => {
+            let colon = bytes.iter().position(|&b| b == b':')?;
+            let len: usize = std::str::from_utf8(&bytes[..colon]).ok()?.parse().ok()?;
+            let start = colon + 1;
+            let value = bytes.get(start..start.checked_add(len)?)?;
+            Some((Value::Bytes(value), &bytes[start + len..]))
+        }
+        _ => None,
+    }
+}
+
+/// `TORRENT_NAME`, `TORRENT_SIZE` (bytes), `TORRENT_SIZE_HUMAN` (`1.4 GiB`), `TORRENT_FILES`
+/// (count) and `TORRENT_TRACKER` (the first announce URL, empty for trackerless torrents)
+pub fn detect(bytes: &[u8]) -> Option<Vec<(String, String)>> {
+    if !bytes.starts_with(b"d") {
+        return None;
+    }
+    let (metainfo, _) = parse(bytes, 0)?;
+    let info = metainfo.get("info")?;
+    let name = info.get("name")?.text()?;
+    let (size, files) = match info.get("files") {
+        Some(Value::List(files)) => (
+            files.iter().filter_map(|file| file.get("length")?.int()).sum(),
+            files.len(),
+        ),
+        _ => (info.get("length")?.int()?, 1),
+    };
+    let tracker = metainfo
+        .get("announce")
+        .and_then(Value::text)
+        .or_else(|| match metainfo.get("announce-list") {
+            Some(Value::List(tiers)) => tiers.iter().find_map(|tier| match tier {
+                Value::List(trackers) => trackers.first()?.text(),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .unwrap_or_default();
+    Some(vec![
+        ("TORRENT_NAME".to_string(), name),
+        ("TORRENT_SIZE".to_string(), size.to_string()),
+        ("TORRENT_SIZE_HUMAN".to_string(), human_size(size)),
+        ("TORRENT_FILES".to_string(), files.to_string()),
+        ("TORRENT_TRACKER".to_string(), tracker),
+    ])
+}
+
+fn human_size(bytes: i64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{bytes} B") } else { format!("{size:.1} {}", units[unit]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    fn string(text: &str) -> String {
+        format!("{}:{text}", text.len())
+    }
+
+    #[test]
+    fn reads_a_single_file_torrent() {
+        let torrent = format!(
+            "d8:announce{}4:infod6:lengthi1536e4:name{}12:piece lengthi16384eee",
+            string("http://tracker.example/announce"),
+            string("file.iso")
+        );
+        let fields = detect(torrent.as_bytes());
+        assert_eq!(field(&fields, "TORRENT_NAME"), "file.iso");
+        assert_eq!(field(&fields, "TORRENT_SIZE"), "1536");
+        assert_eq!(field(&fields, "TORRENT_SIZE_HUMAN"), "1.5 KiB");
+        assert_eq!(field(&fields, "TORRENT_FILES"), "1");
+        assert_eq!(field(&fields, "TORRENT_TRACKER"), "http://tracker.example/announce");
+    }
+
+    #[test]
+    fn sums_the_files_of_a_multi_file_torrent() {
+        let torrent = format!(
+            "d13:announce-listll{}ee4:infod5:filesld6:lengthi1024e4:pathl1:aeed6:lengthi2048e4:pathl1:beee4:name{}ee",
+            string("udp://tracker.example:1337"),
+            string("album")
+        );
+        let fields = detect(torrent.as_bytes());
+        assert_eq!(field(&fields, "TORRENT_SIZE"), "3072");
+        assert_eq!(field(&fields, "TORRENT_FILES"), "2");
+        assert_eq!(field(&fields, "TORRENT_TRACKER"), "udp://tracker.example:1337");
+        // Trackerless
+        assert_eq!(field(&detect(b"d4:infod6:lengthi1e4:name1:xee"), "TORRENT_TRACKER"), "");
+    }
+
+    #[test]
+    fn rejects_malformed_and_hostile_input() {
+        let deep = format!("d4:info{}", "l".repeat(100));
+        for bytes in [b"not bencode".as_slice(), b"d4:infod4:name1:xee", b"d4:info", b"d4:infod4:name99:xee", deep.as_bytes()] {
+            assert!(detect(bytes).is_none(), "{}", String::from_utf8_lossy(bytes));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Stack traces and compiler errors, reduced to the location of the top frame in user code.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static PYTHON_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^\s*File "([^"]+)", line (\d+)"#).unwrap());
+static RUST_PANIC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)panicked at (?:'.*?', )?([^\s:]+):(\d+):(\d+)").unwrap());
+static RUST_DIAGNOSTIC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*--> ([^\s:]+):(\d+):(\d+)").unwrap());
+static JS_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*at (?:.*? \()?(?:file://)?([^\s()]+?):(\d+):(\d+)\)?\s*$").unwrap());
+static JAVA_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*at ([\w$.]+)\.[\w$<>]+\(([\w$]+\.(?:java|kt|scala)):(\d+)\)").unwrap());
+static COMPILER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^([^\s:]+\.\w+)[:(](\d+)(?:[:,](\d+))?\)?:? (?:fatal )?(?:error|warning)\b").unwrap()
+});
+
+/// `TRACE_LANGUAGE` (`python`, `rust`, `javascript`, `java` or `compiler`), `TRACE_FILE`,
+/// `TRACE_LINE`, `TRACE_COLUMN` (empty if unknown) and `TRACE_MESSAGE`
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let (language, file, line, column, message) = python(text)
+        .or_else(|| rust(text))
+        .or_else(|| java(text))
+        .or_else(|| javascript(text))
+        .or_else(|| compiler(text))?;
+    Some(vec![
+        ("TRACE_LANGUAGE".to_string(), language.to_string()),
+        ("TRACE_FILE".to_string(), file),
+        ("TRACE_LINE".to_string(), line),
+        ("TRACE_COLUMN".to_string(), column),
+        ("TRACE_MESSAGE".to_string(), message.trim().to_string()),
+    ])
+}
+
+type Location = (&'static str, String, String, String, String);
+
+/// Frames in installed packages or the runtime itself are never where the fix goes
+fn is_library(file: &str) -> bool {
+    ["site-packages/", "dist-packages/", "/lib/python", "node_modules/", "node:", "/rustc/", ".cargo/registry/", "<"]
+        .iter()
+        .any(|marker| file.contains(marker))
+}
+
+fn python(text: &str) -> Option<Location> {
+    if !text.contains("Traceback (most recent call last)") {
+        return None;
+    }
+    // Most recent call last: the user frame nearest the error is the last non-library one
+    let frames: Vec<_> = PYTHON_FRAME.captures_iter(text).collect();
+    let frame = frames.iter().rev().find(|frame| !is_library(&frame[1])).or(frames.last())?;
+    let message = text.lines().rev().find(|line| !line.trim().is_empty() && !line.starts_with(' ')).unwrap_or_default();
+    Some(("python", frame[1].to_string(), frame[2].to_string(), String::new(), message.to_string()))
+}
+
+fn rust(text: &str) -> Option<Location> {
+    if let Some(panic) = RUST_PANIC.captures(text) {
+        let line_start = text[..panic.get(0)?.start()].rfind('\n').map_or(0, |i| i + 1);
+        let message = text[line_start..].lines().take(2).collect::<Vec<_>>().join(" ");
+        return Some(("rust", panic[1].to_string(), panic[2].to_string(), panic[3].to_string(), message));
+    }
+    let location = RUST_DIAGNOSTIC.captures(text)?;
+    let message = text
+        .lines()
+        .find(|line| line.starts_with("error") || line.starts_with("warning"))
+        .unwrap_or_default();
+    Some(("rust", location[1].to_string(), location[2].to_string(), location[3].to_string(), message.to_string()))
+}
+
+fn java(text: &str) -> Option<Location> {
+    let frames: Vec<_> = JAVA_FRAME.captures_iter(text).collect();
+    let runtime = ["java.", "javax.", "jdk.", "sun.", "kotlin.", "scala.", "org.junit."];
+    let frame = frames
+        .iter()
+        .find(|frame| !runtime.iter().any(|prefix| frame[1].starts_with(prefix)))
+        .or(frames.first())?;
+    // Sources live under their package path: com.example.Foo -> com/example/Foo.java
+    let package = frame[1].rsplit_once('.').map_or("", |(package, _)| package);
+    let file = if package.is_empty() {
+        frame[2].to_string()
+    } else {
+        format!("{}/{}", package.replace('.', "/"), &frame[2])
+    };
+    let message = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    Some(("java", file, frame[3].to_string(), String::new(), message.to_string()))
+}
+
+fn javascript(text: &str) -> Option<Location> {
+    let frames: Vec<_> = JS_FRAME.captures_iter(text).collect();
+    let frame = frames.iter().find(|frame| !is_library(&frame[1])).or(frames.first())?;
+    let message = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    Some(("javascript", frame[1].to_string(), frame[2].to_string(), frame[3].to_string(), message.to_string()))
+}
+
+fn compiler(text: &str) -> Option<Location> {
+    let location = COMPILER.captures(text)?;
+    let message = text[location.get(0)?.start()..].lines().next().unwrap_or_default();
+    Some((
+        "compiler",
+        location[1].to_string(),
+        location[2].to_string(),
+        location.get(3).map_or(String::new(), |column| column.as_str().to_string()),
+        message.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn python_points_at_the_last_user_frame() {
+        let fields = detect(
+            r#"Traceback (most recent call last):
+  File "/home/me/app.py", line 10, in <module>
+    main()
+  File "/usr/lib/python3.12/json/__init__.py", line 346, in loads
+    return _default_decoder.decode(s)
+ValueError: bad input"#,
+        );
+        assert_eq!(field(&fields, "TRACE_LANGUAGE"), "python");
+        assert_eq!(field(&fields, "TRACE_FILE"), "/home/me/app.py");
+        assert_eq!(field(&fields, "TRACE_LINE"), "10");
+        assert_eq!(field(&fields, "TRACE_COLUMN"), "");
+        assert_eq!(field(&fields, "TRACE_MESSAGE"), "ValueError: bad input");
+    }
+
+    #[test]
+    fn reads_rust_panics_and_diagnostics() {
+        let panic = detect("thread 'main' panicked at src/main.rs:4:5:\nattempt to divide by zero\n");
+        assert_eq!(field(&panic, "TRACE_FILE"), "src/main.rs");
+        assert_eq!(field(&panic, "TRACE_LINE"), "4");
+        assert_eq!(field(&panic, "TRACE_COLUMN"), "5");
+        let diagnostic = detect("error[E0308]: mismatched types\n --> src/lib.rs:12:9\n");
+        assert_eq!(field(&diagnostic, "TRACE_FILE"), "src/lib.rs");
+        assert_eq!(field(&diagnostic, "TRACE_MESSAGE"), "error[E0308]: mismatched types");
+    }
+
+    #[test]
+    fn java_and_javascript_skip_runtime_frames() {
+        let java = detect(
+            "Exception in thread \"main\" java.lang.NullPointerException\n\tat java.util.Objects.requireNonNull(Objects.java:209)\n\tat com.example.App.run(App.java:42)\n",
+        );
+        assert_eq!(field(&java, "TRACE_LANGUAGE"), "java");
+        assert_eq!(field(&java, "TRACE_FILE"), "com/example/App.java");
+        assert_eq!(field(&java, "TRACE_LINE"), "42");
+        let javascript = detect(
+            "TypeError: x is undefined\n    at node:internal/main:5:3\n    at render (/home/me/app/src/view.js:12:7)\n",
+        );
+        assert_eq!(field(&javascript, "TRACE_LANGUAGE"), "javascript");
+        assert_eq!(field(&javascript, "TRACE_FILE"), "/home/me/app/src/view.js");
+        assert_eq!(field(&javascript, "TRACE_COLUMN"), "7");
+    }
+
+    #[test]
+    fn reads_compiler_errors() {
+        let fields = detect("main.c:3:5: error: expected ';' before '}' token");
+        assert_eq!(field(&fields, "TRACE_LANGUAGE"), "compiler");
+        assert_eq!(field(&fields, "TRACE_FILE"), "main.c");
+        assert_eq!(field(&fields, "TRACE_COLUMN"), "5");
+        assert!(detect("nothing to see here").is_none());
+    }
+}
@@ -0,0 +1,330 @@
+//! A lone emoji, or a few characters of less common Unicode (symbols, other scripts, invisible
+//! format characters), with their codepoints and escapes. Blocks come from a built-in table of
+//! the common ones; emoji are recognised by their blocks, so a few text symbols count too.
+
+use itertools::Itertools;
+
+/// Longer texts aren't looked at: a flag or family emoji takes up to 10 codepoints
+const MAX_CODEPOINTS: usize = 12;
+
+const ZWJ: char = '\u{200D}';
+const VARIATION_SELECTOR: char = '\u{FE0F}';
+const KEYCAP: char = '\u{20E3}';
+const BLACK_FLAG: char = '\u{1F3F4}';
+const CANCEL_TAG: char = '\u{E007F}';
+
+const BLOCKS: &[(u32, u32, &str)] = &[
+    (0x0000, 0x007F, "Basic Latin"),
+    (0x0080, 0x00FF, "Latin-1 Supplement"),
+    (0x0100, 0x017F, "Latin Extended-A"),
+    (0x0180, 0x024F, "Latin Extended-B"),
+    (0x0250, 0x02AF, "IPA Extensions"),
+    (0x02B0, 0x02FF, "Spacing Modifier Letters"),
+    (0x0300, 0x036F, "Combining Diacritical Marks"),
+    (0x0370, 0x03FF, "Greek and Coptic"),
+    (0x0400, 0x04FF, "Cyrillic"),
+    (0x0500, 0x052F, "Cyrillic Supplement"),
+    (0x0530, 0x058F, "Armenian"),
+    (0x0590, 0x05FF, "Hebrew"),
+    (0x0600, 0x06FF, "Arabic"),
+    (0x0700, 0x074F, "Syriac"),
+    (0x0900, 0x097F, "Devanagari"),
+    (0x0980, 0x09FF, "Bengali"),
+    (0x0E00, 0x0E7F, "Thai"),
+    (0x10A0, 0x10FF, "Georgian"),
+    (0x1100, 0x11FF, "Hangul Jamo"),
+    (0x13A0, 0x13FF, "Cherokee"),
+    (0x1E00, 0x1EFF, "Latin Extended Additional"),
+    (0x1F00, 0x1FFF, "Greek Extended"),
+    (0x2000, 0x206F, "General Punctuation"),
+    (0x2070, 0x209F, "Superscripts and Subscripts"),
+    (0x20A0, 0x20CF, "Currency Symbols"),
+    (0x20D0, 0x20FF, "Combining Diacritical Marks for Symbols"),
+    (0x2100, 0x214F, "Letterlike Symbols"),
+    (0x2150, 0x218F, "Number Forms"),
+    (0x2190, 0x21FF, "Arrows"),
+    (0x2200, 0x22FF, "Mathematical Operators"),
+    (0x2300, 0x23FF, "Miscellaneous Technical"),
+    (0x2400, 0x243F, "Control Pictures"),
+    (0x2460, 0x24FF, "Enclosed Alphanumerics"),
+    (0x2500, 0x257F, "Box Drawing"),
+    (0x2580, 0x259F, "Block Elements"),
+    (0x25A0, 0x25FF, "Geometric Shapes"),
+    (0x2600, 0x26FF, "Miscellaneous Symbols"),
+    (0x2700, 0x27BF, "Dingbats"),
+    (0x27C0, 0x27EF, "Miscellaneous Mathematical Symbols-A"),
+    (0x27F0, 0x27FF, "Supplemental Arrows-A"),
+    (0x2800, 0x28FF, "Braille Patterns"),
+    (0x2900, 0x297F, "Supplemental Arrows-B"),
+    (0x2980, 0x29FF, "Miscellaneous Mathematical Symbols-B"),
+    (0x2A00, 0x2AFF, "Supplemental Mathematical Operators"),
+    (0x2B00, 0x2BFF, "Miscellaneous Symbols and Arrows"),
+    (0x2E00, 0x2E7F, "Supplemental Punctuation"),
+    (0x3000, 0x303F, "CJK Symbols and Punctuation"),
+    (0x3040, 0x309F, "Hiragana"),
+    (0x30A0, 0x30FF, "Katakana"),
+    (0x3100, 0x312F, "Bopomofo"),
+    (0x3130, 0x318F, "Hangul Compatibility Jamo"),
+    (0x3200, 0x32FF, "Enclosed CJK Letters and Months"),
+    (0x3300, 0x33FF, "CJK Compatibility"),
+    (0x3400, 0x4DBF, "CJK Unified Ideographs Extension A"),
+    (0x4DC0, 0x4DFF, "Yijing Hexagram Symbols"),
+    (0x4E00, 0x9FFF, "CJK Unified Ideographs"),
+    (0xA000, 0xA48F, "Yi Syllables"),
+    (0xAC00, 0xD7AF, "Hangul Syllables"),
+    (0xE000, 0xF8FF, "Private Use Area"),
+    (0xF900, 0xFAFF, "CJK Compatibility Ideographs"),
+    (0xFB00, 0xFB4F, "Alphabetic Presentation Forms"),
+    (0xFB50, 0xFDFF, "Arabic Presentation Forms-A"),
+    (0xFE00, 0xFE0F, "Variation Selectors"),
+    (0xFE10, 0xFE1F, "Vertical Forms"),
+    (0xFE20, 0xFE2F, "Combining Half Marks"),
+    (0xFE30, 0xFE4F, "CJK Compatibility Forms"),
+    (0xFE50, 0xFE6F, "Small Form Variants"),
+    (0xFE70, 0xFEFF, "Arabic Presentation Forms-B"),
+    (0xFF00, 0xFFEF, "Halfwidth and Fullwidth Forms"),
+    (0xFFF0, 0xFFFF, "Specials"),
+    (0x10000, 0x1007F, "Linear B Syllabary"),
+    (0x1D100, 0x1D1FF, "Musical Symbols"),
+    (0x1D400, 0x1D7FF, "Mathematical Alphanumeric Symbols"),
+    (0x1F000, 0x1F02F, "Mahjong Tiles"),
+    (0x1F030, 0x1F09F, "Domino Tiles"),
+    (0x1F0A0, 0x1F0FF, "Playing Cards"),
+    (0x1F100, 0x1F1FF, "Enclosed Alphanumeric Supplement"),
+    (0x1F200, 0x1F2FF, "Enclosed Ideographic Supplement"),
+    (0x1F300, 0x1F5FF, "Miscellaneous Symbols and Pictographs"),
+    (0x1F600, 0x1F64F, "Emoticons"),
+    (0x1F650, 0x1F67F, "Ornamental Dingbats"),
+    (0x1F680, 0x1F6FF, "Transport and Map Symbols"),
+    (0x1F700, 0x1F77F, "Alchemical Symbols"),
+    (0x1F780, 0x1F7FF, "Geometric Shapes Extended"),
+    (0x1F800, 0x1F8FF, "Supplemental Arrows-C"),
+    (0x1F900, 0x1F9FF, "Supplemental Symbols and Pictographs"),
+    (0x1FA00, 0x1FA6F, "Chess Symbols"),
+    (0x1FA70, 0x1FAFF, "Symbols and Pictographs Extended-A"),
+    (0x1FB00, 0x1FBFF, "Symbols for Legacy Computing"),
+    (0x20000, 0x2A6DF, "CJK Unified Ideographs Extension B"),
+    (0xE0000, 0xE007F, "Tags"),
+    (0xE0100, 0xE01EF, "Variation Selectors Supplement"),
+    (0xF0000, 0xFFFFF, "Supplementary Private Use Area-A"),
+    (0x100000, 0x10FFFF, "Supplementary Private Use Area-B"),
+];
+
+/// Codepoints that are emoji, or can be shown as one with U+FE0F
+const EMOJI: &[(u32, u32)] = &[
+    (0x00A9, 0x00A9),
+    (0x00AE, 0x00AE),
+    (0x203C, 0x203C),
+    (0x2049, 0x2049),
+    (0x2122, 0x2122),
+    (0x2139, 0x2139),
+    (0x2194, 0x21AA),
+    (0x231A, 0x231B),
+    (0x2328, 0x2328),
+    (0x23CF, 0x23FA),
+    (0x24C2, 0x24C2),
+    (0x25AA, 0x25FE),
+    (0x2600, 0x27BF),
+    (0x2934, 0x2935),
+    (0x2B05, 0x2B55),
+    (0x3030, 0x3030),
+    (0x303D, 0x303D),
+    (0x3297, 0x3299),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F170, 0x1F251),
+    (0x1F300, 0x1F6FF),
+    (0x1F7E0, 0x1F7F0),
+    (0x1F900, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+];
+
+/// `UNICODE_CODEPOINTS` (`U+1F600 U+FE0F`), `UNICODE_BLOCKS` (those the table knows, `;`
+/// separated), `UNICODE_CLASSES` (letter, number, symbol, emoji, space, control, format or
+/// private_use, per character), escapes of the non-ASCII characters as `UNICODE_ESCAPE`
+/// (`\u{1F600}`), `UNICODE_ESCAPE_JSON` (`\uD83D\uDE00`, also for JavaScript and Java),
+/// `UNICODE_ESCAPE_PYTHON` (`\U0001F600`) and `UNICODE_HTML` (`&#x1F600;`), and the bytes as
+/// `UNICODE_UTF8` (`F0 9F 98 80`). Latin letters, however accented, aren't unusual.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() || chars.len() > MAX_CODEPOINTS || chars.iter().any(|c| c.is_whitespace()) {
+        return None;
+    }
+    // ASCII and Latin letters, up to Latin Extended-B
+    if chars.iter().all(|&c| c.is_ascii() || c.is_alphabetic() && u32::from(c) <= 0x24F) {
+        return None;
+    }
+    let escape = |format: fn(char) -> String| {
+        chars.iter().map(|&c| if c.is_ascii() { c.to_string() } else { format(c) }).collect::<String>()
+    };
+    Some(vec![
+        ("UNICODE_CODEPOINTS".to_string(), chars.iter().map(|&c| format!("U+{:04X}", u32::from(c))).join(" ")),
+        ("UNICODE_BLOCKS".to_string(), chars.iter().filter_map(|&c| block(c)).unique().join("; ")),
+        ("UNICODE_CLASSES".to_string(), chars.iter().map(|&c| class(c)).join(" ")),
+        ("UNICODE_ESCAPE".to_string(), escape(|c| format!("\\u{{{:X}}}", u32::from(c)))),
+        (
+            "UNICODE_ESCAPE_JSON".to_string(),
+            escape(|c| c.encode_utf16(&mut [0; 2]).iter().map(|unit| format!("\\u{unit:04X}")).collect()),
+        ),
+        (
+            "UNICODE_ESCAPE_PYTHON".to_string(),
+            escape(|c| match u32::from(c) {
+                code @ ..=0xFFFF => format!("\\u{code:04X}"),
+                code => format!("\\U{code:08X}"),
+            }),
+        ),
+        ("UNICODE_HTML".to_string(), escape(|c| format!("&#x{:X};", u32::from(c)))),
+        ("UNICODE_UTF8".to_string(), text.bytes().map(|byte| format!("{byte:02X}")).join(" ")),
+    ])
+}
+
+/// A single emoji, including flags, keycaps and ZWJ sequences: `EMOJI`, `EMOJI_KIND` (`emoji`,
+/// `sequence`, `flag` or `keycap`) and for a flag `EMOJI_REGION` (`DE`, or `GBSCT` for a
+/// subdivision), else empty
+pub fn detect_emoji(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim();
+    let chars: Vec<char> = text.chars().collect();
+    let (kind, region) = match chars.as_slice() {
+        [first, second] if is_regional_indicator(*first) && is_regional_indicator(*second) => {
+            let region = [first, second].iter().map(|&&c| regional_letter(c)).collect::<Option<String>>()?;
+            ("flag", region)
+        }
+        [base, VARIATION_SELECTOR, KEYCAP] | [base, KEYCAP] if base.is_ascii_digit() || matches!(base, '#' | '*') => {
+            ("keycap", String::new())
+        }
+        [BLACK_FLAG, tags @ .., CANCEL_TAG] if !tags.is_empty() => {
+            let region = tags
+                .iter()
+                .map(|&c| char::from_u32(u32::from(c).checked_sub(0xE0000)?).filter(char::is_ascii_alphanumeric))
+                .collect::<Option<String>>()?;
+            ("flag", region.to_ascii_uppercase())
+        }
+        _ => {
+            let parts: Vec<&[char]> = chars.split(|&c| c == ZWJ).collect();
+            if chars.len() > MAX_CODEPOINTS || !parts.iter().all(|part| is_emoji_element(part)) {
+                return None;
+            }
+            (if parts.len() > 1 { "sequence" } else { "emoji" }, String::new())
+        }
+    };
+    Some(vec![
+        ("EMOJI".to_string(), text.to_string()),
+        ("EMOJI_KIND".to_string(), kind.to_string()),
+        ("EMOJI_REGION".to_string(), region),
+    ])
+}
+
+/// An emoji, maybe followed by U+FE0F and a skin tone modifier
+fn is_emoji_element(part: &[char]) -> bool {
+    let Some((&base, modifiers)) = part.split_first() else {
+        return false;
+    };
+    let is_skin_tone = |c: char| ('\u{1F3FB}'..='\u{1F3FF}').contains(&c);
+    in_ranges(base, EMOJI)
+        && !is_skin_tone(base)
+        && match modifiers {
+            [] => true,
+            [modifier] => *modifier == VARIATION_SELECTOR || is_skin_tone(*modifier),
+            [VARIATION_SELECTOR, tone] => is_skin_tone(*tone),
+            _ => false,
+        }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn regional_letter(c: char) -> Option<char> {
+    char::from_u32(u32::from(c) - 0x1F1E6 + u32::from(b'A'))
+}
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let code = u32::from(c);
+    ranges.iter().any(|&(start, end)| (start..=end).contains(&code))
+}
+
+fn block(c: char) -> Option<&'static str> {
+    let code = u32::from(c);
+    BLOCKS.iter().find(|&&(start, end, _)| (start..=end).contains(&code)).map(|&(_, _, name)| name)
+}
+
+fn class(c: char) -> &'static str {
+    let code = u32::from(c);
+    if c.is_whitespace() {
+        "space"
+    } else if c.is_control() {
+        "control"
+    } else if matches!(code, 0x200B..=0x200F | 0x2060..=0x206F | 0xFE00..=0xFE0F | 0xFEFF | 0xE0000..=0xE01EF) {
+        "format"
+    } else if matches!(code, 0xE000..=0xF8FF | 0xF0000..) {
+        "private_use"
+    } else if in_ranges(c, EMOJI) || is_regional_indicator(c) {
+        "emoji"
+    } else if c.is_alphabetic() {
+        "letter"
+    } else if c.is_numeric() {
+        "number"
+    } else {
+        "symbol"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn escapes_a_symbol_every_way() {
+        let fields = detect("→");
+        assert_eq!(field(&fields, "UNICODE_CODEPOINTS"), "U+2192");
+        assert_eq!(field(&fields, "UNICODE_BLOCKS"), "Arrows");
+        assert_eq!(field(&fields, "UNICODE_CLASSES"), "symbol");
+        assert_eq!(field(&fields, "UNICODE_ESCAPE"), "\\u{2192}");
+        assert_eq!(field(&fields, "UNICODE_HTML"), "&#x2192;");
+        assert_eq!(field(&fields, "UNICODE_UTF8"), "E2 86 92");
+    }
+
+    #[test]
+    fn astral_characters_escape_as_surrogates_for_json() {
+        let fields = detect("😀");
+        assert_eq!(field(&fields, "UNICODE_ESCAPE_JSON"), "\\uD83D\\uDE00");
+        assert_eq!(field(&fields, "UNICODE_ESCAPE_PYTHON"), "\\U0001F600");
+        assert_eq!(field(&fields, "UNICODE_BLOCKS"), "Emoticons");
+        assert_eq!(field(&fields, "UNICODE_CLASSES"), "emoji");
+    }
+
+    #[test]
+    fn finds_invisible_characters_among_ascii() {
+        let fields = detect("a\u{200B}b");
+        assert_eq!(field(&fields, "UNICODE_CLASSES"), "letter format letter");
+        assert_eq!(field(&fields, "UNICODE_ESCAPE"), "a\\u{200B}b");
+    }
+
+    #[test]
+    fn latin_text_is_not_unusual() {
+        for text in ["café", "hello world", "Ærøskøbing", "", "→→→→→→→→→→→→→"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn tells_emoji_kinds_apart() {
+        let kind = |text: &str| field(&detect_emoji(text), "EMOJI_KIND");
+        assert_eq!(kind("😀"), "emoji");
+        assert_eq!(kind("👍🏽"), "emoji");
+        assert_eq!(kind("1\u{FE0F}\u{20E3}"), "keycap");
+        assert_eq!(kind("👨\u{200D}👩\u{200D}👧"), "sequence");
+        assert_eq!(field(&detect_emoji("🇩🇪"), "EMOJI_REGION"), "DE");
+        let scotland = "\u{1F3F4}\u{E0067}\u{E0062}\u{E0073}\u{E0063}\u{E0074}\u{E007F}";
+        assert_eq!(field(&detect_emoji(scotland), "EMOJI_REGION"), "GBSCT");
+    }
+
+    #[test]
+    fn rejects_several_emoji_or_none() {
+        for text in ["a", "😀😀", "🏽", "😀 ok"] {
+            assert!(detect_emoji(text).is_none(), "{text:?}");
+        }
+    }
+}
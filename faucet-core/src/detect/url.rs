@@ -0,0 +1,133 @@
+//! Web links, inspected for the tricks phishing links use, so suspicious ones can be routed to a
+//! safe preview rather than the browser.
+
+/// `URL_SCHEME`, `URL_HOST` and `1`/`0` flags: `URL_IS_PUNYCODE` (an `xn--` label),
+/// `URL_HAS_USERINFO` (`https://bank.com@evil.com/`), `URL_IP_HOST` (a bare IP, also in decimal
+/// or hex) and `URL_MIXED_SCRIPT` (a label mixing e.g. Latin and Cyrillic letters).
+/// `URL_WARNINGS` lists the flags that are set, comma-separated.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let url = text.trim();
+    if url.contains(char::is_whitespace) {
+        return None;
+    }
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    if !matches!(scheme.as_str(), "http" | "https" | "ftp") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((userinfo, host_port)) => (Some(userinfo), host_port),
+        None => (None, authority),
+    };
+    let host = if host_port.starts_with('[') {
+        // IPv6, with the brackets
+        host_port.split_inclusive(']').next().unwrap_or(host_port)
+    } else {
+        host_port.split(':').next().unwrap_or(host_port)
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.is_empty() {
+        return None;
+    }
+
+    let flags = [
+        ("URL_IS_PUNYCODE", "punycode host", host.split('.').any(|label| label.starts_with("xn--"))),
+        ("URL_HAS_USERINFO", "userinfo", userinfo.is_some()),
+        ("URL_IP_HOST", "IP address host", is_ip(&host)),
+        ("URL_MIXED_SCRIPT", "mixed-script host", host.split('.').any(is_mixed_script)),
+    ];
+    let warnings: Vec<&str> = flags.iter().filter(|(_, _, set)| *set).map(|(_, warning, _)| *warning).collect();
+    let mut vars = vec![
+        ("URL_SCHEME".to_string(), scheme),
+        ("URL_HOST".to_string(), host),
+        ("URL_WARNINGS".to_string(), warnings.join(", ")),
+    ];
+    vars.extend(flags.iter().map(|(name, _, set)| (name.to_string(), if *set { "1" } else { "0" }.to_string())));
+    Some(vars)
+}
+
+/// A link `detect` has warnings for
+pub fn detect_suspicious(text: &str) -> Option<Vec<(String, String)>> {
+    let vars = detect(text)?;
+    vars.iter()
+        .any(|(name, value)| name == "URL_WARNINGS" && !value.is_empty())
+        .then(Vec::new)
+}
+
+/// Dotted IPv4, IPv6 in brackets, or the single-number forms browsers also accept
+/// (`http://3232235777/`, `http://0xc0a80001/`)
+fn is_ip(host: &str) -> bool {
+    let ipv6 = host.strip_prefix('[').and_then(|host| host.strip_suffix(']'));
+    let hex = host.strip_prefix("0x");
+    host.parse::<std::net::Ipv4Addr>().is_ok()
+        || ipv6.is_some_and(|host| host.parse::<std::net::Ipv6Addr>().is_ok())
+        || host.bytes().all(|byte| byte.is_ascii_digit())
+        || hex.is_some_and(|hex| !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+}
+
+#[derive(PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Other,
+}
+
+fn script(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    Some(match c as u32 {
+        0x41..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+        0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x400..=0x52F => Script::Cyrillic,
+        _ => Script::Other,
+    })
+}
+
+/// Whether the label's letters come from more than one script
+fn is_mixed_script(label: &str) -> bool {
+    let mut scripts = label.chars().filter_map(script);
+    let Some(first) = scripts.next() else {
+        return false;
+    };
+    scripts.any(|other| other != first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn a_plain_link_has_no_warnings() {
+        let fields = detect("HTTPS://Example.COM./path?q=1");
+        assert_eq!(field(&fields, "URL_SCHEME"), "https");
+        assert_eq!(field(&fields, "URL_HOST"), "example.com");
+        assert_eq!(field(&fields, "URL_WARNINGS"), "");
+        assert_eq!(field(&fields, "URL_HAS_USERINFO"), "0");
+        assert!(detect_suspicious("https://example.com/").is_none());
+    }
+
+    #[test]
+    fn flags_phishing_tricks() {
+        let userinfo = detect("https://bank.com@evil.example/login");
+        assert_eq!(field(&userinfo, "URL_HOST"), "evil.example");
+        assert_eq!(field(&userinfo, "URL_WARNINGS"), "userinfo");
+        for ip in ["http://3232235777/", "http://0xc0a80001/", "http://[::1]:8080/", "http://192.168.0.1/"] {
+            assert_eq!(field(&detect(ip), "URL_IP_HOST"), "1", "{ip}");
+        }
+        assert_eq!(field(&detect("https://xn--80ak6aa92e.com/"), "URL_IS_PUNYCODE"), "1");
+        // A Cyrillic а among Latin letters
+        assert_eq!(field(&detect("https://p\u{430}ypal.com/"), "URL_MIXED_SCRIPT"), "1");
+        assert!(detect_suspicious("https://bank.com@evil.example/").is_some());
+    }
+
+    #[test]
+    fn only_web_links_are_inspected() {
+        for text in ["example.com", "mailto:a@example.com", "ssh://host/", "https://", "see https://example.com"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+}
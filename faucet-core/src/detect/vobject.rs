@@ -0,0 +1,171 @@
+//! iCalendar (`BEGIN:VCALENDAR`) and vCard (`BEGIN:VCARD`) payloads.
+
+/// `ICAL_SUMMARY`, `ICAL_START`, `ICAL_END`, `ICAL_LOCATION`, `ICAL_DESCRIPTION` of the first
+/// event (or todo), with dates as ISO 8601, plus `ICAL_EVENTS` (a count)
+pub fn detect_calendar(text: &str) -> Option<Vec<(String, String)>> {
+    let properties = properties(text, "VCALENDAR")?;
+    let events = properties
+        .iter()
+        .filter(|(name, value)| name == "BEGIN" && (value == "VEVENT" || value == "VTODO"))
+        .count();
+    let first_event = properties
+        .iter()
+        .skip_while(|(name, value)| !(name == "BEGIN" && (value == "VEVENT" || value == "VTODO")))
+        .take_while(|(name, value)| !(name == "END" && (value == "VEVENT" || value == "VTODO")));
+    let mut vars = vec![
+        ("ICAL_SUMMARY".to_string(), String::new()),
+        ("ICAL_START".to_string(), String::new()),
+        ("ICAL_END".to_string(), String::new()),
+        ("ICAL_LOCATION".to_string(), String::new()),
+        ("ICAL_DESCRIPTION".to_string(), String::new()),
+    ];
+    for (name, value) in first_event {
+        let (var, value) = match name.as_str() {
+            "SUMMARY" => ("ICAL_SUMMARY", value.clone()),
+            "DTSTART" => ("ICAL_START", iso_date(value)),
+            "DTEND" | "DUE" => ("ICAL_END", iso_date(value)),
+            "LOCATION" => ("ICAL_LOCATION", value.clone()),
+            "DESCRIPTION" => ("ICAL_DESCRIPTION", value.clone()),
+            _ => continue,
+        };
+        if let Some((_, slot)) = vars.iter_mut().find(|(key, _)| key == var)
+        && slot.is_empty()
+        {
+            *slot = value;
+        }
+    }
+    vars.push(("ICAL_EVENTS".to_string(), events.to_string()));
+    Some(vars)
+}
+
+/// `VCARD_NAME`, `VCARD_EMAIL`, `VCARD_TEL`, `VCARD_ORG` of the first card, plus `VCARD_COUNT`
+pub fn detect_card(text: &str) -> Option<Vec<(String, String)>> {
+    let properties = properties(text, "VCARD")?;
+    let count = properties
+        .iter()
+        .filter(|(name, value)| name == "BEGIN" && value == "VCARD")
+        .count();
+    let first = |wanted: &str| {
+        properties
+            .iter()
+            .take_while(|(name, value)| !(name == "END" && value == "VCARD"))
+            .find(|(name, _)| name == wanted)
+            .map(|(_, value)| value.clone())
+    };
+    let name = first("FN").or_else(|| {
+        // N is family;given;additional;prefix;suffix
+        first("N").map(|n| {
+            let mut parts: Vec<&str> = n.split(';').take(2).filter(|part| !part.is_empty()).collect();
+            parts.reverse();
+            parts.join(" ")
+        })
+    });
+    Some(vec![
+        ("VCARD_NAME".to_string(), name.unwrap_or_default()),
+        ("VCARD_EMAIL".to_string(), first("EMAIL").unwrap_or_default()),
+        ("VCARD_TEL".to_string(), first("TEL").unwrap_or_default()),
+        ("VCARD_ORG".to_string(), first("ORG").map(|org| org.replace(';', ", ")).unwrap_or_default()),
+        ("VCARD_COUNT".to_string(), count.to_string()),
+    ])
+}
+
+/// Unfolded content lines as upper-cased names (parameters dropped) and unescaped values,
+/// if the text is a `BEGIN:<component>` object
+fn properties(text: &str, component: &str) -> Option<Vec<(String, String)>> {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let first_line = text.lines().next()?.trim_end();
+    if !first_line.eq_ignore_ascii_case(&format!("BEGIN:{component}")) {
+        return None;
+    }
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    Some(
+        lines
+            .iter()
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                let name = name.split(';').next()?.to_ascii_uppercase();
+                let value = value
+                    .replace("\\n", "\n")
+                    .replace("\\N", "\n")
+                    .replace("\\,", ",")
+                    .replace("\\;", ";")
+                    .replace("\\\\", "\\");
+                Some((name, value.trim().to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// `20240501T100000Z` -> `2024-05-01T10:00:00Z`, `20240501` -> `2024-05-01`; anything else is kept
+fn iso_date(value: &str) -> String {
+    let (date, time) = value.split_once('T').unwrap_or((value, ""));
+    if date.len() != 8 || !date.bytes().all(|byte| byte.is_ascii_digit()) {
+        return value.to_string();
+    }
+    let date = format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]);
+    let (clock, zone) = time.split_at(time.len().min(6));
+    if clock.len() == 6 && clock.bytes().all(|byte| byte.is_ascii_digit()) {
+        format!("{date}T{}:{}:{}{zone}", &clock[..2], &clock[2..4], &clock[4..])
+    } else {
+        date
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn reads_the_first_event_of_a_calendar() {
+        let calendar = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+SUMMARY:Team sync\\, weekly and a very long\r
+  title\r
+DTSTART:20240501T100000Z\r
+DTEND;TZID=Europe/Berlin:20240501T110000\r
+LOCATION:Room 1\r
+DESCRIPTION:Line one\\nline two\r
+END:VEVENT\r
+BEGIN:VTODO\r
+SUMMARY:Other\r
+DUE;VALUE=DATE:20240502\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+        let fields = detect_calendar(calendar);
+        assert_eq!(field(&fields, "ICAL_SUMMARY"), "Team sync, weekly and a very long title");
+        assert_eq!(field(&fields, "ICAL_START"), "2024-05-01T10:00:00Z");
+        assert_eq!(field(&fields, "ICAL_END"), "2024-05-01T11:00:00");
+        assert_eq!(field(&fields, "ICAL_LOCATION"), "Room 1");
+        assert_eq!(field(&fields, "ICAL_DESCRIPTION"), "Line one\nline two");
+        assert_eq!(field(&fields, "ICAL_EVENTS"), "2");
+    }
+
+    #[test]
+    fn reads_a_card_building_the_name_from_n() {
+        let fields = detect_card(
+            "BEGIN:VCARD\nVERSION:4.0\nN:Doe;Jane;;;\nEMAIL;TYPE=work:jane@example.com\nTEL:+1 555 0100\nORG:Example;Research\nEND:VCARD\n",
+        );
+        assert_eq!(field(&fields, "VCARD_NAME"), "Jane Doe");
+        assert_eq!(field(&fields, "VCARD_EMAIL"), "jane@example.com");
+        assert_eq!(field(&fields, "VCARD_TEL"), "+1 555 0100");
+        assert_eq!(field(&fields, "VCARD_ORG"), "Example, Research");
+        assert_eq!(field(&fields, "VCARD_COUNT"), "1");
+    }
+
+    #[test]
+    fn each_detector_wants_its_own_component() {
+        assert!(detect_calendar("BEGIN:VCARD\nFN:Jane\nEND:VCARD").is_none());
+        assert!(detect_card("BEGIN:VCALENDAR\nEND:VCALENDAR").is_none());
+        assert!(detect_card("Hello\nBEGIN:VCARD").is_none());
+    }
+}
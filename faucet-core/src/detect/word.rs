@@ -0,0 +1,55 @@
+//! A single word or short phrase, as copied to look up in a dictionary or translate.
+
+/// `WORD` (the phrase, single spaced, without surrounding quotes or trailing punctuation) and
+/// `WORD_COUNT`. Words are letters, with inner apostrophes and hyphens (`don't`, `well-known`);
+/// `word_max_words` limits how many make a phrase.
+pub fn detect(text: &str) -> Option<Vec<(String, String)>> {
+    let text = text
+        .trim()
+        .trim_matches(['"', '\'', '“', '”', '‘', '’', '«', '»'])
+        .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    if text.is_empty() || text.contains('\n') || text.chars().count() > 64 {
+        return None;
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if !words.iter().all(|word| is_word(word)) {
+        return None;
+    }
+    Some(vec![
+        ("WORD".to_string(), words.join(" ")),
+        ("WORD_COUNT".to_string(), words.len().to_string()),
+    ])
+}
+
+fn is_word(word: &str) -> bool {
+    let starts_and_ends_with_letter = word.chars().next().is_some_and(char::is_alphabetic)
+        && word.chars().next_back().is_some_and(char::is_alphabetic);
+    starts_and_ends_with_letter && word.chars().all(|c| c.is_alphabetic() || matches!(c, '\'' | '’' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::field;
+
+    #[test]
+    fn trims_quotes_and_trailing_punctuation() {
+        let fields = detect("“Serendipity.”");
+        assert_eq!(field(&fields, "WORD"), "Serendipity");
+        assert_eq!(field(&fields, "WORD_COUNT"), "1");
+    }
+
+    #[test]
+    fn phrases_keep_inner_apostrophes_and_hyphens() {
+        let fields = detect("well-known  don't");
+        assert_eq!(field(&fields, "WORD"), "well-known don't");
+        assert_eq!(field(&fields, "WORD_COUNT"), "2");
+    }
+
+    #[test]
+    fn rejects_anything_but_letters() {
+        for text in ["abc123", "-dash", "two\nlines", "", "x = 1"] {
+            assert!(detect(text).is_none(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Config errors rendered with their location and a caret excerpt of the offending line.
+
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+pub struct Location {
+    /// One-based
+    pub line: usize,
+    /// One-based
+    pub column: usize,
+}
+
+pub struct Diagnostic {
+    /// Where in the config the problem is, e.g. `scorers[3].command_label`
+    pub path: String,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    fn render(&self, file: &Path, source: &str) -> String {
+        let mut rendered = if self.path.is_empty() || self.path == "." {
+            format!("error: {}", self.message)
+        } else {
+            format!("error: {}: {}", self.path, self.message)
+        };
+        let Some(location) = &self.location else {
+            rendered.push_str(&format!("\n  --> {}", file.display()));
+            return rendered;
+        };
+        rendered.push_str(&format!(
+            "\n  --> {}:{}:{}",
+            file.display(),
+            location.line,
+            location.column
+        ));
+        if let Some(line) = source.lines().nth(location.line.saturating_sub(1)) {
+            let gutter = " ".repeat(location.line.to_string().len());
+            let caret_offset: String = line
+                .chars()
+                .take(location.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            rendered.push_str(&format!(
+                "\n{gutter} |\n{} | {line}\n{gutter} | {caret_offset}^",
+                location.line
+            ));
+        }
+        rendered
+    }
+}
+
+pub fn render_all(diagnostics: &[Diagnostic], file: &Path, source: &str) -> String {
+    let rendered = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.render(file, source))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "{} problem{} in config:\n\n{rendered}",
+        diagnostics.len(),
+        if diagnostics.len() == 1 { "" } else { "s" }
+    )
+}
+
+/// Deserializes the config in the format its file extension names: TOML for `.toml`, else YAML
+pub fn parse_config<T: DeserializeOwned>(source: &str, file: &Path) -> Result<T> {
+    if file.extension().is_some_and(|extension| extension == "toml") {
+        parse_toml(source, file)
+    } else {
+        parse_yaml(source, file)
+    }
+}
+
+/// Deserializes YAML, reporting the failing key path and source location on error
+pub fn parse_yaml<T: DeserializeOwned>(source: &str, file: &Path) -> Result<T> {
+    serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(source)).map_err(|e| {
+        let path = e.path().to_string();
+        let location = e.inner().location().map(|location| Location {
+            line: location.line(),
+            column: location.column(),
+        });
+        // serde_yaml's message already embeds the path and location, which the rendering repeats
+        let mut message = e.inner().to_string();
+        if let Some(location) = &location {
+            let suffix = format!(" at line {} column {}", location.line, location.column);
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+        }
+        // An error raised once a sequence item is read has the path of the sequence
+        if let Some((prefix, stripped)) = message.split_once(": ")
+            && !prefix.is_empty()
+            && path.starts_with(prefix)
+        {
+            message = stripped.to_string();
+        }
+        let diagnostic = Diagnostic {
+            path,
+            message,
+            location,
+        };
+        anyhow!("{}", render_all(&[diagnostic], file, source))
+    })
+}
+
+/// Deserializes TOML, reporting the failing key path and source location on error
+pub fn parse_toml<T: DeserializeOwned>(source: &str, file: &Path) -> Result<T> {
+    let error = |path: String, error: &toml::de::Error| {
+        let diagnostic = Diagnostic {
+            path,
+            message: error.message().trim_end().to_string(),
+            location: error.span().map(|span| offset_location(source, span.start)),
+        };
+        anyhow!("{}", render_all(&[diagnostic], file, source))
+    };
+    let deserializer = toml::de::Deserializer::parse(source).map_err(|e| error(String::new(), &e))?;
+    serde_path_to_error::deserialize(deserializer).map_err(|e| error(e.path().to_string(), e.inner()))
+}
+
+fn offset_location(source: &str, offset: usize) -> Location {
+    let before = &source[..offset.min(source.len())];
+    Location {
+        line: before.matches('\n').count() + 1,
+        column: before.rsplit('\n').next().unwrap_or_default().chars().count() + 1,
+    }
+}
+
+/// Finds the first occurrence of `needle` anywhere in the source
+pub fn locate(source: &str, needle: &str) -> Option<Location> {
+    source.lines().enumerate().find_map(|(line_idx, line)| {
+        line.find(needle).map(|byte_idx| Location {
+            line: line_idx + 1,
+            column: line[..byte_idx].chars().count() + 1,
+        })
+    })
+}
+
+/// Finds the first occurrence of `needle` at or after the first line containing `anchor`
+pub fn locate_after(source: &str, anchor: &str, needle: &str) -> Option<Location> {
+    let anchor_line = source.lines().position(|line| line.contains(anchor))?;
+    source.lines().enumerate().skip(anchor_line).find_map(|(line_idx, line)| {
+        line.find(needle).map(|byte_idx| Location {
+            line: line_idx + 1,
+            column: line[..byte_idx].chars().count() + 1,
+        })
+    })
+}
+
+/// Finds `needle` within item `index` of the block sequence under top-level `key` (or, in TOML,
+/// of the `[[key]]` tables). Returns `None` when it can't place the value.
+pub fn locate_in_sequence(source: &str, key: &str, index: usize, needle: &str) -> Option<Location> {
+    let lines: Vec<&str> = source.lines().collect();
+    let (start, end) = match lines
+        .iter()
+        .position(|line| line.trim_end().strip_prefix(key) == Some(":"))
+    {
+        Some(key_line) => {
+            let mut items = lines
+                .iter()
+                .enumerate()
+                .skip(key_line + 1)
+                .take_while(|(_, line)| line.trim().is_empty() || line.starts_with([' ', '-', '#']))
+                .filter(|(_, line)| line.trim_start().starts_with("- "))
+                .map(|(line_idx, line)| (line_idx, line.len() - line.trim_start().len()));
+            let (_, item_indent) = items.clone().next()?;
+            let mut item_starts = items.by_ref().filter(|(_, indent)| *indent == item_indent);
+            let start = item_starts.nth(index)?.0;
+            (start, item_starts.next().map_or(lines.len(), |(line_idx, _)| line_idx))
+        }
+        None => {
+            let header = format!("[[{key}]]");
+            let start = lines.iter().enumerate().filter(|(_, line)| line.trim() == header).nth(index)?.0;
+            // The item's own subtables (`[scorers.when]`) belong to it
+            let end = lines
+                .iter()
+                .enumerate()
+                .skip(start + 1)
+                .find(|(_, line)| {
+                    let line = line.trim_start();
+                    line.starts_with('[') && !line.starts_with(&format!("[{key}."))
+                })
+                .map_or(lines.len(), |(line_idx, _)| line_idx);
+            (start, end)
+        }
+    };
+    (start..end).find_map(|line_idx| {
+        lines[line_idx].find(needle).map(|byte_idx| Location {
+            line: line_idx + 1,
+            column: lines[line_idx][..byte_idx].chars().count() + 1,
+        })
+    })
+}
@@ -0,0 +1,48 @@
+//! The `define` builtin: looks a word up with a local dictionary client (`sdcv`, else `dict`)
+//! and shows the definition in the pager or a notification.
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Characters of the definition a notification shows
+pub const NOTIFY_CHARS: usize = 400;
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Show {
+    /// Opens the definition in the pager
+    #[default]
+    Pager,
+    /// Sends the start of the definition as a notification
+    Notify,
+}
+
+/// The definition of `word` from `program` (given the word as its last argument), else `sdcv -n`
+/// when installed, else `dict`
+pub fn look_up(program: Option<&str>, word: &str) -> Result<String> {
+    let fallback = if crate::check_command_exists("sdcv").is_ok() { "sdcv -n" } else { "dict" };
+    let argv = crate::resolve_program(program, &[], fallback);
+    let Some((program, args)) = argv.split_first() else {
+        bail!("No dictionary program configured");
+    };
+    let output = std::process::Command::new(program).args(args).arg(word).output()?;
+    let definition = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    // sdcv succeeds with a "Nothing similar to ..." apology
+    if !output.status.success() || definition.is_empty() || definition.starts_with("Nothing similar to") {
+        bail!("No definition found for '{word}'");
+    }
+    Ok(definition)
+}
+
+/// The start of `definition`, cut at a line break where one is near
+pub fn excerpt(definition: &str) -> String {
+    if definition.chars().count() <= NOTIFY_CHARS {
+        return definition.to_string();
+    }
+    let cut: String = definition.chars().take(NOTIFY_CHARS).collect();
+    let cut = match cut.rfind('\n') {
+        Some(end) if end > NOTIFY_CHARS / 2 => &cut[..end],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end())
+}
@@ -0,0 +1,75 @@
+//! The `download` builtin: fetches a plumbed link into the spool with curl, capped in size and
+//! checked against the content types it may serve, so the file itself (a PDF, an image) can be
+//! plumbed next and end up in a local viewer rather than the browser.
+
+use anyhow::{Result, bail};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+pub const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Downloads `url` to a new spool file named for its content type, returned with that type.
+/// Fails, leaving nothing behind, past `max_bytes` or when no `accept` pattern matches the type.
+pub fn fetch(url: &str, max_bytes: u64, accept: &[String], max_age: Duration) -> Result<(PathBuf, String)> {
+    let staged = crate::spool::create(None, max_age)?;
+    let result = fetch_to(url, &staged, max_bytes, accept).and_then(|mime| {
+        let path = crate::spool::create(Some(crate::scratch::mime_extension(&mime)), max_age)?;
+        std::fs::rename(&staged, &path)?;
+        Ok((path, mime))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staged);
+    }
+    result
+}
+
+fn fetch_to(url: &str, path: &Path, max_bytes: u64, accept: &[String]) -> Result<String> {
+    let headers = tempfile::NamedTempFile::new()?;
+    let mut curl = std::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--proto", "=http,https", "--dump-header"])
+        .arg(headers.path())
+        .arg("--")
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Could not run curl: {e}"))?;
+    let mut body = curl.stdout.take().expect("stdout is piped").take(max_bytes + 1);
+    let copied = std::io::copy(&mut body, &mut std::fs::File::create(path)?)?;
+    if copied > max_bytes {
+        let _ = curl.kill();
+        let _ = curl.wait();
+        bail!("'{url}' is larger than {max_bytes} bytes");
+    }
+    let mut stderr = String::new();
+    curl.stderr.take().expect("stderr is piped").read_to_string(&mut stderr)?;
+    if !curl.wait()?.success() {
+        bail!("Downloading '{url}' failed: {}", stderr.trim());
+    }
+    let mime = content_type(&std::fs::read_to_string(headers.path())?)
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    if !accept.is_empty() && !accept.iter().any(|pattern| crate::xdg::mime_matches(pattern, &mime)) {
+        bail!("'{url}' is {mime}, not one of: {}", accept.join(", "));
+    }
+    Ok(mime)
+}
+
+/// The final response's `Content-Type`, without parameters
+pub fn content_type(headers: &str) -> Option<String> {
+    header(headers, "content-type")
+        .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+        .filter(|mime| !mime.is_empty())
+}
+
+/// The value of the final response's `name` header; redirects' headers come first
+pub fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    let last_response = headers.rsplit("\nHTTP/").next().unwrap_or(headers);
+    last_response
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
@@ -0,0 +1,103 @@
+//! `faucet config dump`: prints the effective config, i.e. as faucet sees it after defaults,
+//! score constants and command line flags are applied, for dotfile managers to diff against.
+//!
+//! ```sh
+//! faucet config dump --format nix > faucet.nix
+//! ```
+
+use crate::Config;
+use anyhow::{Result, anyhow, bail};
+use serde_yaml::Value;
+
+pub fn run(config: &Config, args: &[String]) -> Result<()> {
+    let Some((action, args)) = args.split_first() else {
+        bail!("config requires an action: dump or validate");
+    };
+    if action != "dump" {
+        bail!("Unknown config action '{action}', expected one of: dump, validate");
+    }
+    let mut format = "yaml".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = args.next().cloned().ok_or_else(|| anyhow!("Missing value for '{arg}'"))?,
+            _ => bail!("Unknown config dump argument '{arg}'"),
+        }
+    }
+    let mut value = serde_yaml::to_value(config)?;
+    strip_nulls(&mut value);
+    let dumped = match format.as_str() {
+        "yaml" => serde_yaml::to_string(&value)?,
+        "toml" => toml::to_string(config)?,
+        "nix" => format!("{}\n", nix(&value, 0)),
+        _ => bail!("Unknown format '{format}', expected one of: nix, yaml, toml"),
+    };
+    print!("{dumped}");
+    Ok(())
+}
+
+/// Drops unset options, as TOML has no null and they only restate the defaults
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(entries) => {
+            entries.retain(|_, value| !value.is_null());
+            entries.values_mut().for_each(strip_nulls);
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(strip_nulls),
+        Value::Tagged(tagged) => strip_nulls(&mut tagged.value),
+        _ => {}
+    }
+}
+
+/// Renders `value` as a Nix expression, e.g. for home-manager's `pkgs.formats.yaml`
+fn nix(value: &Value, indent: usize) -> String {
+    let pad = "  ".repeat(indent + 1);
+    let close = "  ".repeat(indent);
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(bool) => bool.to_string(),
+        // A bare negative number in a list would read as a subtraction
+        Value::Number(number) if number.as_f64().is_some_and(|number| number < 0.0) => format!("({number})"),
+        Value::Number(number) => number.to_string(),
+        Value::String(string) => nix_string(string),
+        Value::Sequence(items) if items.is_empty() => "[ ]".to_string(),
+        Value::Sequence(items) => {
+            let items: String = items.iter().map(|item| format!("{pad}{}\n", nix(item, indent + 1))).collect();
+            format!("[\n{items}{close}]")
+        }
+        Value::Mapping(entries) if entries.is_empty() => "{ }".to_string(),
+        Value::Mapping(entries) => {
+            let entries: String = entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Value::String(key) => key.clone(),
+                        key => serde_yaml::to_string(key).unwrap_or_default().trim().to_string(),
+                    };
+                    format!("{pad}{} = {};\n", nix_key(&key), nix(value, indent + 1))
+                })
+                .collect();
+            format!("{{\n{entries}{close}}}")
+        }
+        Value::Tagged(tagged) => nix(&tagged.value, indent),
+    }
+}
+
+fn nix_key(key: &str) -> String {
+    const KEYWORDS: [&str; 10] = ["assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with"];
+    let mut chars = key.chars();
+    let identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '\''));
+    if identifier && !KEYWORDS.contains(&key) { key.to_string() } else { nix_string(key) }
+}
+
+fn nix_string(string: &str) -> String {
+    let escaped = string
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("\"{escaped}\"")
+}
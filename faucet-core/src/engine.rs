@@ -0,0 +1,144 @@
+//! `ScoreEngine`: the scoring pipeline for programs embedding faucet. It classifies and scores
+//! data as `faucet` would, with the config's scorers, detectors and derive command, and says how
+//! a plumb would end, but runs no command.
+//!
+//! A `pure` engine starts no processes either, so its outcome depends on nothing but the config
+//! and the bytes, which makes it fit for fuzzing and property tests:
+//!
+//! ```
+//! # #[cfg(feature = "x11")] {
+//! let engine = faucet_core::ScoreEngine::parse(
+//!     "options: {display_server: X11}
+//! commands: {browser: {display: Browser, builtin: browser}}
+//! scorers: [{regex: '^https://', command_label: browser, score_change: 20}]",
+//! )?
+//! .pure();
+//! assert!(matches!(engine.decide(b"https://example.com")?, faucet_core::Outcome::AutoSelect { .. }));
+//! # }
+//! # anyhow::Ok(())
+//! ```
+
+use crate::cache::ProbeCache;
+use crate::{
+    Config, Data, Decision, Plumb, Validation, PureRunner, Runner, ScoredCommand, ScoredCommands, SystemRunner, cli, mirrors, ranking,
+    rank_commands, score_commands,
+};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Named as the data's source where faucet would name the clipboard or stdin
+const SOURCE: &str = "library";
+
+/// How plumbing the data would end
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Outcome {
+    /// No command scored above zero
+    NoMatch,
+    /// The command would run without asking
+    AutoSelect { command: ScoredCommand },
+    /// The picker would offer these, best first
+    Menu { candidates: Vec<ScoredCommand> },
+}
+
+pub struct ScoreEngine {
+    config: Config,
+    cache: ProbeCache,
+    runner: Arc<dyn Runner>,
+}
+
+impl ScoreEngine {
+    /// Reads and validates the config at `path`, failing on every problem `faucet validate` reports
+    pub fn load(path: &Path) -> Result<Self> {
+        let (config, _) = crate::load_config(path, &cli::Flags::default(), Validation::Full)?;
+        Ok(Self::new(config))
+    }
+
+    /// Validates a YAML config given as text, as `load` does a file
+    pub fn parse(source: &str) -> Result<Self> {
+        let file = Path::new("faucet.yaml");
+        let mut config: Config = crate::diagnostic::parse_config(source, file)?;
+        crate::check_config(&mut config, source, file, Validation::Full)?;
+        Ok(Self::new(config))
+    }
+
+    fn new(config: Config) -> Self {
+        let cache = ProbeCache::new(config.options.probe_cache_size);
+        Self { config, cache, runner: Arc::new(SystemRunner) }
+    }
+
+    /// Starts the processes scoring needs (`file`, command scorers, the derive command) through
+    /// `runner`, e.g. a `MockRunner` a test keeps a handle on to check what was run
+    pub fn with_runner(mut self, runner: Arc<dyn Runner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Starts no processes at all: binary data is `application/octet-stream`, and command
+    /// scorers, the derive command and `ffprobe`-based detection never fire
+    pub fn pure(self) -> Self {
+        self.with_runner(Arc::new(PureRunner))
+    }
+
+    /// The commands `data` scores above zero for, best first, with the scorers behind each score.
+    /// Command scorers, and detectors needing a program, run as they would in `faucet`.
+    pub fn evaluate(&self, data: &Data) -> Result<Vec<ScoredCommand>> {
+        self.score(data, |plumb, scored_commands| ranking::candidates(plumb, &rank_commands(scored_commands), true))
+    }
+
+    /// Whether plumbing `bytes` (read as text when they are UTF-8) would auto-select a command,
+    /// show the picker or find nothing, with the config's thresholds
+    pub fn decide(&self, bytes: &[u8]) -> Result<Outcome> {
+        let data = Data::from_bytes(bytes.to_vec());
+        self.score(&data, |plumb, scored_commands| {
+            let mut options = self.config.options.clone();
+            options.apply_threshold_overrides(plumb.mime(), SOURCE);
+            match crate::decide(&options, rank_commands(scored_commands)) {
+                Decision::NoMatch => Outcome::NoMatch,
+                Decision::AutoSelect(ranked) => Outcome::AutoSelect {
+                    command: ranking::candidates(plumb, &[ranked], true).remove(0),
+                },
+                Decision::Menu(ranked) => Outcome::Menu { candidates: ranking::candidates(plumb, &ranked, true) },
+            }
+        })
+    }
+
+    /// Scores `data` and hands the result to `report`; a panic on the way is an error, not a crash
+    fn score<T>(&self, data: &Data, report: impl FnOnce(&Plumb, &ScoredCommands) -> T) -> Result<T> {
+        std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let rewritten;
+            let data = match data {
+                Data::Text(text) if !self.config.options.mirrors.is_empty() => {
+                    rewritten = Data::Text(mirrors::rewrite(&self.config.options.mirrors, text)?);
+                    &rewritten
+                }
+                data => data,
+            };
+            let file = tempfile::Builder::new().prefix("faucet_").tempfile()?;
+            let path = file.path().to_str().ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+            data.write_to_temp_file(path)?;
+            let text_for_matching = data.get_text_for_matching(self.runner.as_ref(), path)?;
+            let data_file = crate::with_extension(file, data.extension(&text_for_matching))?;
+            let data_path = data_file.to_str().ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+
+            let mut plumb =
+                Plumb::with_runner(&self.config.options, data, data_path, SOURCE, text_for_matching, self.runner.as_ref());
+            if let Some(derive_command) = &self.config.options.derive_command {
+                plumb.derive(derive_command)?;
+            }
+            let scored_commands = score_commands(&self.config, &plumb, &self.cache, false)?;
+            Ok(report(&plumb, &scored_commands))
+        }))
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(anyhow!("Scoring panicked: {message}"))
+        })
+    }
+}
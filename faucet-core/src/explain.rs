@@ -0,0 +1,78 @@
+//! `faucet --explain <input>` (or `faucet score <input>`): the whole scoring breakdown as a table,
+//! every command with the scorers that fired for it, their deltas and its total, and why a
+//! command wasn't offered. Nothing is run.
+
+use crate::cache::ProbeCache;
+use crate::{Config, Decision, Effect, Plumb, RankedCommand, ScoredCommands, action_key, explain as attribute};
+
+/// One row per configured command, in config order, then the thresholds and the decision
+pub fn report(
+    config: &Config,
+    plumb: &Plumb,
+    cache: &ProbeCache,
+    scored: &ScoredCommands,
+    ranked: &[RankedCommand],
+    decision: &Decision,
+) -> String {
+    let data_fingerprint = plumb.fingerprint();
+    let fired: Vec<_> = config
+        .scorers
+        .iter()
+        .filter(|scorer| scorer.fires(plumb, cache, data_fingerprint).is_ok_and(|fired| fired))
+        .collect();
+    let rows: Vec<(&str, String, String)> = config
+        .commands
+        .iter()
+        .map(|(label, command)| {
+            let vetoed_by = fired.iter().find(|scorer| match &scorer.effect {
+                Effect::Veto { veto } => veto.iter().any(|vetoed| vetoed == label),
+                effect => effect.deltas().any(|(scored, delta)| {
+                    scored == label && plumb.options.veto_threshold.is_some_and(|threshold| delta <= threshold)
+                }),
+            });
+            let (total, detail) = if !command.accepts(plumb.mime()) {
+                ("-".to_string(), format!("does not accept {}", plumb.mime()))
+            } else if let Some(scorer) = vetoed_by {
+                ("-".to_string(), format!("vetoed by {}", scorer.describe()))
+            } else if let Some((_, total, reasons)) = scored.get(label) {
+                let rank = ranked.iter().position(|(_, (ranked_label, _))| *ranked_label == label);
+                let status = match (rank, &command.min_score) {
+                    (Some(rank), _) => format!("#{}", rank + 1),
+                    (None, Some(min_score)) if *total > 0 => format!("below min_score {}", min_score.value()),
+                    (None, _) => "not offered".to_string(),
+                };
+                let fired = if reasons.is_empty() { "no scorer fired".to_string() } else { attribute(reasons) };
+                (total.to_string(), format!("{status}: {fired}"))
+            } else if scored.values().any(|(other, _, _)| action_key(other, plumb) == action_key(command, plumb)) {
+                ("-".to_string(), "merged into the command before it running the same action".to_string())
+            } else {
+                // Only a scripted delta (lua, external, a plugin) vetoes without showing in the config
+                ("-".to_string(), format!("vetoed by a delta at or below veto_threshold {}", plumb.options.veto_threshold.unwrap_or_default()))
+            };
+            (label.as_str(), total, detail)
+        })
+        .collect();
+
+    let label_width = rows.iter().map(|(label, _, _)| label.len()).chain(["COMMAND".len()]).max().unwrap_or_default();
+    let mut report = format!("{:label_width$}  {:>6}  SCORERS\n", "COMMAND", "TOTAL");
+    for (label, total, detail) in &rows {
+        report.push_str(&format!("{label:label_width$}  {total:>6}  {detail}\n"));
+    }
+    report.push_str(&format!(
+        "\nData: {} from {}; auto-select above {}, by a margin over {}{}\n",
+        plumb.mime(),
+        plumb.source,
+        plumb.options.auto_select_min_threshold,
+        plumb.options.auto_select_max_threshold,
+        if plumb.options.auto_select { "" } else { " (auto_select is off)" },
+    ));
+    report.push_str(&match decision {
+        Decision::NoMatch => "Nothing scored, nothing would run\n".to_string(),
+        Decision::AutoSelect((_, (label, (_, score, _)))) => format!("Would auto-select '{label}' ({score})\n"),
+        Decision::Menu(ranked) => match ranked.len() {
+            1 => "Would offer 1 command in the picker\n".to_string(),
+            count => format!("Would offer {count} commands in the picker\n"),
+        },
+    });
+    report
+}
@@ -0,0 +1,111 @@
+//! Integer arithmetic over named constants, for score values like `$STRONG + 5` or `-2 * $WEAK`.
+
+use anyhow::{Result, anyhow, bail};
+use indexmap::IndexMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+pub fn eval(expression: &str, constants: &IndexMap<String, i32>) -> Result<i32> {
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+        constants,
+    };
+    let value = parser.sum()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.chars.peek() {
+        bail!("Unexpected '{c}' in '{expression}'");
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    constants: &'a IndexMap<String, i32>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn sum(&mut self) -> Result<i32> {
+        let mut value = self.product()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.chars.next_if(|c| matches!(c, '+' | '-')) {
+                Some(op) => op,
+                None => return Ok(value),
+            };
+            let rhs = self.product()?;
+            value = match op {
+                '+' => value.checked_add(rhs),
+                _ => value.checked_sub(rhs),
+            }
+            .ok_or_else(|| anyhow!("Score arithmetic overflowed"))?;
+        }
+    }
+
+    fn product(&mut self) -> Result<i32> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.chars.next_if(|c| matches!(c, '*' | '/')) {
+                Some(op) => op,
+                None => return Ok(value),
+            };
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value.checked_mul(rhs),
+                _ if rhs == 0 => bail!("Division by zero"),
+                _ => value.checked_div(rhs),
+            }
+            .ok_or_else(|| anyhow!("Score arithmetic overflowed"))?;
+        }
+    }
+
+    fn unary(&mut self) -> Result<i32> {
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'-').is_some() {
+            return self
+                .unary()?
+                .checked_neg()
+                .ok_or_else(|| anyhow!("Score arithmetic overflowed"));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<i32> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.sum()?;
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&')').is_none() {
+                    bail!("Missing ')'");
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(digit) = self.chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                Ok(digits.parse()?)
+            }
+            Some('$') => {
+                self.chars.next();
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                self.constants
+                    .get(&name)
+                    .copied()
+                    .ok_or_else(|| anyhow!("Unknown constant '${name}'"))
+            }
+            Some(c) => bail!("Unexpected '{c}'"),
+            None => bail!("Unexpected end of expression"),
+        }
+    }
+}
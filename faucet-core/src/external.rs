@@ -0,0 +1,64 @@
+//! `external` scorers: a command given a JSON description of the data on stdin, that prints the
+//! deltas as a JSON array of `[label, delta]` pairs. Where a `command` scorer can only succeed or
+//! fail, adding fixed scores to fixed labels, one external process can weigh every command.
+//!
+//! ```yaml
+//! - external: ~/bin/score-links
+//! ```
+//!
+//! The description holds `text` (absent for binary data, which is in `data_file`), `mime`, `size`
+//! in bytes, `source`, `path` (the file `faucet file` plumbs, else absent), `detected` (the names
+//! of the detectors that fired) and `vars`, the variables commands get. The command runs in `sh`
+//! with those variables too; one that exits non-zero, or prints anything but the array, scores
+//! nothing.
+
+use crate::Plumb;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::{trace, warn};
+
+#[derive(Serialize)]
+struct Description<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    data_file: &'a str,
+    mime: &'a str,
+    size: usize,
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    detected: Vec<&'static str>,
+    vars: BTreeMap<String, String>,
+}
+
+/// The deltas `command` prints for the data; a command that fails is logged and scores nothing
+pub fn deltas(command: &str, plumb: &Plumb) -> Vec<(String, i32)> {
+    run(command, plumb).unwrap_or_else(|e| {
+        warn!("External scorer '{command}' failed: {e}");
+        Vec::new()
+    })
+}
+
+fn run(command: &str, plumb: &Plumb) -> anyhow::Result<Vec<(String, i32)>> {
+    let description = Description {
+        text: plumb.data.is_text().then_some(plumb.text.as_str()),
+        data_file: plumb.data_file,
+        mime: plumb.mime(),
+        size: plumb.size(),
+        source: plumb.source,
+        path: plumb.source_path.as_deref(),
+        detected: plumb.detections.iter().map(|detection| detection.name).collect(),
+        vars: plumb.vars().into_iter().filter(|(name, _)| name != "TEXT").collect(),
+    };
+    let input = serde_json::to_vec(&description)?;
+    let output = plumb
+        .runner
+        .output_with_input(plumb.shell(command).envs(plumb.context().iter().cloned()), &input)?;
+    if !output.status.success() {
+        anyhow::bail!("{}", output.status);
+    }
+    let deltas: Vec<(String, i32)> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow::anyhow!("expected a JSON array of [label, delta] pairs: {e}"))?;
+    trace!("External scorer '{command}' printed {deltas:?}");
+    Ok(deltas)
+}
@@ -0,0 +1,78 @@
+//! Shell-style globs for `pattern` scorers, matched against the name of a plumbed file.
+
+use regex::Regex;
+
+/// `pattern` as a case-insensitive regex: `*` and `?` stay within a path component, `**` crosses
+/// them, `[abc]`/`[!abc]` are classes and `{tar.gz,zip}` alternatives
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    let mut regex = String::from("(?i)^");
+    let mut chars = pattern.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.next_if_eq(&'*').is_some() => {
+                // `**/` also matches no directory at all
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.next_if(|c| matches!(c, '!' | '^')).is_some() {
+                    regex.push('^');
+                }
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' | '[' | '&' | '~' => regex.push_str(&regex::escape(&c.to_string())),
+                        _ => regex.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(format!("Unclosed '[' in '{pattern}'"));
+                }
+                regex.push(']');
+            }
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if braces > 0 {
+        return Err(format!("Unclosed '{{' in '{pattern}'"));
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|e| e.to_string())
+}
+
+/// Whether `path` matches: a pattern with a `/` is matched against the end of the path, any other
+/// against the file name alone
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let compiled = match pattern {
+        pattern if !pattern.contains('/') => return compile(pattern).is_ok_and(|regex| regex.is_match(file_name(path))),
+        pattern if pattern.starts_with('/') || pattern.starts_with("**") => compile(pattern),
+        // `Downloads/*.pdf` is wherever a Downloads directory is
+        pattern => compile(&format!("**/{pattern}")),
+    };
+    compiled.is_ok_and(|regex| regex.is_match(path))
+}
+
+/// The last component of `path`
+pub fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
@@ -0,0 +1,209 @@
+//! A clipboard history for plumbing. `faucet watch --clipboard` stays resident, keeps the last
+//! `history_size` text clipboard entries in memory and hands them out on the runtime dir's
+//! `history.sock`; `faucet pick-history` shows them in the picker and plumbs the chosen one.
+//! Entries only reach the disk when `history_file` is set.
+
+use crate::cli::Flags;
+use crate::lifecycle::Lifecycle;
+use crate::clipboard::{ClipboardProvider, Selection};
+use crate::{Config, Data, Plumb, expand_home};
+use anyhow::{Result, bail};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Longer clipboard texts aren't kept
+const MAX_ENTRY: usize = 1024 * 1024;
+/// Characters of an entry's first line shown in the picker
+const PREVIEW_CHARS: usize = 80;
+
+/// Newest entry first
+#[derive(Clone, Default)]
+struct Ring(Arc<Mutex<VecDeque<String>>>);
+
+impl Ring {
+    fn entries(&self) -> MutexGuard<'_, VecDeque<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Puts `text` in front, moving it there if it was already kept
+    fn push(&self, text: String, size: usize) {
+        let mut entries = self.entries();
+        entries.retain(|entry| *entry != text);
+        entries.push_front(text);
+        entries.truncate(size);
+    }
+}
+
+fn socket_path() -> Result<PathBuf> {
+    Ok(crate::runtime::dir()?.join("history.sock"))
+}
+
+/// `faucet watch --clipboard`
+pub fn watch(config_path: &Path, flags: &Flags, args: &[String]) -> Result<()> {
+    if let Some(arg) = args.first() {
+        bail!("Unknown watch --clipboard argument '{arg}'");
+    }
+    let lifecycle = Lifecycle::install("watch --clipboard", config_path.to_path_buf(), flags.clone())?;
+    let mut config = lifecycle.load_config()?;
+    let socket = socket_path()?;
+    if UnixStream::connect(&socket).is_ok() {
+        bail!("Another faucet is already keeping the clipboard history");
+    }
+    // Left behind by a history that was killed
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    let ring = Ring::default();
+    for entry in load(&config).into_iter().rev() {
+        ring.push(entry, config.options.history_size);
+    }
+
+    let served = ring.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let sent = stream.map_err(anyhow::Error::from).and_then(|mut stream| {
+                for entry in served.entries().iter() {
+                    stream.write_all(entry.as_bytes())?;
+                    stream.write_all(b"\0")?;
+                }
+                Ok(())
+            });
+            if let Err(e) = sent {
+                error!("Failed to send the clipboard history: {e}");
+            }
+        }
+    });
+
+    info!("Keeping the last {} clipboard entries", config.options.history_size);
+    let mut provider = crate::clipboard::provider(&config.options.display_server)?;
+    let mut last = None;
+    loop {
+        if lifecycle.take_reload() {
+            match lifecycle.load_config() {
+                Ok(reloaded) => {
+                    provider = crate::clipboard::provider(&reloaded.options.display_server)?;
+                    config = reloaded;
+                    ring.entries().truncate(config.options.history_size);
+                }
+                Err(e) => error!("Keeping the previous config: {e}"),
+            }
+        }
+        if let Some(text) = read_clipboard(provider.as_ref())
+            && last.as_ref() != Some(&text)
+        {
+            let _busy = lifecycle.busy();
+            last = Some(text.clone());
+            ring.push(text, config.options.history_size);
+            lifecycle.handled();
+            if let Err(e) = save(&config, &ring) {
+                error!("Failed to save the clipboard history: {e}");
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// `faucet pick-history`
+pub fn pick(config: &Config, flags: &Flags, args: &[String]) -> Result<()> {
+    if let Some(arg) = args.first() {
+        bail!("Unknown pick-history argument '{arg}'");
+    }
+    let entries = match UnixStream::connect(socket_path()?) {
+        Ok(mut stream) => {
+            let mut content = String::new();
+            stream.read_to_string(&mut content)?;
+            split(&content)
+        }
+        Err(_) if config.options.history_file.is_some() => load(config),
+        Err(_) => bail!("No clipboard history is being kept, start 'faucet watch --clipboard'"),
+    };
+    if entries.is_empty() {
+        debug!("The clipboard history is empty");
+        return Ok(());
+    }
+
+    let data = Data::Text(String::new());
+    let plumb = Plumb::new(&config.options, &data, "", "history", String::new());
+    let lines: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| format!("{}: {}", index + 1, preview(entry)))
+        .collect();
+    let chosen = plumb.pick(&lines.join("\n"))?;
+    let Some(entry) = chosen
+        .split_once(": ")
+        .and_then(|(number, _)| number.parse::<usize>().ok())
+        .and_then(|number| entries.get(number.checked_sub(1)?))
+    else {
+        debug!("Didn't pick a history entry");
+        return Ok(());
+    };
+    let mut replumb = plumb.replumb(entry)?;
+    if flags.no_exec {
+        replumb.arg("--no-exec");
+    }
+    let status = replumb.status()?;
+    if !status.success() {
+        bail!("Plumbing the history entry failed: {status}");
+    }
+    Ok(())
+}
+
+/// The first non-blank line, cut short, with `…` when there is more
+fn preview(entry: &str) -> String {
+    let line = entry.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    let mut preview: String = line.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < entry.trim().len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Text on the clipboard, if it is text worth keeping
+fn read_clipboard(provider: &dyn ClipboardProvider) -> Option<String> {
+    let (bytes, None) = provider.read(Selection::Clipboard).ok()? else {
+        return None;
+    };
+    String::from_utf8(bytes)
+        .ok()
+        .filter(|text| !text.trim().is_empty() && text.len() <= MAX_ENTRY && !text.contains('\0'))
+}
+
+fn split(content: &str) -> Vec<String> {
+    content.split_terminator('\0').map(str::to_string).collect()
+}
+
+/// The entries kept in `history_file`, if one is set
+fn load(config: &Config) -> Vec<String> {
+    let Some(path) = &config.options.history_file else {
+        return Vec::new();
+    };
+    let Ok(path) = expand_home(path) else {
+        return Vec::new();
+    };
+    let mut entries = std::fs::read_to_string(path).map(|content| split(&content)).unwrap_or_default();
+    entries.truncate(config.options.history_size);
+    entries
+}
+
+fn save(config: &Config, ring: &Ring) -> Result<()> {
+    let Some(path) = &config.options.history_file else {
+        return Ok(());
+    };
+    let path = expand_home(path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    // Only readable by the user, and renamed into place so a reader never sees half of it
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    for entry in ring.entries().iter() {
+        file.write_all(entry.as_bytes())?;
+        file.write_all(b"\0")?;
+    }
+    file.persist(path)?;
+    Ok(())
+}
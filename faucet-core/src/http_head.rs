@@ -0,0 +1,84 @@
+//! `http_head` scorers: a HEAD request on a plumbed http(s) link, matched on the type and size
+//! the server reports, so a link to a video can outscore the browser without fetching the body.
+//! Off unless `options.http_probe` is set; one request per plumb, however many scorers ask.
+
+use crate::Runner;
+use anyhow::{Result, anyhow, bail};
+use std::time::Duration;
+
+/// What the server said about the link
+pub struct Head {
+    pub mime: String,
+    pub length: Option<u64>,
+}
+
+/// Asks for `url`'s headers, following redirects, giving up after `timeout`
+pub fn probe(runner: &dyn Runner, url: &str, timeout: Duration) -> Result<Head> {
+    let output = runner
+        .output(
+            std::process::Command::new("curl")
+                .args(["--fail", "--silent", "--show-error", "--head", "--location", "--proto", "=http,https"])
+                .arg("--max-time")
+                .arg(format!("{:.3}", timeout.as_secs_f64()))
+                .arg("--")
+                .arg(url),
+        )
+        .map_err(|e| anyhow!("Could not run curl: {e}"))?;
+    if !output.status.success() {
+        bail!("HEAD '{url}' failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let headers = String::from_utf8_lossy(&output.stdout);
+    Ok(Head {
+        mime: crate::download::content_type(&headers).unwrap_or_else(|| "application/octet-stream".to_string()),
+        length: crate::download::header(&headers, "content-length").and_then(|length| length.parse().ok()),
+    })
+}
+
+/// Parsed `http_head` condition: `"<mime glob>"`, optionally bounding the size as in
+/// `"video/* > 10M"` or `"application/pdf <= 512K"` (K, M and G are powers of 1024)
+pub struct HeadCondition<'a> {
+    pub mime_pattern: &'a str,
+    pub size: Option<(&'a str, u64)>,
+}
+
+const COMPARISONS: [&str; 4] = ["<=", ">=", "<", ">"];
+
+impl<'a> HeadCondition<'a> {
+    pub fn parse(expression: &'a str) -> Option<Self> {
+        let comparison = COMPARISONS.iter().find_map(|comparison| {
+            expression.split_once(comparison).map(|(mime, bound)| (mime, *comparison, bound))
+        });
+        let (mime_pattern, size) = match comparison {
+            Some((mime, comparison, bound)) => (mime.trim(), Some((comparison, parse_size(bound.trim())?))),
+            None => (expression.trim(), None),
+        };
+        crate::is_mime_pattern(mime_pattern).then_some(Self { mime_pattern, size })
+    }
+
+    /// True when the type fits and, if the condition bounds the size, the server gave a size within it
+    pub fn matches(&self, head: &Head) -> bool {
+        if !crate::xdg::mime_matches(self.mime_pattern, &head.mime) {
+            return false;
+        }
+        let Some((comparison, bound)) = self.size else {
+            return true;
+        };
+        head.length.is_some_and(|length| match comparison {
+            "<=" => length <= bound,
+            ">=" => length >= bound,
+            "<" => length < bound,
+            _ => length > bound,
+        })
+    }
+}
+
+/// `512`, `512K`, `10M` or `1G` in bytes
+pub fn parse_size(size: &str) -> Option<u64> {
+    let (digits, multiplier) = match size.char_indices().last()? {
+        (index, 'K' | 'k') => (&size[..index], 1 << 10),
+        (index, 'M' | 'm') => (&size[..index], 1 << 20),
+        (index, 'G' | 'g') => (&size[..index], 1 << 30),
+        _ => (size, 1),
+    };
+    digits.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
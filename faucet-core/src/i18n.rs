@@ -0,0 +1,174 @@
+//! User-facing strings faucet produces itself (picker prompt, builtins' menu entries, confirmations,
+//! notifications), looked up in the active locale. Built-in catalogs cover a few languages;
+//! `<config dir>/faucet/locales/<locale>.yaml` (a map of message id to text) overrides or adds to
+//! them. Missing messages fall back to English.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("prompt", "Faucet [{source}]: "),
+    ("prompt_queued", "Faucet [{source}] (+{queued}): "),
+    ("confirm_yes", "Yes"),
+    ("confirm_no", "No"),
+    ("busy_summary", "Faucet is busy"),
+    ("busy_body", "A menu is already open"),
+    ("daemon_summary", "Faucet: {count} commands match"),
+    ("checksum_ok", "Checksum OK"),
+    ("checksum_mismatch", "Checksum MISMATCH"),
+    ("builtin_open_url", "Open link"),
+    ("builtin_browser", "Open in browser"),
+    ("builtin_editor", "Edit"),
+    ("builtin_pager", "View"),
+    ("builtin_links", "Open a link inside"),
+    ("builtin_strip_ansi", "Strip colours"),
+    ("builtin_verify_checksum", "Verify checksum"),
+    ("builtin_scratch", "Save as scratch file"),
+    ("builtin_download", "Download"),
+    ("builtin_define", "Define"),
+    ("builtin_calculate", "Calculate"),
+];
+
+const BUILT_IN: &[(&str, &[(&str, &str)])] = &[
+    (
+        "de",
+        &[
+            ("prompt_queued", "Faucet [{source}] (+{queued} wartend): "),
+            ("confirm_yes", "Ja"),
+            ("confirm_no", "Nein"),
+            ("busy_summary", "Faucet ist beschäftigt"),
+            ("busy_body", "Ein Menü ist bereits geöffnet"),
+            ("daemon_summary", "Faucet: {count} passende Befehle"),
+            ("checksum_ok", "Prüfsumme stimmt"),
+            ("checksum_mismatch", "Prüfsumme stimmt NICHT"),
+            ("builtin_open_url", "Link öffnen"),
+            ("builtin_browser", "Im Browser öffnen"),
+            ("builtin_editor", "Bearbeiten"),
+            ("builtin_pager", "Anzeigen"),
+            ("builtin_links", "Enthaltenen Link öffnen"),
+            ("builtin_strip_ansi", "Farben entfernen"),
+            ("builtin_verify_checksum", "Prüfsumme prüfen"),
+            ("builtin_scratch", "Als Notizdatei speichern"),
+            ("builtin_download", "Herunterladen"),
+            ("builtin_define", "Nachschlagen"),
+            ("builtin_calculate", "Berechnen"),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("prompt", "Faucet [{source}] : "),
+            ("prompt_queued", "Faucet [{source}] (+{queued} en attente) : "),
+            ("confirm_yes", "Oui"),
+            ("confirm_no", "Non"),
+            ("busy_summary", "Faucet est occupé"),
+            ("busy_body", "Un menu est déjà ouvert"),
+            ("daemon_summary", "Faucet : {count} commandes correspondent"),
+            ("checksum_ok", "Somme de contrôle correcte"),
+            ("checksum_mismatch", "Somme de contrôle INCORRECTE"),
+            ("builtin_open_url", "Ouvrir le lien"),
+            ("builtin_browser", "Ouvrir dans le navigateur"),
+            ("builtin_editor", "Modifier"),
+            ("builtin_pager", "Afficher"),
+            ("builtin_links", "Ouvrir un lien contenu"),
+            ("builtin_strip_ansi", "Retirer les couleurs"),
+            ("builtin_verify_checksum", "Vérifier la somme de contrôle"),
+            ("builtin_scratch", "Enregistrer comme brouillon"),
+            ("builtin_download", "Télécharger"),
+            ("builtin_define", "Définir"),
+            ("builtin_calculate", "Calculer"),
+        ],
+    ),
+    (
+        "es",
+        &[
+            ("prompt_queued", "Faucet [{source}] (+{queued} en cola): "),
+            ("confirm_yes", "Sí"),
+            ("confirm_no", "No"),
+            ("busy_summary", "Faucet está ocupado"),
+            ("busy_body", "Ya hay un menú abierto"),
+            ("daemon_summary", "Faucet: {count} comandos coinciden"),
+            ("checksum_ok", "Suma de verificación correcta"),
+            ("checksum_mismatch", "Suma de verificación INCORRECTA"),
+            ("builtin_open_url", "Abrir enlace"),
+            ("builtin_browser", "Abrir en el navegador"),
+            ("builtin_editor", "Editar"),
+            ("builtin_pager", "Ver"),
+            ("builtin_links", "Abrir un enlace del texto"),
+            ("builtin_strip_ansi", "Quitar colores"),
+            ("builtin_verify_checksum", "Verificar suma de comprobación"),
+            ("builtin_scratch", "Guardar como borrador"),
+            ("builtin_download", "Descargar"),
+            ("builtin_define", "Definir"),
+            ("builtin_calculate", "Calcular"),
+        ],
+    ),
+];
+
+static CATALOG: OnceLock<HashMap<&'static str, String>> = OnceLock::new();
+
+/// Loads the catalog for `locale`, else `$LC_ALL`/`$LC_MESSAGES`/`$LANG`. Only the first call has an effect.
+pub fn init(locale: Option<&str>) {
+    let locale = locale.map(str::to_string).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+    });
+    let mut catalog: HashMap<&'static str, String> =
+        ENGLISH.iter().map(|(id, text)| (*id, text.to_string())).collect();
+    if let Some(locale) = locale {
+        // `de_DE.UTF-8` is looked up as `de`, then `de_DE`, each overriding the last
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let language = locale.split('_').next().unwrap_or_default();
+        let mut names = vec![language];
+        if locale != language {
+            names.push(locale);
+        }
+        for name in names {
+            if let Some((_, messages)) = BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+                catalog.extend(messages.iter().map(|(id, text)| (*id, text.to_string())));
+            }
+            load_file(name, &mut catalog);
+        }
+    }
+    let _ = CATALOG.set(catalog);
+}
+
+fn load_file(name: &str, catalog: &mut HashMap<&'static str, String>) {
+    let Some(path) = dirs::config_dir().map(|dir| dir.join("faucet").join("locales").join(format!("{name}.yaml")))
+    else {
+        return;
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let messages: HashMap<String, String> = match serde_yaml::from_str(&content) {
+        Ok(messages) => messages,
+        Err(e) => {
+            warn!("Ignoring {}: {e}", path.display());
+            return;
+        }
+    };
+    debug!("Loaded translations from {}", path.display());
+    for (id, text) in messages {
+        match ENGLISH.iter().find(|(known, _)| *known == id) {
+            Some((id, _)) => {
+                catalog.insert(id, text);
+            }
+            None => warn!("Unknown message '{id}' in {}", path.display()),
+        }
+    }
+}
+
+/// The message `id` in the active locale, with `{name}` placeholders filled from `args`
+pub fn tr(id: &str, args: &[(&str, &str)]) -> String {
+    let text = CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(id).cloned())
+        .or_else(|| ENGLISH.iter().find(|(known, _)| *known == id).map(|(_, text)| text.to_string()))
+        .unwrap_or_else(|| id.to_string());
+    args.iter()
+        .fold(text, |text, (name, value)| text.replace(&format!("{{{name}}}"), value))
+}
@@ -0,0 +1,129 @@
+//! Where a launched command's stderr goes. A command started from a key binding has no terminal,
+//! so by default its stderr is kept in a per-job file under `$XDG_STATE_HOME/faucet/jobs`.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, warn};
+
+/// Job files kept; older ones are removed when a new job starts
+const KEEP_JOBS: usize = 50;
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StderrMode {
+    /// `inherit` when faucet's stderr is a terminal, else `file`
+    #[default]
+    Auto,
+    /// The command writes to faucet's stderr
+    Inherit,
+    /// Each line goes to faucet's log
+    Log,
+    /// A file per job under the state dir, removed if the command wrote nothing
+    File,
+}
+
+pub struct JobLog {
+    label: String,
+    mode: StderrMode,
+    path: Option<PathBuf>,
+    reader: Option<JoinHandle<()>>,
+    /// A file made for the job, removed once it finishes
+    data_file: Option<tempfile::TempPath>,
+}
+
+impl JobLog {
+    pub fn new(mode: StderrMode, label: &str) -> Self {
+        let mode = match mode {
+            StderrMode::Auto if std::io::IsTerminal::is_terminal(&std::io::stderr()) => StderrMode::Inherit,
+            StderrMode::Auto => StderrMode::File,
+            mode => mode,
+        };
+        Self { label: label.to_string(), mode, path: None, reader: None, data_file: None }
+    }
+
+    /// What to give the command as its stderr
+    pub fn stderr(&mut self) -> Result<Stdio> {
+        Ok(match self.mode {
+            StderrMode::Log => Stdio::piped(),
+            StderrMode::File => {
+                let dir = dirs::state_dir()
+                    .or_else(dirs::cache_dir)
+                    .ok_or_else(|| anyhow!("Could not find a state directory"))?
+                    .join("faucet")
+                    .join("jobs");
+                std::fs::create_dir_all(&dir)?;
+                prune(&dir);
+                let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                let label: String = self
+                    .label
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') { c } else { '_' })
+                    .collect();
+                let path = dir.join(format!("{secs}-{}-{label}.log", std::process::id()));
+                let file = std::fs::File::create(&path)?;
+                self.path = Some(path);
+                file.into()
+            }
+            _ => Stdio::inherit(),
+        })
+    }
+
+    /// Starts forwarding a piped stderr to the log
+    pub fn attach(&mut self, child: &mut Child) {
+        let Some(stderr) = child.stderr.take() else {
+            return;
+        };
+        let label = self.label.clone();
+        self.reader = Some(std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                warn!("{label}: {line}");
+            }
+        }));
+    }
+
+    /// Keeps `data_file` until the job finishes
+    pub fn keep(&mut self, data_file: tempfile::TempPath) {
+        self.data_file = Some(data_file);
+    }
+
+    /// Waits for the last of the output and says where it went
+    pub fn finish(self, status: ExitStatus) {
+        if let Some(reader) = self.reader {
+            let _ = reader.join();
+        }
+        let Some(path) = self.path else {
+            if !status.success() {
+                error!("'{}' exited with {status}", self.label);
+            }
+            return;
+        };
+        if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() == 0) {
+            let _ = std::fs::remove_file(&path);
+            if !status.success() {
+                error!("'{}' exited with {status}", self.label);
+            }
+        } else if status.success() {
+            debug!("stderr of '{}' is in {}", self.label, path.display());
+        } else {
+            error!("'{}' exited with {status}, its stderr is in {}", self.label, path.display());
+        }
+    }
+}
+
+fn prune(dir: &std::path::Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut jobs: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    // Named by start time, so the oldest sort first
+    jobs.sort();
+    let excess = (jobs.len() + 1).saturating_sub(KEEP_JOBS);
+    for job in jobs.into_iter().take(excess) {
+        let _ = std::fs::remove_file(job);
+    }
+}
@@ -0,0 +1,2916 @@
+//! faucet's scoring engine and command line. To score data from another program without
+//! running or showing anything, load a `ScoreEngine` and `evaluate` it:
+//!
+//! ```no_run
+//! let engine = faucet_core::ScoreEngine::load("faucet.yaml".as_ref())?;
+//! for command in engine.evaluate(&faucet_core::Data::Text("https://example.com".into()))? {
+//!     println!("{} {}", command.score, command.label);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use cache::ProbeCache;
+use clipboard::Selection;
+use diagnostic::Diagnostic;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::{stdin, IsTerminal, Read, Write};
+use tracing::{debug, error, trace, warn};
+use itertools::{Either, Itertools};
+
+mod bench;
+mod cli;
+mod cache;
+mod cancel;
+mod checksum;
+#[cfg(feature = "daemon")]
+mod client;
+mod clipboard;
+mod compiled;
+mod context;
+mod cooldown;
+#[cfg(feature = "daemon")]
+mod daemon;
+mod desktop;
+mod detect;
+mod diagnostic;
+mod dictionary;
+mod download;
+mod dump;
+mod engine;
+mod explain;
+mod external;
+mod expr;
+mod glob;
+mod history;
+mod http_head;
+mod i18n;
+mod joblog;
+mod redact;
+mod runtime;
+mod runner;
+mod lifecycle;
+mod lua;
+mod links;
+mod lock;
+mod menu;
+mod mirrors;
+mod monitor;
+mod plugin;
+mod previous;
+#[cfg(feature = "daemon")]
+mod protocol;
+mod ranking;
+mod scratch;
+mod selection;
+mod simulate;
+mod spool;
+mod template;
+#[cfg(test)]
+mod tests;
+mod watch;
+mod xdg;
+
+pub use engine::{Outcome, ScoreEngine};
+pub use ranking::{Match, ScoredCommand};
+pub use cancel::CancelToken;
+pub use runner::{Call, CancellableRunner, MockRunner, PureRunner, Runner, SystemRunner};
+
+#[derive(Serialize)]
+struct Scorer {
+    /// Shown when explaining scores; defaults to the condition
+    name: Option<String>,
+    #[serde(flatten)]
+    condition: Condition,
+    #[serde(flatten)]
+    effect: Effect,
+}
+
+/// Every key a scorer may have. The condition and effect are untagged, so a misspelt key would
+/// otherwise be ignored, leaving the scorer without the condition it was meant to have
+const SCORER_KEYS: [&str; 17] = [
+    "name",
+    "regex",
+    "head",
+    "command",
+    "timeout_ms",
+    "xdg_default",
+    "detected",
+    "http_head",
+    "pattern",
+    "min_bytes",
+    "max_bytes",
+    "command_label",
+    "score_change",
+    "scores",
+    "veto",
+    "lua",
+    "external",
+];
+
+impl<'de> Deserialize<'de> for Scorer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(flatten)]
+            condition: Condition,
+            #[serde(flatten)]
+            effect: Effect,
+        }
+
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        if let Some(key) = fields.keys().find(|key| !SCORER_KEYS.contains(&key.as_str())) {
+            return Err(serde::de::Error::unknown_field(key, &SCORER_KEYS));
+        }
+        let Fields { name, condition, effect } =
+            serde_json::from_value(serde_json::Value::Object(fields)).map_err(serde::de::Error::custom)?;
+        Ok(Self { name, condition, effect })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Condition {
+    Regex {
+        regex: String,
+        #[serde(default)]
+        head: bool,
+    },
+    Command {
+        command: String,
+        /// Kills the command once it has run this long, counting the scorer as not matched
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+    },
+    /// `"<mime glob> == <desktop id>"` against the XDG default applications
+    XdgDefault {
+        xdg_default: String,
+    },
+    /// Matches when the named built-in detector recognised the data (see `detect`)
+    Detected {
+        detected: String,
+    },
+    /// `"<mime glob> [<|<=|>|>= <size>]"` against a HEAD request on the link (see `http_head`)
+    HttpHead {
+        http_head: String,
+    },
+    /// A glob (`*.pdf`, `*.{tar.gz,zip}`) against the name of the file `faucet file` plumbs
+    Pattern {
+        pattern: String,
+    },
+    /// Bounds (inclusive) on the size of the data
+    Size(SizeBounds),
+    /// None at all, which only `lua` and `external` scorers may go without; last, as any scorer parses as this
+    Unconditional {},
+}
+
+/// At least one of the bounds, so that a scorer with neither isn't taken for a size condition
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "SizeFields")]
+struct SizeBounds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_bytes: Option<ByteSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_bytes: Option<ByteSize>,
+}
+
+#[derive(Deserialize)]
+struct SizeFields {
+    #[serde(default)]
+    min_bytes: Option<ByteSize>,
+    #[serde(default)]
+    max_bytes: Option<ByteSize>,
+}
+
+impl TryFrom<SizeFields> for SizeBounds {
+    type Error = &'static str;
+
+    fn try_from(SizeFields { min_bytes, max_bytes }: SizeFields) -> Result<Self, Self::Error> {
+        if min_bytes.is_none() && max_bytes.is_none() {
+            return Err("a size condition needs min_bytes or max_bytes");
+        }
+        Ok(Self { min_bytes, max_bytes })
+    }
+}
+
+/// Bytes, or a size with a K, M or G suffix (powers of 1024)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl ByteSize {
+    fn bytes(&self) -> Option<u64> {
+        match self {
+            ByteSize::Bytes(bytes) => Some(*bytes),
+            ByteSize::Text(size) => http_head::parse_size(size),
+        }
+    }
+}
+
+impl std::fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteSize::Bytes(bytes) => write!(f, "{bytes}"),
+            ByteSize::Text(size) => write!(f, "{size}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Effect {
+    Single {
+        command_label: String,
+        score_change: ScoreValue,
+    },
+    Multi {
+        scores: Vec<(String, ScoreValue)>,
+    },
+    /// Removes the commands from consideration outright, whatever else scored them
+    Veto {
+        veto: OneOrMany,
+    },
+    /// A script returning the deltas, see `lua`
+    Lua {
+        lua: String,
+    },
+    /// A command given the data as JSON on stdin and printing the deltas, see `external`
+    External {
+        external: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        match self {
+            OneOrMany::One(label) => Either::Left(std::iter::once(label.as_str())),
+            OneOrMany::Many(labels) => Either::Right(labels.iter().map(String::as_str)),
+        }
+    }
+}
+
+/// A score literal, or an expression over the config's `constants` such as `$STRONG + 5`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScoreValue {
+    Literal(i32),
+    Expression(String),
+}
+
+impl ScoreValue {
+    fn value(&self) -> i32 {
+        match self {
+            ScoreValue::Literal(value) => *value,
+            // `resolve_scores` replaces every expression before anything is scored
+            ScoreValue::Expression(_) => 0,
+        }
+    }
+}
+
+impl Condition {
+    fn kind(&self) -> &'static str {
+        match self {
+            Condition::Regex { .. } => "regex",
+            Condition::Command { .. } => "command",
+            Condition::XdgDefault { .. } => "xdg_default",
+            Condition::Detected { .. } => "detected",
+            Condition::HttpHead { .. } => "http_head",
+            Condition::Pattern { .. } => "pattern",
+            Condition::Size(_) => "size",
+            Condition::Unconditional {} => "unconditional",
+        }
+    }
+
+    fn source(&self) -> std::borrow::Cow<'_, str> {
+        let source = match self {
+            Condition::Regex { regex, .. } => regex,
+            Condition::Command { command, .. } => command,
+            Condition::XdgDefault { xdg_default } => xdg_default,
+            Condition::Detected { detected } => detected,
+            Condition::HttpHead { http_head } => http_head,
+            Condition::Pattern { pattern } => pattern,
+            Condition::Unconditional {} => "",
+            Condition::Size(SizeBounds { min_bytes, max_bytes }) => {
+                let bound = |op: &str, size: &Option<ByteSize>| size.as_ref().map(|size| format!("{op} {size}"));
+                return [bound(">=", min_bytes), bound("<=", max_bytes)].into_iter().flatten().join(", ").into();
+            }
+        };
+        source.into()
+    }
+
+    fn is_none(&self) -> bool {
+        matches!(self, Condition::Unconditional {})
+    }
+
+    /// `None` when it can't tell, as for a command that timed out or couldn't be started (which
+    /// fails instead when `scorer_failure` is `abort`)
+    fn matches(&self, plumb: &Plumb) -> Result<Option<bool>> {
+        let matched = match self {
+            Condition::Regex { regex, .. } => {
+                Regex::new(regex).is_ok_and(|re| re.is_match(&plumb.text))
+            }
+            Condition::Command { command, timeout_ms } => {
+                let mut shell = plumb.shell(command);
+                shell.envs(plumb.context().iter().cloned());
+                let status = match timeout_ms {
+                    Some(timeout_ms) => {
+                        plumb.runner.status_within(&mut shell, std::time::Duration::from_millis(*timeout_ms))
+                    }
+                    None => plumb.runner.status(&mut shell).map(Some),
+                };
+                let command_succeeded = match status {
+                    Ok(Some(status)) => status.success(),
+                    Ok(None) => {
+                        warn!("Command scorer ({command}) timed out after {} ms", timeout_ms.unwrap_or_default());
+                        return Ok(None);
+                    }
+                    Err(e) => match plumb.options.scorer_failure {
+                        ScorerFailure::Ignore => {
+                            debug!("Failed to execute command for scoring: {e}");
+                            return Ok(None);
+                        }
+                        ScorerFailure::Warn => {
+                            error!("Failed to execute command for scoring: {e}");
+                            return Ok(None);
+                        }
+                        ScorerFailure::Abort => anyhow::bail!("Failed to execute command scorer ({command}): {e}"),
+                    },
+                };
+                trace!("Command ({}) {}", command, if command_succeeded { "succeeded" } else { "did not succeed" });
+                command_succeeded
+            }
+            Condition::XdgDefault { xdg_default } => {
+                xdg::DefaultCondition::parse(xdg_default).is_some_and(|condition| condition.matches(plumb.mime()))
+            }
+            Condition::Detected { detected } => {
+                plumb.detections.iter().any(|detection| detection.name == detected)
+            }
+            Condition::HttpHead { http_head } => {
+                http_head::HeadCondition::parse(http_head)
+                    .zip(plumb.http_head())
+                    .is_some_and(|(condition, head)| condition.matches(head))
+            }
+            Condition::Pattern { pattern } => {
+                plumb.source_path.as_deref().is_some_and(|path| glob::matches(pattern, path))
+            }
+            Condition::Unconditional {} => true,
+            Condition::Size(SizeBounds { min_bytes, max_bytes }) => {
+                let size = plumb.size() as u64;
+                min_bytes.as_ref().is_none_or(|min| min.bytes().is_some_and(|min| size >= min))
+                    && max_bytes.as_ref().is_none_or(|max| max.bytes().is_some_and(|max| size <= max))
+            }
+        };
+        Ok(Some(matched))
+    }
+}
+
+impl Effect {
+    fn deltas(&self) -> impl Iterator<Item = (&str, i32)> {
+        match self {
+            Effect::Single { command_label, score_change } => {
+                Either::Left(std::iter::once((command_label.as_str(), score_change.value())))
+            }
+            Effect::Multi { scores } => Either::Right(Either::Left(
+                scores.iter().map(|(label, score_change)| (label.as_str(), score_change.value())),
+            )),
+            Effect::Veto { .. } | Effect::Lua { .. } | Effect::External { .. } => {
+                Either::Right(Either::Right(std::iter::empty()))
+            }
+        }
+    }
+
+    fn command_labels(&self) -> impl Iterator<Item = &str> {
+        match self {
+            Effect::Veto { veto } => Either::Left(veto.iter()),
+            _ => Either::Right(self.deltas().map(|(label, _)| label)),
+        }
+    }
+}
+
+impl Scorer {
+    fn command_labels(&self) -> impl Iterator<Item = &str> {
+        self.effect.command_labels()
+    }
+
+    /// Whether this scorer only needs the head of streamed stdin to decide
+    fn is_head(&self) -> bool {
+        matches!(self.condition, Condition::Regex { head: true, .. })
+    }
+
+    /// Whether the condition holds for the data, probed at most once per data through `cache`
+    fn fires(&self, plumb: &Plumb, cache: &ProbeCache, data_fingerprint: u64) -> Result<bool> {
+        let condition_fingerprint = cache::fingerprint((self.condition.kind(), self.condition.source()));
+        // A command also sees the context and the previous plumb, so the same data plumbed from
+        // elsewhere, or after another, is probed anew
+        let data_fingerprint = match self.condition {
+            Condition::Command { .. } => cache::fingerprint((data_fingerprint, plumb.context(), plumb.previous())),
+            _ => data_fingerprint,
+        };
+        cache.get_or_probe((condition_fingerprint, data_fingerprint), || {
+            let matched = self.condition.matches(plumb)?;
+            // A probe the cancel killed (or never started) can't tell
+            Ok(matched.filter(|_| !plumb.runner.is_cancelled()))
+        })
+    }
+
+    /// How the scorer is named when attributing scores to it
+    fn describe(&self) -> String {
+        match (&self.name, &self.effect) {
+            (Some(name), _) => name.clone(),
+            (None, Effect::Lua { lua }) if self.condition.is_none() => {
+                format!("lua '{}'", lua.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default())
+            }
+            (None, Effect::External { external }) if self.condition.is_none() => format!("external '{external}'"),
+            (None, _) => format!("{} '{}'", self.condition.kind(), self.condition.source()),
+        }
+    }
+
+    fn kind(&self) -> String {
+        match self.effect {
+            Effect::Single { .. } => self.condition.kind().to_string(),
+            Effect::Multi { .. } => format!("{}_multi", self.condition.kind()),
+            Effect::Veto { .. } => format!("{}_veto", self.condition.kind()),
+            Effect::Lua { .. } if self.condition.is_none() => "lua".to_string(),
+            Effect::Lua { .. } => format!("{}_lua", self.condition.kind()),
+            Effect::External { .. } if self.condition.is_none() => "external".to_string(),
+            Effect::External { .. } => format!("{}_external", self.condition.kind()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Command {
+    /// The `{placeholder}` templated menu entry; a builtin's defaults to its name in the locale
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    display: String,
+    #[serde(flatten)]
+    action: Action,
+    /// Score the command must reach before it is offered at all
+    #[serde(default)]
+    min_score: Option<ScoreValue>,
+    /// Seconds during which auto-selecting the command again for the same data does nothing
+    #[serde(default)]
+    cooldown_secs: Option<u64>,
+    /// MIME types (`text/*`, `image/png`) the command can handle; empty accepts anything
+    #[serde(default)]
+    accepts: Vec<String>,
+    /// Ports (`edit`, `web`, ...) the command is offered on by `faucet port <name>`
+    #[serde(default)]
+    ports: Vec<String>,
+    /// `{placeholder}` templated question the picker must confirm before the command runs
+    #[serde(default)]
+    confirm: Option<String>,
+    /// Rewrites text data before the command gets it
+    #[serde(default)]
+    rewrite: Option<Rewrite>,
+    /// Shell command run after `command` exits successfully, with `$EXIT_CODE` set
+    #[serde(default)]
+    on_success: Option<String>,
+    /// Shell command run after `command` fails, with `$EXIT_CODE` set
+    #[serde(default)]
+    on_failure: Option<String>,
+}
+
+impl Command {
+    /// The `{placeholder}` templated fields, by name
+    fn templates(&self) -> Vec<(&'static str, &str)> {
+        let mut templates = vec![("display", self.display.as_str())];
+        if let Some(confirm) = &self.confirm {
+            templates.push(("confirm", confirm));
+        }
+        match &self.action {
+            Action::Builtin(Builtin::OpenUrl { url }) => templates.push(("builtin.url", url)),
+            Action::Builtin(Builtin::Editor { file, line }) => {
+                templates.extend(file.as_deref().map(|file| ("builtin.file", file)));
+                templates.extend(line.as_deref().map(|line| ("builtin.line", line)));
+            }
+            _ => {}
+        }
+        templates
+    }
+
+    fn accepts(&self, mime: &str) -> bool {
+        self.accepts.is_empty() || self.accepts.iter().any(|pattern| xdg::mime_matches(pattern, mime))
+    }
+}
+
+/// Every match of the regex `pattern` is replaced by `replacement`, where `$1`/`${name}` are its groups
+#[derive(Clone, Serialize, Deserialize)]
+struct Rewrite {
+    pattern: String,
+    replacement: String,
+}
+
+impl Rewrite {
+    fn apply(&self, text: &str) -> Result<String> {
+        Ok(Regex::new(&self.pattern)?.replace_all(text, &self.replacement).into_owned())
+    }
+}
+
+/// A pattern `xdg::mime_matches` understands
+fn is_mime_pattern(pattern: &str) -> bool {
+    match pattern.split_once('/') {
+        Some(("*", subtype)) => subtype == "*",
+        Some((kind, subtype)) => {
+            !kind.is_empty() && !kind.contains('*') && (subtype == "*" || !subtype.is_empty() && !subtype.contains('*'))
+        }
+        None => pattern == "*",
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Action {
+    Shell { command: String },
+    Builtin(Builtin),
+}
+
+/// Commands run directly rather than through `sh -c`, so the data is never shell-parsed
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "builtin", rename_all = "snake_case")]
+enum Builtin {
+    /// Opens a `{placeholder}` URL template with the configured browser
+    OpenUrl { url: String },
+    /// Opens the text (or the data file for binary data) in the browser
+    Browser,
+    /// Opens the data file in `$VISUAL`/`$EDITOR`, or the `{placeholder}` templated `file` at `line`
+    Editor {
+        #[serde(default)]
+        file: Option<String>,
+        #[serde(default)]
+        line: Option<String>,
+    },
+    /// Opens the data file in `$PAGER`
+    Pager,
+    /// Extracts the links from Markdown/HTML text and plumbs the one picked (or the only one)
+    Links,
+    /// Strips terminal escape sequences from the text and plumbs the result
+    StripAnsi,
+    /// Checks a detected `checksum` against its file in `dir` (or the latest download there) and notifies
+    VerifyChecksum {
+        #[serde(default)]
+        dir: Option<String>,
+    },
+    /// Saves the data to a dated file in `dir` and opens it with `open_with`, else the editor
+    Scratch {
+        #[serde(default)]
+        dir: Option<String>,
+        #[serde(default)]
+        open_with: Option<String>,
+    },
+    /// Downloads the linked file to the spool, up to `max_bytes` and of an `accept`ed MIME
+    /// type, and plumbs it
+    Download {
+        #[serde(default)]
+        max_bytes: Option<u64>,
+        #[serde(default)]
+        accept: Vec<String>,
+    },
+    /// Looks the detected `word` (else the text) up with `program`, else `sdcv`/`dict`, and shows
+    /// the definition in the pager or a notification
+    Define {
+        #[serde(default)]
+        program: Option<String>,
+        #[serde(default)]
+        show: dictionary::Show,
+    },
+    /// Evaluates the detected `math` expression (else the text), or has `program` evaluate it, and
+    /// copies the result to the clipboard
+    Calculate {
+        #[serde(default)]
+        program: Option<String>,
+    },
+}
+
+impl Builtin {
+    /// The menu entry of a builtin command with no `display` of its own
+    fn name(&self) -> String {
+        let id = match self {
+            Builtin::OpenUrl { .. } => "builtin_open_url",
+            Builtin::Browser => "builtin_browser",
+            Builtin::Editor { .. } => "builtin_editor",
+            Builtin::Pager => "builtin_pager",
+            Builtin::Links => "builtin_links",
+            Builtin::StripAnsi => "builtin_strip_ansi",
+            Builtin::VerifyChecksum { .. } => "builtin_verify_checksum",
+            Builtin::Scratch { .. } => "builtin_scratch",
+            Builtin::Download { .. } => "builtin_download",
+            Builtin::Define { .. } => "builtin_define",
+            Builtin::Calculate { .. } => "builtin_calculate",
+        };
+        i18n::tr(id, &[])
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Shell { command } => write!(f, "{command}"),
+            Action::Builtin(builtin) => write!(f, "builtin {builtin}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Builtin::OpenUrl { url } => write!(f, "open_url {url}"),
+            Builtin::Browser => write!(f, "browser"),
+            Builtin::Editor { file: None, .. } => write!(f, "editor"),
+            Builtin::Editor { file: Some(file), line } => {
+                write!(f, "editor {file}")?;
+                match line {
+                    Some(line) => write!(f, ":{line}"),
+                    None => Ok(()),
+                }
+            }
+            Builtin::Pager => write!(f, "pager"),
+            Builtin::Links => write!(f, "links"),
+            Builtin::StripAnsi => write!(f, "strip_ansi"),
+            Builtin::VerifyChecksum { dir } => {
+                write!(f, "verify_checksum {}", dir.as_deref().unwrap_or(checksum::DEFAULT_DIR))
+            }
+            Builtin::Scratch { dir, open_with } => {
+                write!(f, "scratch {}", dir.as_deref().unwrap_or(scratch::DEFAULT_DIR))?;
+                match open_with {
+                    Some(open_with) => write!(f, " with {open_with}"),
+                    None => Ok(()),
+                }
+            }
+            Builtin::Download { max_bytes, accept } => {
+                write!(f, "download up to {} bytes", max_bytes.unwrap_or(download::DEFAULT_MAX_BYTES))?;
+                match accept.as_slice() {
+                    [] => Ok(()),
+                    accept => write!(f, " of {}", accept.join(", ")),
+                }
+            }
+            Builtin::Define { program, show } => {
+                write!(f, "define with {}", program.as_deref().unwrap_or("sdcv or dict"))?;
+                match show {
+                    dictionary::Show::Pager => Ok(()),
+                    dictionary::Show::Notify => write!(f, " in a notification"),
+                }
+            }
+            Builtin::Calculate { program: None } => write!(f, "calculate"),
+            Builtin::Calculate { program: Some(program) } => write!(f, "calculate with {program}"),
+        }
+    }
+}
+
+/// Picks the configured program, else the first set env var, else `fallback`, split into program and args
+fn resolve_program(configured: Option<&str>, env_vars: &[&str], fallback: &str) -> Vec<String> {
+    let program = configured
+        .map(str::to_string)
+        .or_else(|| {
+            env_vars.iter().find_map(|var| {
+                std::env::var(var)
+                    .ok()
+                    .and_then(|value| value.split(':').next().map(str::to_string))
+                    .filter(|value| !value.trim().is_empty())
+            })
+        })
+        .unwrap_or_else(|| fallback.to_string());
+    program.split_whitespace().map(str::to_string).collect()
+}
+
+/// Expands a leading `~/` to the home directory
+fn expand_home(path: &str) -> Result<std::path::PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => Ok(dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+            .join(rest)),
+        None => Ok(std::path::PathBuf::from(path)),
+    }
+}
+
+/// Arguments opening `file` at `line` in the given editor: `+LINE FILE` for vi-likes, emacs, nano and
+/// kakoune, `-g FILE:LINE` for VS Code
+fn editor_location(editor: &str, file: String, line: Option<String>) -> Vec<String> {
+    let Some(line) = line else {
+        return vec![file];
+    };
+    let name = std::path::Path::new(editor).file_name().and_then(|name| name.to_str()).unwrap_or(editor);
+    match name {
+        "code" | "codium" | "code-oss" => vec!["-g".to_string(), format!("{file}:{line}")],
+        "subl" | "zed" | "hx" | "helix" => vec![format!("{file}:{line}")],
+        _ => vec![format!("+{line}"), file],
+    }
+}
+
+fn default_min_threshold() -> i32 {
+    10
+}
+
+fn default_max_threshold() -> i32 {
+    100
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_probe_cache_size() -> usize {
+    256
+}
+
+fn default_http_probe_timeout_ms() -> u64 {
+    1500
+}
+
+fn default_spool_max_age_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_max_replumb_depth() -> usize {
+    8
+}
+
+fn default_dmenu_command() -> String {
+    menu::BACKENDS[0].command.to_string()
+}
+
+fn default_menu_lines() -> u32 {
+    20
+}
+
+fn default_word_max_words() -> usize {
+    3
+}
+
+fn default_history_size() -> usize {
+    50
+}
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    commands: IndexMap<String, Command>,
+    scorers: Vec<Scorer>,
+    options: Options,
+    /// Named scores that score values can reference as `$NAME`
+    #[serde(default)]
+    constants: IndexMap<String, i32>,
+    /// The picker, in place of `options.dmenu_command`
+    #[serde(default)]
+    menu: Option<menu::Menu>,
+    /// Scorer plugins found next to the config file, see `plugin`
+    #[serde(skip)]
+    plugins: Vec<plugin::Plugin>,
+}
+
+/// Where plumbed data can come from, as the source is named in `threshold_overrides` and `faucet simulate`
+const SOURCES: [&str; 5] = ["clipboard", "selection", "stdin", "file", "command line"];
+
+/// Thresholds for data of a MIME class and/or from a source, replacing the global pair
+#[derive(Clone, Serialize, Deserialize)]
+struct ThresholdOverride {
+    /// `image/*`, `text/plain`, ...
+    #[serde(default)]
+    mime: Option<String>,
+    /// One of `SOURCES`
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    auto_select_min_threshold: Option<i32>,
+    #[serde(default)]
+    auto_select_max_threshold: Option<i32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Options {
+    /// Kill switch: when false faucet exits without reading or running anything
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default = "default_min_threshold")]
+    auto_select_min_threshold: i32,
+    #[serde(default = "default_max_threshold")]
+    auto_select_max_threshold: i32,
+    /// Applied in order to data they match, so later entries win
+    #[serde(default)]
+    threshold_overrides: Vec<ThresholdOverride>,
+    /// When false the picker is always shown (`--no-auto` per invocation)
+    #[serde(default = "default_true")]
+    auto_select: bool,
+    #[serde(default = "default_dmenu_command")]
+    dmenu_command: String,
+    /// Rows the picker shows, as `$FAUCET_MENU_LINES`
+    #[serde(default = "default_menu_lines")]
+    menu_lines: u32,
+    /// Replaces the translated picker prompt (`--prompt` per invocation); `{source}` is where the
+    /// data came from and `{queued}` the number of plumbs waiting
+    #[serde(default)]
+    menu_prompt: Option<String>,
+    /// Extra picker arguments, passed to `dmenu_command` as `"$@"`
+    #[serde(default)]
+    menu_args: Vec<String>,
+    /// Monitor the picker opens on: `pointer`, `focused` or an index, as `$FAUCET_MENU_MONITOR`
+    #[serde(default)]
+    menu_monitor: Option<monitor::MenuMonitor>,
+    /// Sites whose links are rewritten to an alternative frontend before scoring, see `mirrors`
+    #[serde(default)]
+    mirrors: IndexMap<String, String>,
+    /// Picker used in a terminal with no display: `auto`, `fzf`, `builtin` or `off`
+    #[serde(default)]
+    terminal_picker: menu::TerminalPicker,
+    display_server: DisplayServer,
+    /// Browser for builtin commands, else `$BROWSER`, else `xdg-open`
+    #[serde(default)]
+    browser: Option<String>,
+    /// Editor for the builtin `editor` command, else `$VISUAL`/`$EDITOR`, else `vi`
+    #[serde(default)]
+    editor: Option<String>,
+    /// Pager for the builtin `pager` command, else `$PAGER`, else `less`
+    #[serde(default)]
+    pager: Option<String>,
+    /// Terminal that hosts the editor/pager when faucet has no tty, else `$TERMINAL`, else `xterm`
+    #[serde(default)]
+    terminal: Option<String>,
+    /// Notification program for builtin commands (given a summary and body), else `notify-send`
+    #[serde(default)]
+    notify: Option<String>,
+    /// Shell command whose stdout becomes `$DERIVED_TEXT` (e.g. OCR, URL extraction)
+    #[serde(default)]
+    derive_command: Option<String>,
+    /// Spool piped stdin in the background and try `head` scorers on the first N bytes
+    #[serde(default)]
+    stream_head_bytes: Option<usize>,
+    /// Language for faucet's own strings, else `$LC_ALL`/`$LC_MESSAGES`/`$LANG`
+    #[serde(default)]
+    locale: Option<String>,
+    /// What a plumb does when another one's picker is open: `queue`, `replace` or `reject`
+    #[serde(default)]
+    on_busy: lock::OnBusy,
+    /// Number of scorer outcomes remembered per (scorer, data) pair; 0 disables the cache
+    #[serde(default = "default_probe_cache_size")]
+    probe_cache_size: usize,
+    /// Keep binary data in the spool directory rather than a temp file removed on exit
+    #[serde(default)]
+    spool: bool,
+    /// Spooled files older than this are removed on the next plumb
+    #[serde(default = "default_spool_max_age_secs")]
+    spool_max_age_secs: u64,
+    /// Where launched commands' stderr goes: `auto`, `inherit`, `log` or `file`
+    #[serde(default)]
+    command_stderr: joblog::StderrMode,
+    /// How many times builtins like `links` may hand their output back to faucet in a row
+    #[serde(default = "default_max_replumb_depth")]
+    max_replumb_depth: usize,
+    /// A single delta at or below this vetoes the command, as a `veto` scorer would
+    #[serde(default)]
+    veto_threshold: Option<i32>,
+    /// Clipboard entries `faucet watch --clipboard` keeps for `faucet pick-history`
+    #[serde(default = "default_history_size")]
+    history_size: usize,
+    /// Keeps the clipboard history in this file across restarts; by default it is only in memory
+    #[serde(default)]
+    history_file: Option<String>,
+    /// Lets `http_head` scorers send a HEAD request for plumbed links
+    #[serde(default)]
+    http_probe: bool,
+    /// How long the HEAD request may take before `http_head` scorers give up on the link
+    #[serde(default = "default_http_probe_timeout_ms")]
+    http_probe_timeout_ms: u64,
+    /// Words a phrase may have for the `word` detector to recognise it
+    #[serde(default = "default_word_max_words")]
+    word_max_words: usize,
+    /// Makes `faucet daemon` notify the candidates for a clip no command auto-selects for
+    #[serde(default)]
+    daemon_notify: bool,
+    /// What a command scorer that can't be started does: `ignore`, `warn` or `abort` the plumb
+    #[serde(default)]
+    scorer_failure: ScorerFailure,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum DisplayServer {
+    X11,
+    Wayland,
+    /// The Windows clipboard, for faucet running under WSL
+    Windows,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScorerFailure {
+    /// Counts the scorer as not matched, logging it only for debugging
+    Ignore,
+    /// Counts the scorer as not matched, logging an error
+    #[default]
+    Warn,
+    /// Fails the plumb
+    Abort,
+}
+
+impl Options {
+    /// Replaces the thresholds with those of every override matching the data's MIME type and source
+    fn apply_threshold_overrides(&mut self, mime: &str, source: &str) {
+        for threshold_override in &self.threshold_overrides {
+            let matches = threshold_override.mime.as_deref().is_none_or(|pattern| xdg::mime_matches(pattern, mime))
+                && threshold_override.source.as_deref().is_none_or(|expected| expected == source);
+            if !matches {
+                continue;
+            }
+            if let Some(min_threshold) = threshold_override.auto_select_min_threshold {
+                self.auto_select_min_threshold = min_threshold;
+            }
+            if let Some(max_threshold) = threshold_override.auto_select_max_threshold {
+                self.auto_select_max_threshold = max_threshold;
+            }
+        }
+    }
+}
+
+impl DisplayServer {
+    /// The cargo feature that compiles this backend in
+    fn feature(&self) -> &'static str {
+        match self {
+            DisplayServer::X11 => "x11",
+            DisplayServer::Wayland => "wayland",
+            DisplayServer::Windows => "wsl",
+        }
+    }
+
+    fn is_compiled_in(&self) -> bool {
+        match self {
+            DisplayServer::X11 => cfg!(feature = "x11"),
+            DisplayServer::Wayland => cfg!(feature = "wayland"),
+            // Its provider is built either way, and unit tests fall back on it
+            DisplayServer::Windows => cfg!(any(feature = "wsl", test)),
+        }
+    }
+}
+
+/// Sends a desktop notification through `options.notify`, else `notify-send`
+fn notify(options: &Options, summary: &str, body: &str) -> Result<()> {
+    let argv = resolve_program(options.notify.as_deref(), &[], "notify-send");
+    let Some((program, args)) = argv.split_first() else {
+        return Ok(());
+    };
+    std::process::Command::new(program).args(args).args([summary, body]).status()?;
+    Ok(())
+}
+
+fn check_command_exists(command: &str) -> Result<()> {
+    let status = std::process::Command::new("which").arg(command)
+        .stdout(std::process::Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        anyhow::bail!("Required command '{}' not found in PATH", command);
+    }
+    Ok(())
+}
+
+fn validate_environment(config: &Config) -> Result<()> {
+    // X11 is read natively
+    if matches!(config.options.display_server, DisplayServer::Wayland) {
+        check_command_exists("wl-paste")?;
+    }
+    if matches!(config.options.display_server, DisplayServer::Windows) {
+        if !clipboard::is_wsl() {
+            anyhow::bail!("display_server 'Windows' reads the Windows clipboard from WSL, and this isn't WSL");
+        }
+        check_command_exists("powershell.exe")?;
+    }
+    for cmd in ["file", "sh"] {
+        check_command_exists(cmd)?;
+    }
+    if cfg!(feature = "gui") && config.options.terminal_picker.resolve().is_none() {
+        match &config.menu {
+            Some(menu) => {
+                if let Some(program) = menu.program() {
+                    check_command_exists(program)?;
+                }
+            }
+            None => check_command_exists("dmenu")?,
+        }
+    }
+    Ok(())
+}
+
+/// What a plumb can't do without: thresholds that can be met, a display server compiled in, and
+/// a condition on every scorer (one without would fire on everything)
+fn validate_essentials(config: &Config, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if config.options.auto_select_min_threshold >= config.options.auto_select_max_threshold {
+        diagnostics.push(Diagnostic {
+            path: "options.auto_select_min_threshold".to_string(),
+            message: format!(
+                "Bad auto select values: min ({}) >= max ({})",
+                config.options.auto_select_min_threshold,
+                config.options.auto_select_max_threshold
+            ),
+            location: diagnostic::locate(source, "auto_select_min_threshold"),
+        });
+    }
+    if !config.options.display_server.is_compiled_in() {
+        diagnostics.push(Diagnostic {
+            path: "options.display_server".to_string(),
+            message: format!(
+                "This faucet was built without the '{}' feature",
+                config.options.display_server.feature()
+            ),
+            location: diagnostic::locate(source, "display_server"),
+        });
+    }
+    for (index, scorer) in config.scorers.iter().enumerate() {
+        // A lua or external scorer decides for itself
+        if scorer.condition.is_none() && !matches!(scorer.effect, Effect::Lua { .. } | Effect::External { .. }) {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}]"),
+                message: "Scorer has no condition (regex, command, xdg_default, detected, http_head, pattern, min_bytes, max_bytes, lua or external)".to_string(),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, ""),
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Checks the rest of the config for problems (regexes, globs, templates, command labels, ...),
+/// collecting all of them rather than stopping at the first
+fn validate_config(config: &Config, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if let Some(veto_threshold) = config.options.veto_threshold
+        && veto_threshold >= 0
+    {
+        diagnostics.push(Diagnostic {
+            path: "options.veto_threshold".to_string(),
+            message: format!("Bad veto_threshold {veto_threshold}: it must be negative, or any score would veto"),
+            location: diagnostic::locate(source, "veto_threshold"),
+        });
+    }
+    for (index, threshold_override) in config.options.threshold_overrides.iter().enumerate() {
+        let path = format!("options.threshold_overrides[{index}]");
+        if let Some(mime) = &threshold_override.mime
+        && !is_mime_pattern(mime)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("{path}.mime"),
+                message: format!("Bad MIME pattern '{mime}' (expected e.g. \"text/plain\", \"image/*\" or \"*/*\")"),
+                location: diagnostic::locate_after(source, "threshold_overrides:", mime),
+            });
+        }
+        if let Some(data_source) = &threshold_override.source
+        && !SOURCES.contains(&data_source.as_str())
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("{path}.source"),
+                message: format!("Unknown source '{data_source}', expected one of: {}", SOURCES.join(", ")),
+                location: diagnostic::locate_after(source, "threshold_overrides:", data_source),
+            });
+        }
+        // An override setting only one threshold keeps the other from the options
+        let (min, max) = match (threshold_override.auto_select_min_threshold, threshold_override.auto_select_max_threshold) {
+            (None, None) => continue,
+            (min, max) => (
+                min.unwrap_or(config.options.auto_select_min_threshold),
+                max.unwrap_or(config.options.auto_select_max_threshold),
+            ),
+        };
+        if min >= max {
+            diagnostics.push(Diagnostic {
+                path: format!("{path}.auto_select_min_threshold"),
+                message: format!("Bad auto select values: min ({min}) >= max ({max})"),
+                location: diagnostic::locate_after(source, "threshold_overrides:", "auto_select_min_threshold"),
+            });
+        }
+    }
+    for (site, problem) in mirrors::problems(&config.options.mirrors) {
+        diagnostics.push(Diagnostic {
+            path: format!("options.mirrors.{site}"),
+            message: problem,
+            location: diagnostic::locate_after(source, "mirrors:", site)
+                .or_else(|| diagnostic::locate_after(source, "[options.mirrors]", site)),
+        });
+    }
+    if let Some(problem) = config.menu.as_ref().and_then(menu::Menu::problem) {
+        diagnostics.push(Diagnostic {
+            path: "menu".to_string(),
+            message: problem,
+            location: diagnostic::locate(source, "menu:").or_else(|| diagnostic::locate(source, "[menu]")),
+        });
+    }
+    if config.options.derive_command.is_some() && !cfg!(feature = "ocr") {
+        diagnostics.push(Diagnostic {
+            path: "options.derive_command".to_string(),
+            message: "This faucet was built without the 'ocr' feature".to_string(),
+            location: diagnostic::locate(source, "derive_command"),
+        });
+    }
+    for (label, command) in &config.commands {
+        if command.display.is_empty() && matches!(command.action, Action::Shell { .. }) {
+            diagnostics.push(Diagnostic {
+                path: format!("commands.{label}.display"),
+                message: "Only builtins can do without a display".to_string(),
+                location: diagnostic::locate(source, &format!("{label}:")),
+            });
+        }
+        let captures = capture_names(config, label);
+        for (field, template) in command.templates() {
+            let problem = match template::placeholders(template) {
+                Ok(names) => names.into_iter().find(|name| !is_plumb_var(name, &captures)).map(|name| {
+                    if name.get(..6).is_some_and(|prefix| prefix.eq_ignore_ascii_case("MATCH_")) {
+                        format!("No regex scorer scoring '{label}' up captures '{{{name}}}'")
+                    } else {
+                        format!("Unknown placeholder '{{{name}}}'")
+                    }
+                }),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(problem) = problem {
+                diagnostics.push(Diagnostic {
+                    path: format!("commands.{label}.{field}"),
+                    message: problem,
+                    location: diagnostic::locate_after(
+                        source,
+                        &format!("{label}:"),
+                        template.lines().next().unwrap_or_default(),
+                    ),
+                });
+            }
+        }
+        if let Some(rewrite) = &command.rewrite
+            && let Err(e) = Regex::new(&rewrite.pattern)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("commands.{label}.rewrite.pattern"),
+                message: bad_regex(&e),
+                location: diagnostic::locate_after(source, &format!("{label}:"), &rewrite.pattern),
+            });
+        }
+        if let Action::Builtin(Builtin::Download { accept, .. }) = &command.action {
+            for pattern in accept.iter().filter(|pattern| !is_mime_pattern(pattern)) {
+                diagnostics.push(Diagnostic {
+                    path: format!("commands.{label}.accept"),
+                    message: format!("Bad MIME pattern '{pattern}' (expected e.g. \"application/pdf\" or \"image/*\")"),
+                    location: diagnostic::locate_after(source, &format!("{label}:"), pattern),
+                });
+            }
+        }
+        for pattern in command.accepts.iter().filter(|pattern| !is_mime_pattern(pattern)) {
+            diagnostics.push(Diagnostic {
+                path: format!("commands.{label}.accepts"),
+                message: format!("Bad MIME pattern '{pattern}' (expected e.g. \"text/plain\", \"image/*\" or \"*/*\")"),
+                location: diagnostic::locate_after(source, &format!("{label}:"), pattern),
+            });
+        }
+    }
+
+    for (index, scorer) in config.scorers.iter().enumerate() {
+        if let Condition::Regex { regex, .. } = &scorer.condition
+        && let Err(e) = Regex::new(regex)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].regex"),
+                message: bad_regex(&e),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "regex"),
+            });
+        }
+        if let Condition::XdgDefault { xdg_default } = &scorer.condition
+        && xdg::DefaultCondition::parse(xdg_default).is_none()
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].xdg_default"),
+                message: format!("Bad xdg_default condition '{xdg_default}' (expected \"<mime> == <app>.desktop\")"),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "xdg_default"),
+            });
+        }
+        if let Condition::HttpHead { http_head } = &scorer.condition
+        && http_head::HeadCondition::parse(http_head).is_none()
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].http_head"),
+                message: format!("Bad http_head condition '{http_head}' (expected e.g. \"video/*\" or \"application/pdf < 20M\")"),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "http_head"),
+            });
+        }
+        if let Condition::Pattern { pattern } = &scorer.condition
+        && let Err(e) = glob::compile(pattern)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].pattern"),
+                message: format!("Bad pattern: {e}"),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "pattern"),
+            });
+        }
+        if let Effect::Lua { lua } = &scorer.effect
+            && let Some(problem) = lua::problem(lua)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].lua"),
+                message: problem,
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "lua"),
+            });
+        }
+        if let Condition::Size(SizeBounds { min_bytes, max_bytes }) = &scorer.condition {
+            for (key, size) in [("min_bytes", min_bytes), ("max_bytes", max_bytes)] {
+                if let Some(size) = size
+                && size.bytes().is_none()
+                {
+                    diagnostics.push(Diagnostic {
+                        path: format!("scorers[{index}].{key}"),
+                        message: format!("Bad size '{size}' (expected bytes, or e.g. \"512K\", \"10M\")"),
+                        location: diagnostic::locate_in_sequence(source, "scorers", index, key),
+                    });
+                }
+            }
+        }
+        if let Condition::Detected { detected } = &scorer.condition
+        && !detect::names().any(|name| name == detected)
+        {
+            diagnostics.push(Diagnostic {
+                path: format!("scorers[{index}].detected"),
+                message: format!(
+                    "Unknown detector '{detected}', expected one of: {}",
+                    detect::names().collect::<Vec<_>>().join(", ")
+                ),
+                location: diagnostic::locate_in_sequence(source, "scorers", index, "detected"),
+            });
+        }
+        for label in scorer.command_labels() {
+            if !config.commands.contains_key(label) {
+                diagnostics.push(Diagnostic {
+                    path: format!("scorers[{index}]"),
+                    message: format!(
+                        "{} '{}' references non-existent command '{}'",
+                        scorer.kind(),
+                        scorer.condition.source(),
+                        label
+                    ),
+                    location: diagnostic::locate_in_sequence(source, "scorers", index, label),
+                });
+            }
+        }
+    }
+    for plugin in &config.plugins {
+        if let Some(problem) = plugin.problem() {
+            diagnostics.push(Diagnostic {
+                path: format!("plugins/{}", plugin.path().file_name().unwrap_or_default().to_string_lossy()),
+                message: problem,
+                location: None,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// The diagnostic message for a regex that doesn't compile; syntax errors end with the cause,
+/// after lines quoting the pattern that the diagnostic already points at
+fn bad_regex(error: &regex::Error) -> String {
+    let error = error.to_string();
+    format!("Bad regex: {}", error.lines().last().unwrap_or_default().trim_start_matches("error: "))
+}
+
+/// Evaluates score expressions against the config's constants, replacing them with literals
+fn resolve_scores(config: &mut Config, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (label, command) in config.commands.iter_mut() {
+        if let Some(ScoreValue::Expression(expression)) = &command.min_score {
+            match expr::eval(expression, &config.constants) {
+                Ok(value) => command.min_score = Some(ScoreValue::Literal(value)),
+                Err(e) => diagnostics.push(Diagnostic {
+                    path: format!("commands.{label}.min_score"),
+                    message: format!("Bad score '{expression}': {e}"),
+                    location: diagnostic::locate(source, expression),
+                }),
+            }
+        }
+    }
+    for (index, scorer) in config.scorers.iter_mut().enumerate() {
+        let score_values: Vec<(String, &mut ScoreValue)> = match &mut scorer.effect {
+            Effect::Single { score_change, .. } => vec![("score_change".to_string(), score_change)],
+            Effect::Multi { scores } => scores
+                .iter_mut()
+                .enumerate()
+                .map(|(score_index, (_, score_change))| (format!("scores[{score_index}]"), score_change))
+                .collect(),
+            Effect::Veto { .. } | Effect::Lua { .. } | Effect::External { .. } => Vec::new(),
+        };
+        for (field, score_value) in score_values {
+            let ScoreValue::Expression(expression) = score_value else {
+                continue;
+            };
+            match expr::eval(expression, &config.constants) {
+                Ok(value) => *score_value = ScoreValue::Literal(value),
+                Err(e) => diagnostics.push(Diagnostic {
+                    path: format!("scorers[{index}].{field}"),
+                    message: format!("Bad score '{expression}': {e}"),
+                    location: diagnostic::locate_in_sequence(source, "scorers", index, expression),
+                }),
+            }
+        }
+    }
+    diagnostics
+}
+
+/// How much of the config `load_config` checks
+#[derive(Clone, Copy, PartialEq)]
+enum Validation {
+    /// Everything, as `faucet validate` reports it
+    Full,
+    /// Only `validate_essentials`, for a plumb started on a keypress: a bad regex, template or
+    /// label elsewhere fails (or never matches) when it is used
+    Lazy,
+}
+
+/// Reads and checks the config, with the command line's threshold flags applied; also returns its source.
+/// A lazy load comes from the `compiled` cache when it can, and is stored there when it can't.
+/// A config read from stdin touches no files: it has no plugins and isn't cached.
+fn load_config(config_path: &std::path::Path, flags: &cli::Flags, validation: Validation) -> Result<(Config, String)> {
+    let from_stdin = cli::is_stdin(config_path);
+    let config_content = if from_stdin {
+        static STDIN: std::sync::OnceLock<Result<String, String>> = std::sync::OnceLock::new();
+        STDIN
+            .get_or_init(|| {
+                let mut content = String::new();
+                stdin().read_to_string(&mut content).map(|_| content).map_err(|e| e.to_string())
+            })
+            .clone()
+            .map_err(|e| anyhow::anyhow!("Failed to read the config from stdin: {e}"))?
+    } else {
+        std::fs::read_to_string(config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file at '{}': {}", config_path.display(), e))?
+    };
+    let plugins = if from_stdin {
+        Vec::new()
+    } else {
+        plugin::discover(config_path.parent().unwrap_or(std::path::Path::new(".")))
+    };
+    let cacheable = validation == Validation::Lazy && !from_stdin;
+    if cacheable
+        && let Some(mut config) = compiled::load(config_path, &config_content)
+    {
+        config.plugins = plugins;
+        // It passed its checks as written, so only what the flags change is checked again
+        apply_flags(&mut config, flags);
+        let diagnostics = validate_essentials(&config, &config_content);
+        if !diagnostics.is_empty() {
+            anyhow::bail!("{}", diagnostic::render_all(&diagnostics, config_path, &config_content));
+        }
+        return Ok((config, config_content));
+    }
+    let mut config: Config = diagnostic::parse_config(&config_content, config_path)?;
+    config.plugins = plugins;
+    let flagged = apply_flags(&mut config, flags);
+    check_config(&mut config, &config_content, config_path, validation)?;
+    if cacheable && !flagged {
+        compiled::store(config_path, &config_content, &config);
+    }
+    Ok((config, config_content))
+}
+
+/// Applies the command line's flags to the options, returning whether any did
+fn apply_flags(config: &mut Config, flags: &cli::Flags) -> bool {
+    if let Some(min_threshold) = flags.min_threshold {
+        config.options.auto_select_min_threshold = min_threshold;
+    }
+    if let Some(max_threshold) = flags.max_threshold {
+        config.options.auto_select_max_threshold = max_threshold;
+    }
+    if flags.no_auto {
+        config.options.auto_select = false;
+    }
+    if let Some(prompt) = &flags.prompt {
+        config.options.menu_prompt = Some(prompt.clone());
+    }
+    flags.min_threshold.is_some() || flags.max_threshold.is_some() || flags.no_auto || flags.prompt.is_some()
+}
+
+fn check_config(config: &mut Config, source: &str, file: &std::path::Path, validation: Validation) -> Result<()> {
+    let mut diagnostics = resolve_scores(config, source);
+    diagnostics.extend(validate_essentials(config, source));
+    if validation == Validation::Full {
+        diagnostics.extend(validate_config(config, source));
+    }
+    if !diagnostics.is_empty() {
+        anyhow::bail!("{}", diagnostic::render_all(&diagnostics, file, source));
+    }
+    if let Some(command) = config.menu.as_ref().and_then(menu::Menu::command) {
+        config.options.dmenu_command = command.to_string();
+    }
+    Ok(())
+}
+
+/// What is plumbed: UTF-8 text, or anything else as bytes
+pub enum Data {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Data {
+    fn get_text_for_matching(&self, runner: &dyn Runner, temp_file_path: &str) -> Result<String> {
+        match self {
+            Data::Text(s) => Ok(s.trim_end().to_string()),
+            Data::Binary(_) => {
+                let output = runner.output(std::process::Command::new("file").args(["--mime-type", "-b", temp_file_path]))?;
+                let mime = String::from_utf8(output.stdout)?.trim().to_string();
+                // Without `file` the data is still matched as some binary
+                Ok(if mime.is_empty() { "application/octet-stream".to_string() } else { mime })
+            }
+        }
+    }
+
+    /// Extension for the data file: guessed from text, or from the MIME type `file` gave binary data
+    fn extension<'a>(&self, text_for_matching: &'a str) -> &'a str {
+        match self {
+            Data::Text(text) => scratch::text_extension(text),
+            Data::Binary(_) => scratch::mime_extension(text_for_matching),
+        }
+    }
+
+    fn write_to_temp_file(&self, path: &str) -> Result<()> {
+        match self {
+            Data::Text(s) => std::fs::write(path, s.as_bytes())?,
+            Data::Binary(bytes) => std::fs::write(path, bytes)?,
+        }
+        Ok(())
+    }
+
+    fn is_text(&self) -> bool {
+        matches!(self, Data::Text(..))
+    }
+
+    /// Text when the bytes are UTF-8, else binary
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Data::Text(text),
+            Err(e) => Data::Binary(e.into_bytes()),
+        }
+    }
+
+    /// Like the full classification, but tolerates a UTF-8 sequence cut off at the end of the head
+    fn from_head(head: &[u8]) -> Self {
+        match std::str::from_utf8(head) {
+            Ok(text) => Data::Text(text.to_string()),
+            Err(e) if e.error_len().is_none() => {
+                Data::Text(String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned())
+            }
+            Err(_) => Data::Binary(head.to_vec()),
+        }
+    }
+}
+
+/// Renames the data file to end in `.extension`, keeping it deleted on drop
+fn with_extension(file: tempfile::NamedTempFile, extension: &str) -> Result<tempfile::TempPath> {
+    let path = file.into_temp_path();
+    let renamed = path.with_extension(extension);
+    std::fs::rename(&path, &renamed)?;
+    path.keep()?;
+    runtime::created(&renamed);
+    Ok(tempfile::TempPath::from_path(renamed))
+}
+
+struct StdinSpool {
+    head: Vec<u8>,
+    reader: std::thread::JoinHandle<std::io::Result<Vec<u8>>>,
+}
+
+impl StdinSpool {
+    /// Streams stdin into `path` on a background thread, returning once `head_bytes` have arrived or stdin closed
+    fn start(path: &str, head_bytes: usize) -> Result<Self> {
+        let mut file = std::fs::File::create(path)?;
+        let (head_tx, head_rx) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let mut head_tx = Some(head_tx);
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 8192];
+            loop {
+                let read = match stdin().read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                file.write_all(&chunk[..read])?;
+                buffer.extend_from_slice(&chunk[..read]);
+                if buffer.len() >= head_bytes
+                && let Some(tx) = head_tx.take()
+                {
+                    let _ = tx.send(buffer.clone());
+                }
+            }
+            if let Some(tx) = head_tx {
+                let _ = tx.send(buffer.clone());
+            }
+            Ok(buffer)
+        });
+        let head = head_rx.recv().unwrap_or_default();
+        Ok(Self { head, reader })
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        let buffer = self
+            .reader
+            .join()
+            .map_err(|_| anyhow::anyhow!("stdin spool thread panicked"))??;
+        Ok(buffer)
+    }
+}
+
+/// `data` with its links rewritten to the configured mirrors, which is what every plumb scores and
+/// runs
+fn mirrored(options: &Options, data: Data) -> Result<Data> {
+    Ok(match data {
+        Data::Text(text) if !options.mirrors.is_empty() => Data::Text(mirrors::rewrite(&options.mirrors, &text)?),
+        data => data,
+    })
+}
+
+/// The options `data` from `source` is decided with: the config's, with the threshold overrides
+/// matching it applied and then the command line's thresholds
+fn plumb_options(config: &Config, flags: &cli::Flags, data: &Data, text_for_matching: &str, source: &str) -> Options {
+    let mut options = config.options.clone();
+    options.apply_threshold_overrides(if data.is_text() { "text/plain" } else { text_for_matching }, source);
+    // The command line still has the last word
+    if let Some(min_threshold) = flags.min_threshold {
+        options.auto_select_min_threshold = min_threshold;
+    }
+    if let Some(max_threshold) = flags.max_threshold {
+        options.auto_select_max_threshold = max_threshold;
+    }
+    options
+}
+
+/// Scores the streamed head with `head` scorers only and, if the winner is decisive, runs it on the
+/// whole data once stdin closes, skipping the other scorers. Hands the spool back when the head
+/// alone can't decide.
+fn run_early_match(
+    config: &Config,
+    flags: &cli::Flags,
+    cache: &ProbeCache,
+    cancel: &CancelToken,
+    spool: StdinSpool,
+    data_file: &str,
+) -> Result<Option<StdinSpool>> {
+    let runner = CancellableRunner::new(cancel.clone());
+    let head = mirrored(&config.options, Data::from_head(&spool.head))?;
+    let text = head.get_text_for_matching(&runner, data_file)?;
+    let options = plumb_options(config, flags, &head, &text, "stdin");
+    let head_plumb = Plumb::with_runner(&options, &head, data_file, "stdin", text, &runner);
+    let scored_commands = score_commands(config, &head_plumb, cache, true)?;
+    if cancel.is_cancelled() {
+        anyhow::bail!("The plumb was cancelled");
+    }
+    let sorted_commands = rank_commands(&scored_commands);
+    if !should_auto_select(&options, &sorted_commands) {
+        return Ok(Some(spool));
+    }
+    let (_, (label, (command, score, _))) = &sorted_commands[0];
+    debug!(
+        "Early match on first {} bytes of stdin: {} with score of {}",
+        spool.head.len(),
+        label,
+        score
+    );
+    // Chosen on the head, but run on (and kept from running again for) all of the data
+    let data = mirrored(&config.options, Data::from_bytes(spool.finish()?))?;
+    // The spool wrote stdin as it came
+    if !config.options.mirrors.is_empty() && data.is_text() {
+        data.write_to_temp_file(data_file)?;
+    }
+    let text = data.get_text_for_matching(&runner, data_file)?;
+    let mut plumb = Plumb::with_runner(&options, &data, data_file, "stdin", text, &runner);
+    if let Some(cooldown_secs) = command.cooldown_secs
+        && !cooldown::claim(label, plumb.fingerprint(), cooldown_secs)?
+    {
+        debug!("'{label}' already ran for this data in the last {cooldown_secs}s, not running it again");
+        return Ok(None);
+    }
+    if !plumb.confirmed(command)? {
+        return Ok(None);
+    }
+    plumb.captures = capture_vars(config, &plumb, label);
+    let (mut child, job_log) = plumb.launch(label, command)?;
+    let status = child.wait()?;
+    job_log.finish(status);
+    plumb.run_hooks(command, status);
+    Ok(None)
+}
+
+/// Variables every plumb may expose, besides the detectors'
+const PLUMB_VARS: [&str; 13] = [
+    "DATA_FILE",
+    "DATA_SIZE",
+    "SOURCE_PATH",
+    "SOURCE_NAME",
+    "IS_BINARY",
+    "TEXT",
+    "DERIVED_TEXT",
+    "DERIVED_FILE",
+    "DETECTED",
+    "HAS_ANSI",
+    "FAUCET_PREV_LABEL",
+    "FAUCET_PREV_TEXT",
+    "FAUCET_PREV_AGE",
+];
+
+/// Whether a command may use `name`, given the `MATCH_` variables its regex scorers capture (see
+/// `capture_names`)
+fn is_plumb_var(name: &str, captures: &[String]) -> bool {
+    PLUMB_VARS.iter().any(|var| var.eq_ignore_ascii_case(name))
+        || detect::is_var(name)
+        || captures.iter().any(|capture| capture.eq_ignore_ascii_case(name))
+}
+
+/// The regexes of the scorers that score `label` up, whose groups reach its command
+fn capturing_regexes<'a>(config: &'a Config, label: &'a str) -> impl Iterator<Item = Regex> + 'a {
+    config.scorers.iter().filter_map(move |scorer| {
+        let Condition::Regex { regex, .. } = &scorer.condition else {
+            return None;
+        };
+        if !scorer.effect.deltas().any(|(scored, delta)| scored == label && delta > 0) {
+            return None;
+        }
+        Regex::new(regex).ok()
+    })
+}
+
+/// Every `MATCH_<n>` and `MATCH_<name>` `label`'s command could be given by `capture_vars`
+fn capture_names(config: &Config, label: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for re in capturing_regexes(config, label) {
+        let numbered = (0..re.captures_len()).map(|index| index.to_string());
+        let named = re.capture_names().flatten().map(str::to_string);
+        names.extend(numbered.chain(named).map(|name| format!("MATCH_{name}")));
+    }
+    names
+}
+
+/// `MATCH_<n>` and `MATCH_<name>` for the groups of every regex scorer that scored `label` up and
+/// matches, so its command gets the matched text as is; the first scorer to capture a name wins
+fn capture_vars(config: &Config, plumb: &Plumb, label: &str) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    for re in capturing_regexes(config, label) {
+        let Some(captures) = re.captures(&plumb.text) else {
+            continue;
+        };
+        let numbered = captures.iter().enumerate().map(|(index, group)| (index.to_string(), group));
+        let named = re.capture_names().flatten().map(|name| (name.to_string(), captures.name(name)));
+        for (name, group) in numbered.chain(named) {
+            let name = format!("MATCH_{name}");
+            if let Some(group) = group
+                && !vars.iter().any(|(known, _)| *known == name)
+            {
+                vars.push((name, group.as_str().to_string()));
+            }
+        }
+    }
+    vars
+}
+
+struct Derived {
+    text: String,
+    file: tempfile::NamedTempFile,
+}
+
+struct Plumb<'a> {
+    options: &'a Options,
+    data: &'a Data,
+    data_file: &'a str,
+    /// Starts every process scoring and launching need; `SystemRunner` unless a test swaps it
+    runner: &'a dyn Runner,
+    /// One of `SOURCES`, for the picker's prompt
+    source: &'a str,
+    /// Absolute path of the file `faucet file` plumbs, for `pattern` scorers
+    source_path: Option<String>,
+    text: String,
+    derived: Option<Derived>,
+    detections: Vec<detect::Detection>,
+    context: std::sync::OnceLock<Vec<(String, String)>>,
+    previous: std::sync::OnceLock<Vec<(String, String)>>,
+    /// The chosen command's regex captures, see `capture_vars`
+    captures: Vec<(String, String)>,
+    http_head: std::sync::OnceLock<Option<http_head::Head>>,
+}
+
+impl<'a> Plumb<'a> {
+    fn new(options: &'a Options, data: &'a Data, data_file: &'a str, source: &'a str, text: String) -> Self {
+        Self::with_runner(options, data, data_file, source, text, &SystemRunner)
+    }
+
+    /// A plumb whose detectors, scorers and commands start their processes through `runner`
+    fn with_runner(
+        options: &'a Options,
+        data: &'a Data,
+        data_file: &'a str,
+        source: &'a str,
+        text: String,
+        runner: &'a dyn Runner,
+    ) -> Self {
+        let detections = match data {
+            Data::Text(_) => detect_text(options, &text, &[], runner),
+            Data::Binary(bytes) => detect::detect_binary(bytes, runner),
+        };
+        Self {
+            options,
+            data,
+            data_file,
+            runner,
+            source,
+            source_path: None,
+            text,
+            derived: None,
+            detections,
+            context: std::sync::OnceLock::new(),
+            previous: std::sync::OnceLock::new(),
+            captures: Vec::new(),
+            http_head: std::sync::OnceLock::new(),
+        }
+    }
+
+    /// `FAUCET_CTX_*` vars for command scorers, gathered once per plumb
+    fn context(&self) -> &[(String, String)] {
+        self.context.get_or_init(|| context::gather(self.runner))
+    }
+
+    /// `FAUCET_PREV_*` vars describing the last plumb, read once per plumb
+    fn previous(&self) -> &[(String, String)] {
+        self.previous.get_or_init(previous::vars)
+    }
+
+    /// The HEAD response for a plumbed http(s) link, requested once per plumb when `http_probe` is on
+    fn http_head(&self) -> Option<&http_head::Head> {
+        self.http_head
+            .get_or_init(|| {
+                let is_web = matches!(self.detected_var("URL_SCHEME"), Some("http" | "https"));
+                if !self.options.http_probe || !is_web {
+                    return None;
+                }
+                let timeout = std::time::Duration::from_millis(self.options.http_probe_timeout_ms);
+                let url = self.text.trim();
+                http_head::probe(self.runner, url, timeout)
+                    .inspect(|head| debug!("HEAD {} is {}, length {:?}", redact::redact(url), head.mime, head.length))
+                    .inspect_err(|e| debug!("{e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Variables exposed to commands as env vars and to builtin templates as `{placeholders}`;
+    /// `PLUMB_VARS` and `detect::is_var` must cover every name
+    fn vars(&self) -> Vec<(String, String)> {
+        let mut vars = vec![
+            ("DATA_FILE".to_string(), self.data_file.to_string()),
+            ("DATA_SIZE".to_string(), self.size().to_string()),
+            (
+                "IS_BINARY".to_string(),
+                if self.data.is_text() { "0" } else { "1" }.to_string(),
+            ),
+        ];
+        if let Some(path) = &self.source_path {
+            vars.push(("SOURCE_PATH".to_string(), path.clone()));
+            vars.push(("SOURCE_NAME".to_string(), glob::file_name(path).to_string()));
+        }
+        if self.data.is_text() {
+            vars.push(("TEXT".to_string(), self.text.clone()));
+        }
+        if let Some(derived) = &self.derived {
+            vars.push(("DERIVED_TEXT".to_string(), derived.text.clone()));
+            vars.push((
+                "DERIVED_FILE".to_string(),
+                derived.file.path().display().to_string(),
+            ));
+        }
+        let detected: Vec<&str> = self.detections.iter().map(|detection| detection.name).collect();
+        vars.push(("DETECTED".to_string(), detected.join(" ")));
+        if !detected.contains(&"ansi") {
+            vars.push(("HAS_ANSI".to_string(), "0".to_string()));
+        }
+        for detection in &self.detections {
+            vars.extend(detection.vars.iter().cloned());
+        }
+        vars.extend(self.previous().iter().cloned());
+        vars.extend(self.captures.iter().cloned());
+        vars
+    }
+
+    /// Length of the data in bytes
+    fn size(&self) -> usize {
+        match self.data {
+            Data::Text(text) => text.len(),
+            Data::Binary(bytes) => bytes.len(),
+        }
+    }
+
+    /// A field exposed by one of the detectors that recognised the data
+    fn detected_var(&self, name: &str) -> Option<&str> {
+        self.detections
+            .iter()
+            .flat_map(|detection| &detection.vars)
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Identifies the data (and anything derived from it) for the probe cache
+    fn fingerprint(&self) -> u64 {
+        let bytes = match self.data {
+            Data::Text(text) => text.as_bytes(),
+            Data::Binary(bytes) => bytes,
+        };
+        cache::fingerprint((
+            bytes,
+            &self.source_path,
+            &self.text,
+            self.derived.as_ref().map(|derived| &derived.text),
+        ))
+    }
+
+    /// MIME type of the data; binary data is matched on its detected type
+    fn mime(&self) -> &str {
+        if self.data.is_text() {
+            "text/plain"
+        } else {
+            &self.text
+        }
+    }
+
+    fn shell(&self, command: &str) -> std::process::Command {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.args(["-c", command]).envs(self.vars());
+        cmd
+    }
+
+    fn expand(&self, template: &str) -> Result<String> {
+        let vars = self.vars();
+        template::expand(template, |name| {
+            vars.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        })
+    }
+
+    /// The command's menu entry: its `display` with the `{placeholders}` filled in (or as written if
+    /// one has no value) or a builtin's name, flagged when the data is a suspicious link
+    fn display(&self, command: &Command) -> String {
+        if let Action::Builtin(builtin) = &command.action
+            && command.display.is_empty()
+        {
+            return self.flagged(builtin.name());
+        }
+        let display = self.expand(&command.display).unwrap_or_else(|e| {
+            debug!("Showing '{}' unexpanded: {e}", command.display);
+            command.display.clone()
+        });
+        self.flagged(display)
+    }
+
+    /// `display`, marked when the link looks like phishing
+    fn flagged(&self, display: String) -> String {
+        match self.detected_var("URL_WARNINGS") {
+            Some(warnings) if !warnings.is_empty() => format!("⚠ {display} ({warnings})"),
+            _ => display,
+        }
+    }
+
+    fn process(&self, action: &Action) -> Result<std::process::Command> {
+        match action {
+            Action::Shell { command } => Ok(self.shell(command)),
+            Action::Builtin(builtin) => {
+                let browser = || {
+                    resolve_program(self.options.browser.as_deref(), &["BROWSER"], "xdg-open")
+                };
+                let (mut argv, in_terminal) = match builtin {
+                    Builtin::OpenUrl { url } => {
+                        let mut argv = browser();
+                        argv.push(self.expand(url)?);
+                        (argv, false)
+                    }
+                    Builtin::Browser => {
+                        // Browsers disagree on `--`, so text they would take for an option isn't passed
+                        if self.data.is_text() && self.text.starts_with('-') {
+                            anyhow::bail!("Not opening text starting with '-' in the browser, which would read it as an option");
+                        }
+                        let mut argv = browser();
+                        argv.push(if self.data.is_text() {
+                            self.text.clone()
+                        } else {
+                            self.data_file.to_string()
+                        });
+                        (argv, false)
+                    }
+                    Builtin::Editor { file, line } => {
+                        let mut argv =
+                            resolve_program(self.options.editor.as_deref(), &["VISUAL", "EDITOR"], "vi");
+                        let file = match file {
+                            Some(file) => self.expand(file)?,
+                            None => self.data_file.to_string(),
+                        };
+                        let line = match line {
+                            Some(line) => Some(self.expand(line)?).filter(|line| !line.is_empty()),
+                            None => None,
+                        };
+                        argv.extend(editor_location(argv.first().map_or("", String::as_str), file, line));
+                        (argv, true)
+                    }
+                    Builtin::Pager => {
+                        let mut argv = resolve_program(self.options.pager.as_deref(), &["PAGER"], "less");
+                        argv.push(self.data_file.to_string());
+                        (argv, true)
+                    }
+                    Builtin::Links => {
+                        let links = links::extract(&self.text);
+                        let target = match links.as_slice() {
+                            [] => anyhow::bail!("No links found in the text"),
+                            [link] => link.target.clone(),
+                            _ => {
+                                let entries: Vec<String> = links.iter().map(links::Link::entry).collect();
+                                let picked = self.pick(&entries.join("\n"))?;
+                                let Some(index) = entries.iter().position(|entry| *entry == picked) else {
+                                    anyhow::bail!("No link picked");
+                                };
+                                links[index].target.clone()
+                            }
+                        };
+                        debug!("Replumbing link {}", redact::redact(&target));
+                        return self.replumb(&target);
+                    }
+                    Builtin::StripAnsi => return self.replumb(&detect::ansi::strip(&self.text)),
+                    Builtin::VerifyChecksum { dir } => {
+                        let var = |name| self.detected_var(name).unwrap_or_default();
+                        if var("CHECKSUM_HASH").is_empty() {
+                            anyhow::bail!("No checksum detected in the data");
+                        }
+                        let (path, matched) = checksum::verify(
+                            var("CHECKSUM_ALGORITHM"),
+                            var("CHECKSUM_HASH"),
+                            var("CHECKSUM_FILE"),
+                            dir.as_deref().unwrap_or(checksum::DEFAULT_DIR),
+                        )?;
+                        let summary = i18n::tr(if matched { "checksum_ok" } else { "checksum_mismatch" }, &[]);
+                        debug!("{summary} for {}", path.display());
+                        let mut argv = resolve_program(self.options.notify.as_deref(), &[], "notify-send");
+                        argv.push(summary);
+                        argv.push(format!("{} {}", var("CHECKSUM_ALGORITHM"), path.display()));
+                        if !matched {
+                            // Still notify, but exit non-zero so `on_failure` runs
+                            let failing = ["sh", "-c", "\"$@\"; exit 1", "sh"].map(str::to_string);
+                            argv.splice(0..0, failing);
+                        }
+                        (argv, false)
+                    }
+                    Builtin::Scratch { dir, open_with } => {
+                        let extension = if self.data.is_text() {
+                            scratch::text_extension(&self.text)
+                        } else {
+                            scratch::mime_extension(self.mime())
+                        };
+                        let path = scratch::save(
+                            dir.as_deref().unwrap_or(scratch::DEFAULT_DIR),
+                            self.data_file,
+                            extension,
+                        )?;
+                        debug!("Saved scratch file {}", path.display());
+                        let (mut argv, in_terminal) = match open_with {
+                            Some(open_with) => (resolve_program(Some(open_with), &[], ""), false),
+                            None => (
+                                resolve_program(self.options.editor.as_deref(), &["VISUAL", "EDITOR"], "vi"),
+                                true,
+                            ),
+                        };
+                        argv.push(path.display().to_string());
+                        (argv, in_terminal)
+                    }
+                    Builtin::Download { max_bytes, accept } => {
+                        let max_age = std::time::Duration::from_secs(self.options.spool_max_age_secs);
+                        let (path, mime) = download::fetch(
+                            self.text.trim(),
+                            max_bytes.unwrap_or(download::DEFAULT_MAX_BYTES),
+                            accept,
+                            max_age,
+                        )?;
+                        debug!("Downloaded {mime} to {}", path.display());
+                        // The spooled file is plumbed's stdin, and reaped once that faucet is done with it
+                        let mut cmd = self.replumb_chained(&std::fs::read(&path)?)?;
+                        cmd.stdin(std::fs::File::open(&path)?);
+                        spool::spawn_reaper(&path, max_age)?;
+                        return Ok(cmd);
+                    }
+                    Builtin::Calculate { program } => {
+                        let expression = self.detected_var("MATH_EXPRESSION").unwrap_or(self.text.trim());
+                        let result = match program {
+                            Some(program) => {
+                                let argv = resolve_program(Some(program), &[], "");
+                                let (program, args) = argv
+                                    .split_first()
+                                    .ok_or_else(|| anyhow::anyhow!("No program configured for builtin {builtin}"))?;
+                                let output = std::process::Command::new(program).args(args).arg(expression).output()?;
+                                if !output.status.success() {
+                                    anyhow::bail!("'{program}' failed to evaluate '{expression}': {}", output.status);
+                                }
+                                String::from_utf8_lossy(&output.stdout).trim().to_string()
+                            }
+                            None => match self.detected_var("MATH_RESULT") {
+                                Some(result) => result.to_string(),
+                                None => detect::math::eval(expression)
+                                    .ok_or_else(|| anyhow::anyhow!("'{expression}' isn't an expression faucet can evaluate"))?,
+                            },
+                        };
+                        debug!("{expression} = {result}");
+                        let mut input = tempfile::tempfile()?;
+                        input.write_all(result.as_bytes())?;
+                        std::io::Seek::rewind(&mut input)?;
+                        let mut cmd = selection::copy_command(self.options, Selection::Clipboard);
+                        cmd.stdin(input).stdout(std::process::Stdio::null());
+                        return Ok(cmd);
+                    }
+                    Builtin::Define { program, show } => {
+                        let word = self.detected_var("WORD").unwrap_or(self.text.trim());
+                        let definition = dictionary::look_up(program.as_deref(), word)?;
+                        match show {
+                            dictionary::Show::Notify => {
+                                let mut argv = resolve_program(self.options.notify.as_deref(), &[], "notify-send");
+                                argv.push(word.to_string());
+                                argv.push(dictionary::excerpt(&definition));
+                                (argv, false)
+                            }
+                            dictionary::Show::Pager => {
+                                // Spooled, as the pager may well outlive this faucet in a terminal of its own
+                                let max_age = std::time::Duration::from_secs(self.options.spool_max_age_secs);
+                                let path = spool::create(Some("txt"), max_age)?;
+                                std::fs::write(&path, definition)?;
+                                spool::spawn_reaper(&path, max_age)?;
+                                let mut argv = resolve_program(self.options.pager.as_deref(), &["PAGER"], "less");
+                                argv.push(path.display().to_string());
+                                (argv, true)
+                            }
+                        }
+                    }
+                };
+                if in_terminal && !std::io::stdout().is_terminal() {
+                    let mut terminal =
+                        resolve_program(self.options.terminal.as_deref(), &["TERMINAL"], "xterm");
+                    terminal.push("-e".to_string());
+                    argv.splice(0..0, terminal);
+                }
+                let (program, args) = argv
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("No program configured for builtin {builtin}"))?;
+                let mut cmd = std::process::Command::new(program);
+                cmd.args(args).envs(self.vars());
+                Ok(cmd)
+            }
+        }
+    }
+
+    /// A faucet process that plumbs `text` afresh, fed through stdin so its size doesn't matter.
+    /// The payloads plumbed so far are passed down as `FAUCET_REPLUMB_CHAIN` fingerprints, so a
+    /// transform that gives back an earlier payload, or a chain past `max_replumb_depth`, fails.
+    fn replumb(&self, text: &str) -> Result<std::process::Command> {
+        let mut input = tempfile::tempfile()?;
+        input.write_all(text.as_bytes())?;
+        std::io::Seek::rewind(&mut input)?;
+        let mut cmd = self.replumb_chained(text.as_bytes())?;
+        cmd.stdin(input);
+        Ok(cmd)
+    }
+
+    /// The faucet process for `replumb`, still to be given `payload` as its stdin
+    fn replumb_chained(&self, payload: &[u8]) -> Result<std::process::Command> {
+        let bytes = match self.data {
+            Data::Text(text) => text.as_bytes(),
+            Data::Binary(bytes) => bytes,
+        };
+        let mut chain: Vec<String> = std::env::var("FAUCET_REPLUMB_CHAIN")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        chain.push(format!("{:016x}", cache::fingerprint(bytes)));
+        let next = format!("{:016x}", cache::fingerprint(payload));
+        if let Some(position) = chain.iter().position(|fingerprint| *fingerprint == next) {
+            anyhow::bail!(
+                "Replumb loop: the output is what was plumbed {} step(s) ago",
+                chain.len() - position
+            );
+        }
+        if chain.len() > self.options.max_replumb_depth {
+            anyhow::bail!(
+                "Replumb chain is deeper than max_replumb_depth ({})",
+                self.options.max_replumb_depth
+            );
+        }
+        let mut cmd = std::process::Command::new(std::env::current_exe()?);
+        cmd.env("FAUCET_REPLUMB_CHAIN", chain.join(" "));
+        // `$FAUCET_CONFIG` is inherited, but a `--config` has to be passed on
+        if let Ok(flags) = cli::take_flags(&mut std::env::args().collect())
+            && let Some(config_path) = flags.config
+        {
+            // Its stdin is the output being plumbed
+            if cli::is_stdin(&config_path) {
+                anyhow::bail!("A config read from stdin can't be passed on to replumb the output");
+            }
+            cmd.arg("--config").arg(config_path);
+        }
+        Ok(cmd)
+    }
+
+    /// Shows newline-separated `entries` in the configured picker and returns the chosen line
+    fn pick(&self, entries: &str) -> Result<String> {
+        let terminal = self.options.terminal_picker.resolve();
+        let Some(mut lock) = lock::acquire(self.options.on_busy)? else {
+            debug!("A picker is already open, rejecting this plumb");
+            notify(self.options, &i18n::tr("busy_summary", &[]), &i18n::tr("busy_body", &[]))?;
+            return Ok(String::new());
+        };
+        let queued = lock.queued.to_string();
+        let args = [("source", self.source), ("queued", queued.as_str())];
+        let prompt = match &self.options.menu_prompt {
+            Some(prompt) => args
+                .iter()
+                .fold(prompt.clone(), |prompt, (name, value)| prompt.replace(&format!("{{{name}}}"), value)),
+            None if lock.queued > 0 => i18n::tr("prompt_queued", &args),
+            None => i18n::tr("prompt", &args),
+        };
+        let mut picker = match terminal {
+            Some(menu::TerminalPicker::Builtin) => return menu::pick_on_tty(entries, &prompt),
+            Some(_) => {
+                debug!("No display, picking in the terminal with fzf");
+                let fzf = menu::BACKENDS.iter().find(|backend| backend.name == "fzf");
+                let mut picker = self.shell(fzf.map_or("fzf", |backend| backend.command));
+                // menu_args are meant for the configured picker
+                picker.arg("faucet");
+                picker
+            }
+            #[cfg(not(feature = "gui"))]
+            None => anyhow::bail!("This faucet was built without the 'gui' feature, so it has no picker"),
+            #[cfg(feature = "gui")]
+            None => {
+                use std::os::unix::process::CommandExt;
+                let mut picker = self.shell(&self.options.dmenu_command);
+                // `$0`, then the extra args as `"$@"`
+                picker.arg("faucet").args(&self.options.menu_args);
+                // Its own process group, so a replacing plumb can close the picker and everything it
+                // started. fzf stays in the terminal's foreground group, or reading the tty stops it.
+                picker.process_group(0);
+                picker
+            }
+        };
+        picker.env("FAUCET_MENU_LINES", self.options.menu_lines.to_string());
+        if let Some(placement) = self.options.menu_monitor {
+            picker.envs(monitor::vars(placement));
+        }
+        picker.env("FAUCET_PROMPT", prompt);
+        // Only set while plumbs are waiting, so a prompt can use `${FAUCET_QUEUED:+...}`
+        if lock.queued > 0 {
+            picker.env("FAUCET_QUEUED", queued);
+        }
+        let mut child = picker
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        if terminal.is_none() {
+            lock.record(child.id())?;
+        }
+        child.stdin.take().unwrap().write_all(entries.as_bytes())?;
+        let output = child.wait_with_output()?;
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+
+    /// Asks the picker to confirm commands with a `confirm` question; the rest need no confirmation
+    fn confirmed(&self, command: &Command) -> Result<bool> {
+        let Some(question) = &command.confirm else {
+            return Ok(true);
+        };
+        let question = self.expand(question)?;
+        let yes = i18n::tr("confirm_yes", &[]);
+        let confirmed = self
+            .pick(&format!("{}\n{yes}: {question}", i18n::tr("confirm_no", &[])))?
+            .starts_with(&format!("{yes}: "));
+        if !confirmed {
+            debug!("'{}' was not confirmed", command.display);
+        }
+        Ok(confirmed)
+    }
+
+    fn run(&self, label: &str, command: &Command) -> Result<()> {
+        if !self.confirmed(command)? {
+            return Ok(());
+        }
+        let (mut child, job_log) = self.launch(label, command)?;
+        let status = child.wait()?;
+        job_log.finish(status);
+        self.run_hooks(command, status);
+        Ok(())
+    }
+
+    /// Starts `command` with its stderr going where `command_stderr` says, on rewritten data if it
+    /// has a `rewrite` that changes the text
+    fn launch(&self, label: &str, command: &Command) -> Result<(std::process::Child, joblog::JobLog)> {
+        if let Some(rewrite) = &command.rewrite
+            && let Data::Text(text) = self.data
+        {
+            let rewritten = rewrite.apply(text)?;
+            if rewritten != *text {
+                debug!("'{label}' gets the data rewritten to '{}'", redact::redact(&rewritten));
+                let data = Data::Text(rewritten.clone());
+                let data_file = runtime::temp_file("faucet_data_")?;
+                data.write_to_temp_file(data_file.path().to_str().unwrap_or_default())?;
+                let data_file = with_extension(data_file, data.extension(&rewritten))?;
+                let data_file_path = data_file
+                    .to_str()
+                    .ok_or_else(|| anyhow::anyhow!("Failed to convert temp file path to string"))?;
+                let mut plumb = Plumb::with_runner(self.options, &data, data_file_path, self.source, rewritten.clone(), self.runner);
+                // Captured from the data as scored
+                plumb.captures = self.captures.clone();
+                if let Some(derive_command) = &self.options.derive_command {
+                    plumb.derive(derive_command)?;
+                }
+                let (child, mut job_log) = plumb.spawn(label, command)?;
+                job_log.keep(data_file);
+                return Ok((child, job_log));
+            }
+        }
+        self.spawn(label, command)
+    }
+
+    fn spawn(&self, label: &str, command: &Command) -> Result<(std::process::Child, joblog::JobLog)> {
+        let mut job_log = joblog::JobLog::new(self.options.command_stderr, label);
+        let mut child = self.runner.spawn(self.process(&command.action)?.stderr(job_log.stderr()?))?;
+        job_log.attach(&mut child);
+        if let Err(e) = previous::record(label, if self.data.is_text() { &self.text } else { "" }) {
+            warn!("Failed to record this plumb for the next: {e}");
+        }
+        Ok((child, job_log))
+    }
+
+    fn run_hooks(&self, command: &Command, status: std::process::ExitStatus) {
+        let hook = if status.success() {
+            &command.on_success
+        } else {
+            &command.on_failure
+        };
+        let Some(hook) = hook else {
+            return;
+        };
+        let exit_code = status.code().map(|code| code.to_string()).unwrap_or_default();
+        debug!("Running hook ({hook}) after '{}' exited with {status}", command.display);
+        if let Err(e) = self.runner.status(self.shell(hook).env("EXIT_CODE", exit_code)) {
+            error!("Failed to execute hook: {e}");
+        }
+    }
+
+    #[cfg(feature = "ocr")]
+    fn derive(&mut self, derive_command: &str) -> Result<()> {
+        let output = self.runner.output(&mut self.shell(derive_command))?;
+        if !output.status.success() {
+            debug!("Derive command ({derive_command}) did not succeed");
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+        if text.is_empty() {
+            debug!("Derive command ({derive_command}) produced no output");
+            return Ok(());
+        }
+        let file = runtime::temp_file("faucet_derived_")?;
+        std::fs::write(file.path(), text.as_bytes())?;
+        debug!(
+            "Derived text: {}",
+            redact::redact(&text).chars().take(100).collect::<String>()
+        );
+        let detections = detect_text(self.options, &text, &self.detections, self.runner);
+        self.detections.extend(detections);
+        self.derived = Some(Derived { text, file });
+        Ok(())
+    }
+
+    /// Validation turns `derive_command` away first
+    #[cfg(not(feature = "ocr"))]
+    fn derive(&mut self, _derive_command: &str) -> Result<()> {
+        anyhow::bail!("This faucet was built without the 'ocr' feature")
+    }
+}
+
+/// Runs the text detectors, leaving out a `word` detection of more than `word_max_words` words
+fn detect_text(options: &Options, text: &str, known: &[detect::Detection], runner: &dyn Runner) -> Vec<detect::Detection> {
+    let mut detections = detect::detect(text, known, runner);
+    detections.retain(|detection| {
+        detection.name != "word"
+            || detection.vars.iter().any(|(key, value)| {
+                key == "WORD_COUNT" && value.parse().is_ok_and(|count: usize| count <= options.word_max_words)
+            })
+    });
+    detections
+}
+
+/// Each command's score and the scorers that contributed to it, as (description, delta)
+type ScoredCommands = IndexMap<String, (Command, i32, Vec<(String, i32)>)>;
+type RankedCommand<'a> = (usize, (&'a String, &'a (Command, i32, Vec<(String, i32)>)));
+
+fn score_commands(config: &Config, plumb: &Plumb, cache: &ProbeCache, head_only: bool) -> Result<ScoredCommands> {
+    let data_fingerprint = plumb.fingerprint();
+    let mut scored_commands: ScoredCommands = config
+        .commands
+        .iter()
+        .filter(|(label, cmd)| {
+            let accepted = cmd.accepts(plumb.mime());
+            if !accepted {
+                trace!("Command '{label}' does not accept {}", plumb.mime());
+            }
+            accepted
+        })
+        .map(|(label, cmd)| (label.clone(), (cmd.clone(), 0, Vec::new())))
+        .collect();
+
+    let scorers: Vec<&Scorer> = config.scorers.iter().filter(|scorer| !head_only || scorer.is_head()).collect();
+    // Command scorers wait on processes, so they are all probed at once, while the rest are
+    // checked; their effects still apply in config order
+    let fired: Vec<bool> = std::thread::scope(|scope| -> Result<_> {
+        let probes: Vec<_> = scorers
+            .iter()
+            .map(|scorer| {
+                matches!(scorer.condition, Condition::Command { .. })
+                    .then(|| scope.spawn(move || scorer.fires(plumb, cache, data_fingerprint)))
+            })
+            .collect();
+        scorers
+            .iter()
+            .zip(probes)
+            .map(|(scorer, probe)| match probe {
+                Some(probe) => probe.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+                None => scorer.fires(plumb, cache, data_fingerprint),
+            })
+            .collect()
+    })?;
+    scorers
+        .into_iter()
+        .zip(fired)
+        .filter(|(_, fired)| *fired)
+        .for_each(|(scorer, _)| {
+            if let Effect::Veto { veto } = &scorer.effect {
+                for command_label in veto.iter() {
+                    if scored_commands.shift_remove(command_label).is_some() {
+                        trace!("Vetoed command '{command_label}'");
+                    }
+                }
+                return;
+            }
+            let scripted = match &scorer.effect {
+                Effect::Lua { lua } => lua::deltas(lua, plumb),
+                Effect::External { external } => external::deltas(external, plumb),
+                _ => Vec::new(),
+            };
+            let deltas = scorer.effect.deltas().chain(scripted.iter().map(|(label, delta)| (label.as_str(), *delta)));
+            add_deltas(config, &mut scored_commands, &scorer.describe(), deltas);
+        });
+    if !head_only {
+        for plugin in &config.plugins {
+            let deltas = plugin.deltas(plumb);
+            let deltas = deltas.iter().map(|(label, delta)| (label.as_str(), *delta));
+            add_deltas(config, &mut scored_commands, &format!("plugin '{}'", plugin.name), deltas);
+        }
+    }
+    merge_duplicate_actions(&mut scored_commands, plumb);
+    Ok(scored_commands)
+}
+
+/// Adds a scorer's deltas to the commands still in the running, attributing them to `scorer`
+fn add_deltas<'a>(
+    config: &Config,
+    scored_commands: &mut ScoredCommands,
+    scorer: &str,
+    deltas: impl Iterator<Item = (&'a str, i32)>,
+) {
+    for (command_label, score_change) in deltas {
+        if !config.commands.contains_key(command_label) {
+            warn!("{scorer} scored non-existent command '{command_label}'");
+        }
+        if config.options.veto_threshold.is_some_and(|threshold| score_change <= threshold) {
+            if scored_commands.shift_remove(command_label).is_some() {
+                trace!("{scorer} vetoed command '{command_label}' with {score_change}");
+            }
+            continue;
+        }
+        if let Some((command, score, reasons)) = scored_commands.get_mut(command_label) {
+            trace!(
+                "Updating score for command '{}' ('{}'): {} -> {}",
+                command.display,
+                command.action,
+                *score,
+                *score + score_change
+            );
+            *score += score_change;
+            reasons.push((scorer.to_string(), score_change));
+        }
+    }
+}
+
+/// What a command runs, as compared when merging duplicates
+fn action_key(command: &Command, plumb: &Plumb) -> String {
+    let action = &command.action;
+    let key = match action {
+        Action::Shell { .. } => action.to_string(),
+        // Builtins are compared as run, e.g. two URL templates that expand alike
+        Action::Builtin(_) => plumb.expand(&action.to_string()).unwrap_or_else(|_| action.to_string()),
+    };
+    // The same action on differently rewritten data is another action
+    match &command.rewrite {
+        Some(rewrite) => format!("{key}\0{}\0{}", rewrite.pattern, rewrite.replacement),
+        None => key,
+    }
+}
+
+/// Folds commands that would run the same thing into the first one defined, summing their scores,
+/// so the menu doesn't offer one action twice under different labels
+fn merge_duplicate_actions(scored_commands: &mut ScoredCommands, plumb: &Plumb) {
+    let mut first_by_action: std::collections::HashMap<String, &String> = std::collections::HashMap::new();
+    let mut duplicates = Vec::new();
+    for (label, (command, _, _)) in scored_commands.iter() {
+        let first = *first_by_action.entry(action_key(command, plumb)).or_insert(label);
+        if first != label {
+            duplicates.push((label.clone(), first.clone()));
+        }
+    }
+    for (label, first) in duplicates {
+        let Some((_, score, reasons)) = scored_commands.shift_remove(&label) else {
+            continue;
+        };
+        trace!("Merging command '{label}' into '{first}', which runs the same action");
+        if let Some((_, first_score, first_reasons)) = scored_commands.get_mut(&first) {
+            *first_score += score;
+            first_reasons.extend(reasons);
+        }
+    }
+}
+
+fn rank_commands(scored_commands: &ScoredCommands) -> Vec<RankedCommand<'_>> {
+    let mut sorted_commands: Vec<_> = scored_commands
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, (command, score, _)))| {
+            *score > 0 && command.min_score.as_ref().is_none_or(|min_score| *score >= min_score.value())
+        })
+        .collect();
+    sorted_commands.sort_by(|(a_idx, (_, (_, a_score, _))), (b_idx, (_, (_, b_score, _)))| {
+        b_score.cmp(a_score).then_with(|| a_idx.cmp(b_idx))
+    });
+    sorted_commands
+}
+
+fn should_auto_select(options: &Options, sorted_commands: &[RankedCommand]) -> bool {
+    if !options.auto_select {
+        return false;
+    }
+    let Some((_, (_, (_, score, _)))) = sorted_commands.first() else {
+        return false;
+    };
+    (sorted_commands.len() == 1 && *score > options.auto_select_min_threshold)
+        || (sorted_commands.len() >= 2 && {
+            let (_, (_, (_, second_score, _))) = &sorted_commands[1];
+            *score - *second_score > options.auto_select_max_threshold
+                && *score > options.auto_select_min_threshold
+        })
+}
+
+/// `--headless` exit status when the caller has to choose, with the candidates on stdout
+const HEADLESS_UNDECIDED: i32 = 2;
+
+enum Decision<'a> {
+    NoMatch,
+    AutoSelect(RankedCommand<'a>),
+    Menu(Vec<RankedCommand<'a>>),
+}
+
+fn decide<'a>(options: &Options, sorted_commands: Vec<RankedCommand<'a>>) -> Decision<'a> {
+    if sorted_commands.is_empty() {
+        Decision::NoMatch
+    } else if should_auto_select(options, &sorted_commands) {
+        Decision::AutoSelect(sorted_commands[0])
+    } else {
+        Decision::Menu(sorted_commands)
+    }
+}
+
+/// One line attributing a score to its scorers: `url (+80), youtube-domain (+40)`
+fn explain(reasons: &[(String, i32)]) -> String {
+    reasons
+        .iter()
+        .map(|(scorer, delta)| format!("{scorer} ({delta:+})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `--no-exec` output: what faucet would have done, paged when printing to a terminal
+fn decision_report(plumb: &Plumb, decision: &Decision) -> String {
+    match decision {
+        Decision::NoMatch => "No scorers matched\n".to_string(),
+        Decision::AutoSelect((_, (label, (command, score, reasons)))) => {
+            format!(
+                "Would auto-select '{label}' ({score}): {}\n        matched: {}\n",
+                command.action,
+                explain(reasons)
+            )
+        }
+        Decision::Menu(sorted_commands) => {
+            let mut report = "Would offer:\n".to_string();
+            for (_, (label, (command, score, reasons))) in sorted_commands {
+                report.push_str(&format!("{score:>6}  {label}  {}  ({})\n", plumb.display(command), command.action));
+                report.push_str(&format!("        matched: {}\n", explain(reasons)));
+            }
+            report
+        }
+    }
+}
+
+/// Prints a report, through the pager when printing to a terminal
+fn page(options: &Options, report: &str) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        print!("{report}");
+        return Ok(());
+    }
+    let pager = resolve_program(options.pager.as_deref(), &["PAGER"], "less");
+    let Some((program, args)) = pager.split_first() else {
+        print!("{report}");
+        return Ok(());
+    };
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(report.as_bytes())?;
+    child.wait()?;
+    Ok(())
+}
+
+/// Whether `FAUCET_DISABLE` is set (to anything but `0`), which turns plumbing off whatever the config says
+fn disabled_by_env() -> bool {
+    std::env::var("FAUCET_DISABLE").is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Subcommands taking the config; `watch`, `daemon`, `client` and `spool-reap` come before it is loaded
+const SUBCOMMANDS: [&str; 9] = [
+    "simulate",
+    "bench",
+    "sync-selections",
+    "serve",
+    "clean",
+    "config",
+    "pick-history",
+    "validate",
+    "desktop-entry",
+];
+
+/// The `faucet` command line, run with the process's arguments; the binary is only this and logging
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let mut flags = cli::take_flags(&mut args)?;
+    // `faucet score <input>` is `faucet --explain <input>`
+    if args.get(1).is_some_and(|arg| arg == "score") {
+        args.remove(1);
+        flags.explain = true;
+    }
+    // `faucet handle <path-or-uri>`, as a desktop entry runs it, is `faucet file <path>` or `faucet <uri>`
+    if args.get(1).is_some_and(|arg| arg == "handle") {
+        let input = desktop::input(&args[2..])?;
+        args.splice(1.., input);
+    }
+    let config_path = match (&flags.config, std::env::var_os("FAUCET_CONFIG").filter(|path| !path.is_empty())) {
+        (Some(path), _) => path.clone(),
+        (None, Some(path)) => path.into(),
+        (None, None) => {
+            let dir = dirs::config_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?.join("faucet");
+            // faucet.toml is read when there is no faucet.yaml
+            let toml = dir.join("faucet.toml");
+            if !dir.join("faucet.yaml").exists() && toml.exists() { toml } else { dir.join("faucet.yaml") }
+        }
+    };
+    let port = cli::take_port(&mut args)?;
+
+    // Watching starts (and carries on) even while the config is broken
+    if args.get(1).is_some_and(|arg| arg == "watch") {
+        for cmd in ["file", "sh"] {
+            check_command_exists(cmd)?;
+        }
+        return Ok(watch::run(&config_path, &flags, &args[2..])?);
+    }
+    if args.get(1).is_some_and(|arg| arg == "daemon") {
+        #[cfg(feature = "daemon")]
+        {
+            for cmd in ["file", "sh"] {
+                check_command_exists(cmd)?;
+            }
+            return Ok(daemon::run(&config_path, &flags, &args[2..])?);
+        }
+        #[cfg(not(feature = "daemon"))]
+        return Err(anyhow::anyhow!("This faucet was built without the 'daemon' feature").into());
+    }
+    // The client leaves the config to the server
+    if args.get(1).is_some_and(|arg| arg == "client") {
+        #[cfg(feature = "daemon")]
+        return Ok(client::run(&args[2..])?);
+        #[cfg(not(feature = "daemon"))]
+        return Err(anyhow::anyhow!("This faucet was built without the 'daemon' feature").into());
+    }
+    // The reaper is started with no flags, so it can't count on finding the config
+    if args.get(1).is_some_and(|arg| arg == "spool-reap") {
+        return Ok(spool::run(&args[2..])?);
+    }
+
+    // Subcommands run now and then, so they check everything; a plumb checks what it needs
+    let subcommand = args.get(1).map(String::as_str).filter(|arg| SUBCOMMANDS.contains(arg));
+    let validation = if subcommand.is_some() { Validation::Full } else { Validation::Lazy };
+    // Before the config, which may be what is broken
+    if subcommand.is_none() && disabled_by_env() {
+        debug!("FAUCET_DISABLE is set, not plumbing anything");
+        return Ok(());
+    }
+    let (mut config, config_content) = load_config(&config_path, &flags, validation)?;
+    i18n::init(config.options.locale.as_deref());
+    if let Some(port) = &port {
+        let ports = config.commands.values().flat_map(|command| &command.ports).unique().join(", ");
+        config.commands.retain(|_, command| command.ports.contains(port));
+        if config.commands.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No command is registered under port '{port}', known ports: {ports}"
+            )
+            .into());
+        }
+    }
+    if let Some(subcommand) = subcommand {
+        let required: &[&str] = match (subcommand, &config.options.display_server) {
+            ("clean" | "config" | "validate" | "desktop-entry", _) => &[],
+            ("sync-selections", DisplayServer::X11) => &["xclip"],
+            ("sync-selections", DisplayServer::Wayland) => &["wl-paste", "wl-copy"],
+            ("sync-selections", DisplayServer::Windows) => &[],
+            _ => &["file", "sh"],
+        };
+        for cmd in required {
+            check_command_exists(cmd)?;
+        }
+        match subcommand {
+            "simulate" => simulate::run(&config, &args[2..])?,
+            "bench" => bench::run(&config, &config_content, &config_path, &args[2..])?,
+            #[cfg(feature = "daemon")]
+            "serve" => protocol::run(&config, &config_path, &flags, &args[2..])?,
+            #[cfg(not(feature = "daemon"))]
+            "serve" => return Err(anyhow::anyhow!("This faucet was built without the 'daemon' feature").into()),
+            "clean" => runtime::clean(&args[2..], std::time::Duration::from_secs(config.options.spool_max_age_secs))?,
+            "config" if args.get(2).is_some_and(|action| action == "validate") => validated(&config_path, &config),
+            "config" => dump::run(&config, &args[2..])?,
+            "desktop-entry" => desktop::run(&config, flags.config.as_deref(), &args[2..])?,
+            "pick-history" => history::pick(&config, &flags, &args[2..])?,
+            "validate" => validated(&config_path, &config),
+            _ => selection::run(&config.options, &args[2..])?,
+        }
+        return Ok(());
+    }
+
+    if !config.options.enabled {
+        debug!("Faucet is disabled in config, not plumbing anything");
+        return Ok(());
+    }
+
+    // A report runs nothing, so it needs none of the programs
+    if !(flags.no_exec || flags.explain || flags.json) {
+        validate_environment(&config)?;
+    }
+
+    debug!(
+        "Loaded {} commands and {} scorers",
+        config.commands.len(),
+        config.scorers.len()
+    );
+    let probe_cache = ProbeCache::new(config.options.probe_cache_size);
+    let cancel = CancelToken::new();
+    cancel::on_signals(cancel.clone())?;
+    // Pressing the keybinding again gives up on the plumb still scoring, as it closes the picker
+    if matches!(config.options.on_busy, lock::OnBusy::Replace) {
+        cancel.supersede()?;
+    }
+    let runner = CancellableRunner::new(cancel.clone());
+    let temp_file_handle = runtime::temp_file("faucet_data_")?;
+    let temp_file_path = temp_file_handle.path().to_str()
+        .ok_or_else(|| anyhow::anyhow!("Failed to convert temp file path to string"))?;
+    if flags.fd.is_some() && args.len() > 1 {
+        return Err(anyhow::anyhow!("--fd passes the data, so no input ('{}') can follow it", args[1..].join(" ")).into());
+    }
+    // A caller's pipe is read whole, as stdin is, with no name for `pattern` scorers to match
+    let fd = flags.fd.or_else(|| match &args[1..] {
+        [mode, path] if mode == "file" => cli::fd_path(path),
+        _ => None,
+    });
+    let data_source: &str;
+    let mut source_path = None;
+    let data: Data = match (fd, args.len()) {
+        (Some(fd), _) => {
+            data_source = "stdin";
+            let bytes = std::fs::read(format!("/dev/fd/{fd}"))
+                .map_err(|e| anyhow::anyhow!("Failed to read file descriptor {fd}: {e}"))?;
+            Data::from_bytes(bytes)
+        }
+        (None, 1) => {
+            // Stdin held the config, so the clipboard has the data
+            if !stdin().is_terminal() && !cli::is_stdin(&config_path) {
+                let buffer = match config.options.stream_head_bytes {
+                    Some(head_bytes) => {
+                        let spool = StdinSpool::start(temp_file_path, head_bytes)?;
+                        let spool = if spool.head.is_empty() || flags.no_exec || flags.explain || flags.json || flags.headless {
+                            spool
+                        } else {
+                            match run_early_match(&config, &flags, &probe_cache, &cancel, spool, temp_file_path)? {
+                                Some(spool) => spool,
+                                None => return Ok(()),
+                            }
+                        };
+                        spool.finish().unwrap_or_default()
+                    }
+                    None => {
+                        let mut buffer = Vec::new();
+                        stdin().read_to_end(&mut buffer).map(|_| buffer).unwrap_or_default()
+                    }
+                };
+                if !buffer.is_empty() {
+                    data_source = "stdin";
+                    Data::from_bytes(buffer)
+                } else {
+                    data_source = "clipboard";
+                    Data::from_bytes(clipboard::read(&config.options.display_server, Selection::Clipboard, &runner)?.0)
+                }
+            } else {
+                data_source = "clipboard";
+                Data::from_bytes(clipboard::read(&config.options.display_server, Selection::Clipboard, &runner)?.0)
+            }
+        }
+        (None, 2) if args[1] == "sel" => {
+            // Selection mode only works on X11
+            if matches!(config.options.display_server, DisplayServer::Wayland) {
+                tracing::warn!("'sel' mode only works on X11, falling back to clipboard");
+                data_source = "clipboard";
+                Data::from_bytes(clipboard::read(&config.options.display_server, Selection::Clipboard, &runner)?.0)
+            } else {
+                data_source = "selection";
+                match clipboard::read(&config.options.display_server, Selection::Primary, &runner)? {
+                    (selection_bytes, Some(_)) => Data::Binary(selection_bytes),
+                    (selection_bytes, None) => Data::from_bytes(selection_bytes),
+                }
+            }
+        }
+        (None, 3) if args[1] == "file" => {
+            data_source = "file";
+            let file_path = &args[2];
+            let file_bytes = std::fs::read(file_path)?;
+            source_path = Some(std::path::absolute(file_path)?);
+
+            Data::from_bytes(file_bytes)
+        }
+        (None, _) => {
+            data_source = "command line";
+            Data::Text(args[1..].join(" "))
+        }
+    };
+
+    if let Some(candidates) =
+        plumb_data(&config, &flags, &probe_cache, &cancel, data, data_source, source_path.as_deref(), temp_file_handle)?
+    {
+        println!("{}", serde_json::to_string(&candidates)?);
+        std::process::exit(HEADLESS_UNDECIDED);
+    }
+    Ok(())
+}
+
+/// `faucet validate` (or `faucet config validate`); loading the config has checked it in full,
+/// failing with every problem found
+fn validated(config_path: &std::path::Path, config: &Config) {
+    println!(
+        "{} is valid: {} commands, {} scorers",
+        config_path.display(),
+        config.commands.len(),
+        config.scorers.len()
+    );
+}
+
+/// Plumbs `data` as faucet does once it has read it: scores it, then runs the command that
+/// auto-selects or offers the commands in the picker, unless `flags` ask for a report instead.
+/// With `--headless` the candidates are handed back instead of picked from. Its probes are killed
+/// when `cancel` is cancelled, and it gives up once scoring is over.
+#[allow(clippy::too_many_arguments)]
+fn plumb_data(
+    config: &Config,
+    flags: &cli::Flags,
+    probe_cache: &ProbeCache,
+    cancel: &CancelToken,
+    data: Data,
+    data_source: &str,
+    source_path: Option<&std::path::Path>,
+    temp_file_handle: tempfile::NamedTempFile,
+) -> Result<Option<Vec<ranking::ScoredCommand>>> {
+    let temp_file_path = temp_file_handle.path().to_str()
+        .ok_or_else(|| anyhow::anyhow!("Failed to convert temp file path to string"))?;
+    let data = mirrored(&config.options, data)?;
+    data.write_to_temp_file(temp_file_path)?;
+
+    let runner = CancellableRunner::new(cancel.clone());
+    let text_for_matching = data.get_text_for_matching(&runner, temp_file_path)?;
+    // Viewers and editors often go by the extension, which is only known once the data is
+    let data_file = with_extension(temp_file_handle, data.extension(&text_for_matching))?;
+    // Reaped once this plumb is over, since the picker and scorers only know it from `DATA_FILE`
+    let spooled = (config.options.spool && !data.is_text())
+        .then(|| spool::Reaped::keep(&data_file, std::time::Duration::from_secs(config.options.spool_max_age_secs)))
+        .transpose()?;
+    let temp_file_path = spooled.as_ref().map_or(&*data_file, spool::Reaped::path).to_str()
+        .ok_or_else(|| anyhow::anyhow!("Failed to convert temp file path to string"))?;
+    let options = plumb_options(config, flags, &data, &text_for_matching, data_source);
+    let (data_kind, data_as_text) = match data {
+        Data::Text(ref text) => ("Text", text.clone()),
+        Data::Binary(..) => ("Data", format!("[Binary: {}]", text_for_matching)),
+    };
+
+    debug!(
+        "text_for_matching: {}",
+        redact::redact(&text_for_matching).chars().take(100).collect::<String>()
+    );
+    debug!("{data_kind} from {data_source} to be plumbed: '{}'", redact::redact(&data_as_text));
+
+    let mut plumb = Plumb::with_runner(&options, &data, temp_file_path, data_source, text_for_matching, &runner);
+    plumb.source_path = source_path.map(|path| path.display().to_string());
+    if let Some(derive_command) = &config.options.derive_command {
+        plumb.derive(derive_command)?;
+    }
+
+    let scored_commands = score_commands(config, &plumb, probe_cache, false)?;
+    if cancel.is_cancelled() {
+        anyhow::bail!("The plumb was cancelled");
+    }
+    cancel.scored();
+    let sorted_commands = rank_commands(&scored_commands);
+
+    let decision = decide(&options, sorted_commands.clone());
+    if flags.explain {
+        let report = explain::report(config, &plumb, probe_cache, &scored_commands, &sorted_commands, &decision);
+        page(plumb.options, &report)?;
+        return Ok(None);
+    }
+    if flags.json {
+        println!("{}", serde_json::to_string(&ranking::ranking(&plumb, &sorted_commands))?);
+        return Ok(None);
+    }
+    if flags.no_exec {
+        page(plumb.options, &decision_report(&plumb, &decision))?;
+        return Ok(None);
+    }
+    // Confirming would take a picker too, so a command asking for it is left to the caller
+    if flags.headless
+        && !matches!(decision, Decision::AutoSelect((_, (_, (command, _, _)))) if command.confirm.is_none())
+    {
+        return Ok(Some(ranking::candidates(&plumb, &sorted_commands, true)));
+    }
+    match decision {
+        Decision::NoMatch => {
+            debug!("No scorers matched");
+        }
+        Decision::AutoSelect((_, (label, (command, score, reasons)))) => {
+            debug!(
+                "Matched auto-select (max threshold: {}, min threshold: {}): {} with score of {} (matched: {})",
+                options.auto_select_max_threshold,
+                options.auto_select_min_threshold,
+                label,
+                score,
+                explain(reasons)
+            );
+            if let Some(cooldown_secs) = command.cooldown_secs
+                && !cooldown::claim(label, plumb.fingerprint(), cooldown_secs)?
+            {
+                debug!("'{label}' already ran for this data in the last {cooldown_secs}s, not running it again");
+                return Ok(None);
+            }
+            plumb.captures = capture_vars(config, &plumb, label);
+            plumb.run(label, command)?;
+        }
+        Decision::Menu(sorted_commands) => {
+            let displays: Vec<String> = sorted_commands
+                .iter()
+                .map(|(_, (_, (cmd, _, _)))| plumb.display(cmd))
+                .collect();
+            let labels = displays.join("\n");
+            debug!("Concatenated labels to dmenu: {labels}");
+            let selected_label = plumb.pick(&labels)?;
+            let selected_command = sorted_commands
+                .iter()
+                .zip(&displays)
+                .find(|(_, display)| **display == selected_label)
+                .map(|((_, command), _)| command);
+
+            if let Some((label, (command, _, _))) = selected_command {
+                debug!("Selected command label: {label}");
+                plumb.captures = capture_vars(config, &plumb, label);
+                plumb.run(label, command)?;
+            } else {
+                debug!("Didn't select a command in dmenu")
+            }
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,114 @@
+//! Signals for the long-running modes (`faucet serve`, `faucet watch`, `faucet daemon`):
+//! - `SIGHUP` reloads the config (a config that fails to load is logged and the old one kept)
+//! - `SIGUSR1` logs the process's state
+//! - `SIGUSR2` cancels the request in hand (the latest, when `serve` has several), killing its
+//!   probes (see `cancel`)
+//! - `SIGTERM` waits for the requests in hand, and the commands they run, to finish, removes the
+//!   runtime and spool files it left, then exits
+
+use crate::cancel::CancelToken;
+use crate::cli::Flags;
+use crate::{Config, Validation, load_config};
+use anyhow::Result;
+use signal_hook::consts::{SIGHUP, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::Instant;
+use tracing::{error, info};
+
+pub struct Lifecycle {
+    mode: &'static str,
+    config_path: PathBuf,
+    flags: Flags,
+    started: Instant,
+    reload: AtomicBool,
+    handled: AtomicU64,
+    busy: RwLock<()>,
+    /// The request in hand's
+    cancel: Mutex<CancelToken>,
+}
+
+impl Lifecycle {
+    /// Starts handling signals on a background thread
+    pub fn install(mode: &'static str, config_path: PathBuf, flags: Flags) -> Result<Arc<Self>> {
+        let lifecycle = Arc::new(Self {
+            mode,
+            config_path,
+            flags,
+            started: Instant::now(),
+            reload: AtomicBool::new(false),
+            handled: AtomicU64::new(0),
+            busy: RwLock::new(()),
+            cancel: Mutex::new(CancelToken::new()),
+        });
+        let mut signals = Signals::new([SIGHUP, SIGUSR1, SIGUSR2, SIGTERM])?;
+        let handler = lifecycle.clone();
+        std::thread::spawn(move || {
+            for signal in signals.forever() {
+                match signal {
+                    SIGHUP => {
+                        info!("SIGHUP: reloading the config");
+                        handler.reload.store(true, Ordering::SeqCst);
+                    }
+                    SIGUSR1 => handler.log_state(),
+                    SIGUSR2 => {
+                        info!("SIGUSR2: cancelling the request in hand");
+                        handler.cancel.lock().unwrap_or_else(|e| e.into_inner()).cancel();
+                    }
+                    _ => handler.shut_down(),
+                }
+            }
+        });
+        Ok(lifecycle)
+    }
+
+    /// Held while handling a request, so shutting down waits for every request in hand
+    pub fn busy(&self) -> RwLockReadGuard<'_, ()> {
+        self.busy.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A token for the request about to be handled, cancelled by `SIGUSR2`
+    pub fn cancellable(&self) -> CancelToken {
+        let token = CancelToken::new();
+        *self.cancel.lock().unwrap_or_else(|e| e.into_inner()) = token.clone();
+        token
+    }
+
+    /// Counts a handled request (a score, an evaluation) for the state log
+    pub fn handled(&self) {
+        self.handled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether a reload was asked for since the last call
+    pub fn take_reload(&self) -> bool {
+        self.reload.swap(false, Ordering::SeqCst)
+    }
+
+    /// The config as it is on disk now
+    pub fn load_config(&self) -> Result<Config> {
+        Ok(load_config(&self.config_path, &self.flags, Validation::Full)?.0)
+    }
+
+    fn log_state(&self) {
+        info!(
+            "faucet {} (pid {}): up {}s, {} handled, config {}",
+            self.mode,
+            std::process::id(),
+            self.started.elapsed().as_secs(),
+            self.handled.load(Ordering::Relaxed),
+            self.config_path.display()
+        );
+    }
+
+    fn shut_down(&self) -> ! {
+        let _idle = self.busy.write().unwrap_or_else(|e| e.into_inner());
+        info!("SIGTERM: shutting down");
+        match crate::runtime::remove_own() {
+            Ok(removed) => info!("Removed {removed} leftover runtime files"),
+            Err(e) => error!("Failed to remove leftover runtime files: {e}"),
+        }
+        std::process::exit(0)
+    }
+}
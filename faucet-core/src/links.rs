@@ -0,0 +1,89 @@
+//! Link extraction from Markdown and HTML, so copied rich text can be plumbed as the URLs inside it.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static MARKDOWN_INLINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"!?\[([^\]]*)\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)"#).unwrap());
+static MARKDOWN_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s{0,3}\[([^\]]+)\]:\s*<?(\S+?)>?(?:\s.*)?$").unwrap());
+static HTML_ANCHOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<a\s[^>]*?href\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))[^>]*>(.*?)</a\s*>"#).unwrap()
+});
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static BARE_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\b(?:https?|ftp)://[^\s<>"'()\[\]]+"#).unwrap());
+
+pub struct Link {
+    pub target: String,
+    pub title: Option<String>,
+}
+
+impl Link {
+    /// One line for the picker: `title — target`, or just the target
+    pub fn entry(&self) -> String {
+        match &self.title {
+            Some(title) => format!("{title} — {}", self.target),
+            None => self.target.clone(),
+        }
+    }
+}
+
+/// Links in document order, deduplicated by target. Bare URLs count too, so plain lists of links work.
+pub fn extract(text: &str) -> Vec<Link> {
+    let mut found: Vec<(usize, Link)> = Vec::new();
+    let mut push = |position: usize, target: &str, title: &str| {
+        let title = HTML_TAG.replace_all(title, "");
+        let title = decode_entities(title.split_whitespace().collect::<Vec<_>>().join(" ").as_str());
+        found.push((
+            position,
+            Link {
+                target: decode_entities(target.trim()),
+                title: Some(title).filter(|title| !title.is_empty() && title != target),
+            },
+        ));
+    };
+    for captures in MARKDOWN_INLINE.captures_iter(text) {
+        push(captures.get(0).unwrap().start(), &captures[2], &captures[1]);
+    }
+    for captures in MARKDOWN_REFERENCE.captures_iter(text) {
+        push(captures.get(0).unwrap().start(), &captures[2], &captures[1]);
+    }
+    for captures in HTML_ANCHOR.captures_iter(text) {
+        let target = captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .or_else(|| captures.get(3))
+            .map_or("", |target| target.as_str());
+        push(captures.get(0).unwrap().start(), target, &captures[4]);
+    }
+    for url in BARE_URL.find_iter(text) {
+        let target = url.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        push(url.start(), target, "");
+    }
+
+    found.sort_by_key(|(position, _)| *position);
+    let mut links: Vec<Link> = Vec::new();
+    for (_, link) in found {
+        if link.target.is_empty() || link.target.starts_with('#') || link.target.starts_with("javascript:") {
+            continue;
+        }
+        match links.iter_mut().find(|existing| existing.target == link.target) {
+            Some(existing) => {
+                if existing.title.is_none() {
+                    existing.title = link.title;
+                }
+            }
+            None => links.push(link),
+        }
+    }
+    links
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,138 @@
+//! Keeps one picker open at a time. The instance showing it holds `picker.lock` in the runtime
+//! dir, with its picker's process group written inside; what a second instance does is `on_busy`.
+//! Queued instances take a ticket in `queue/` and are shown in arrival order, the picker getting
+//! the number still waiting as `$FAUCET_QUEUED`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+const POLL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnBusy {
+    /// Wait for the open picker to close, then show this one
+    #[default]
+    Queue,
+    /// Close the open picker and show this one instead
+    Replace,
+    /// Give up, notifying that a picker is already open
+    Reject,
+}
+
+/// Held while the picker is shown; released on drop
+pub struct PickerLock {
+    file: File,
+    /// Plumbs waiting behind this one
+    pub queued: usize,
+}
+
+impl PickerLock {
+    /// Records the picker's process group so a replacing instance can close it
+    pub fn record(&mut self, process_group: u32) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        write!(self.file, "{process_group}")?;
+        Ok(())
+    }
+}
+
+/// The lock, or `None` when `on_busy` is `reject` and another picker is open
+pub fn acquire(on_busy: OnBusy) -> Result<Option<PickerLock>> {
+    let dir = crate::runtime::dir()?;
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dir.join("picker.lock"))?;
+    let queue = dir.join("queue");
+    match on_busy {
+        OnBusy::Queue => return wait_in_queue(file, &queue).map(Some),
+        _ if try_lock(&file)? => {}
+        OnBusy::Reject => return Ok(None),
+        OnBusy::Replace => {
+            let mut process_group = String::new();
+            file.read_to_string(&mut process_group)?;
+            let process_group = process_group.trim();
+            if process_group.bytes().all(|b| b.is_ascii_digit()) && !process_group.is_empty() {
+                debug!("Closing the open picker (process group {process_group})");
+                std::process::Command::new("kill")
+                    .args(["-TERM", "--", &format!("-{process_group}")])
+                    .status()?;
+            }
+            file.lock()?;
+        }
+    }
+    Ok(Some(PickerLock { file, queued: waiting(&queue).len() }))
+}
+
+fn try_lock(file: &File) -> Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Removes the ticket when its plumb gets the picker or gives up
+struct Ticket(PathBuf);
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Takes a ticket and waits until it is the oldest and the picker is free
+fn wait_in_queue(file: File, queue: &Path) -> Result<PickerLock> {
+    std::fs::create_dir_all(queue)?;
+    let arrived = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    // Zero-padded so tickets sort by arrival
+    let ticket = Ticket(queue.join(format!("{arrived:030}-{}", std::process::id())));
+    File::create(&ticket.0)?;
+    let mut announced = false;
+    loop {
+        let waiting = waiting(queue);
+        if waiting.first() == Some(&ticket.0) && try_lock(&file)? {
+            return Ok(PickerLock { file, queued: waiting.len() - 1 });
+        }
+        if !announced {
+            debug!("A picker is already open, waiting for it to close");
+            announced = true;
+        }
+        std::thread::sleep(POLL);
+    }
+}
+
+/// Tickets in arrival order, dropping those whose plumb died without removing them
+fn waiting(queue: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(queue) else {
+        return Vec::new();
+    };
+    let mut tickets: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|ticket| {
+            let pid = ticket
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.rsplit_once('-'))
+                .map(|(_, pid)| pid.to_string())
+                .unwrap_or_default();
+            // Without /proc there is no telling, so every ticket counts
+            let alive = !Path::new("/proc").exists() || !pid.is_empty() && Path::new("/proc").join(&pid).exists();
+            if !alive {
+                let _ = std::fs::remove_file(ticket);
+            }
+            alive
+        })
+        .collect();
+    tickets.sort();
+    tickets
+}
@@ -0,0 +1,100 @@
+//! `lua` scorers: a script inline in the config, run by an embedded Lua 5.4, that returns the
+//! deltas for the data as a table of command label to score change (or nothing, for none). It
+//! can weigh up several facts at once where a `command` scorer would start a shell for each.
+//!
+//! ```yaml
+//! - lua: |
+//!     if mime:find("^image/") and size > 5 * 1024 * 1024 then
+//!       return { upload = 15, viewer = -5 }
+//!     end
+//! ```
+//!
+//! The script sees `text` (nil for binary data), `mime`, `size` in bytes, `source`, `path` (the
+//! file `faucet file` plumbs, else nil) and `detected` (the names of the detectors that fired).
+//! Only the `string`, `table`, `math` and `utf8` libraries are loaded, so a script can't start
+//! processes or touch files, and one running past `INSTRUCTIONS` is stopped.
+
+use crate::Plumb;
+
+/// Lua VM instructions a script may run for one plumb
+#[cfg(feature = "lua")]
+const INSTRUCTIONS: u64 = 10_000_000;
+
+/// Why `script` can't run, such as a syntax error
+pub fn problem(script: &str) -> Option<String> {
+    #[cfg(feature = "lua")]
+    return vm().and_then(|lua| lua.load(script).into_function().map(drop)).err().map(|e| format!("Bad lua script: {e}"));
+    #[cfg(not(feature = "lua"))]
+    {
+        let _ = script;
+        Some("This faucet was built without the 'lua' feature".to_string())
+    }
+}
+
+/// The deltas `script` returns for the data; a script that fails is logged and scores nothing
+pub fn deltas(script: &str, plumb: &Plumb) -> Vec<(String, i32)> {
+    #[cfg(feature = "lua")]
+    return run(script, plumb).unwrap_or_else(|e| {
+        tracing::error!("Lua scorer failed: {e}");
+        Vec::new()
+    });
+    #[cfg(not(feature = "lua"))]
+    {
+        let _ = (script, plumb);
+        Vec::new()
+    }
+}
+
+#[cfg(feature = "lua")]
+fn vm() -> mlua::Result<mlua::Lua> {
+    use mlua::{HookTriggers, Lua, LuaOptions, StdLib};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let lua = Lua::new_with(StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8, LuaOptions::default())?;
+    const EVERY: u32 = 10_000;
+    let ran = AtomicU64::new(0);
+    lua.set_hook(HookTriggers::new().every_nth_instruction(EVERY), move |_, _| {
+        if ran.fetch_add(EVERY.into(), Ordering::Relaxed) >= INSTRUCTIONS {
+            return Err(mlua::Error::runtime(format!("ran past {INSTRUCTIONS} instructions")));
+        }
+        Ok(())
+    });
+    Ok(lua)
+}
+
+#[cfg(feature = "lua")]
+fn run(script: &str, plumb: &Plumb) -> mlua::Result<Vec<(String, i32)>> {
+    use mlua::Value;
+
+    let lua = vm()?;
+    let globals = lua.globals();
+    if plumb.data.is_text() {
+        globals.set("text", plumb.text.as_str())?;
+    }
+    globals.set("mime", plumb.mime())?;
+    globals.set("size", plumb.size())?;
+    globals.set("source", plumb.source)?;
+    globals.set("path", plumb.source_path.as_deref())?;
+    globals.set("detected", plumb.detections.iter().map(|detection| detection.name).collect::<Vec<_>>())?;
+    let deltas = match lua.load(script).set_name("lua scorer").eval::<Value>()? {
+        Value::Nil => return Ok(Vec::new()),
+        Value::Table(table) => table,
+        other => return Err(mlua::Error::runtime(format!("expected a table of deltas, got a {}", other.type_name()))),
+    };
+    let mut scores = Vec::new();
+    for pair in deltas.pairs::<String, Value>() {
+        let (label, delta) = pair?;
+        let delta = match delta {
+            Value::Integer(delta) => i32::try_from(delta).ok(),
+            Value::Number(delta) if delta.fract() == 0.0 => Some(delta as i32),
+            _ => None,
+        };
+        match delta {
+            Some(delta) => scores.push((label, delta)),
+            None => return Err(mlua::Error::runtime(format!("the delta for '{label}' is not a whole number"))),
+        }
+    }
+    // Tables iterate in no particular order
+    scores.sort();
+    Ok(scores)
+}
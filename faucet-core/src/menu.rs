@@ -0,0 +1,159 @@
+//! The config's `menu` section picks the program that shows the picker: one of the known
+//! `BACKENDS` by name, or any shell `command` that reads entries on stdin and prints the chosen
+//! one. Either way it gets the `FAUCET_PROMPT`/`FAUCET_MENU_*` vars and `menu_args` as `"$@"`.
+//! Without the section `options.dmenu_command` is used. A new picker is one more `Backend`.
+//!
+//! With no display to show it on (over SSH, on a console), a terminal picker stands in: fzf, or
+//! failing that a numbered list read back from the tty (`options.terminal_picker`).
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+
+pub struct Backend {
+    pub name: &'static str,
+    /// Checked for in `PATH` before plumbing
+    pub program: &'static str,
+    pub command: &'static str,
+}
+
+/// The first is the default `options.dmenu_command`
+pub const BACKENDS: &[Backend] = &[
+    Backend {
+        name: "dmenu",
+        program: "dmenu",
+        command: r#"dmenu -l "$FAUCET_MENU_LINES" -i -p "$FAUCET_PROMPT" ${FAUCET_MENU_MONITOR:+-m "$FAUCET_MENU_MONITOR"} "$@""#,
+    },
+    Backend {
+        name: "rofi",
+        program: "rofi",
+        command: r#"rofi -dmenu -i -l "$FAUCET_MENU_LINES" -p "$FAUCET_PROMPT" ${FAUCET_MENU_MONITOR:+-m "$FAUCET_MENU_MONITOR"} "$@""#,
+    },
+    Backend {
+        name: "bemenu",
+        program: "bemenu",
+        command: r#"bemenu -i -l "$FAUCET_MENU_LINES" -p "$FAUCET_PROMPT" ${FAUCET_MENU_MONITOR:+-m "$FAUCET_MENU_MONITOR"} "$@""#,
+    },
+    Backend {
+        name: "wofi",
+        program: "wofi",
+        command: r#"wofi --dmenu -i -L "$FAUCET_MENU_LINES" -p "$FAUCET_PROMPT" "$@""#,
+    },
+    Backend {
+        name: "fuzzel",
+        program: "fuzzel",
+        command: r#"fuzzel --dmenu -l "$FAUCET_MENU_LINES" -p "$FAUCET_PROMPT" ${FAUCET_MENU_OUTPUT:+-o "$FAUCET_MENU_OUTPUT"} "$@""#,
+    },
+    Backend {
+        name: "fzf",
+        program: "fzf",
+        command: r#"fzf --reverse --height "$((FAUCET_MENU_LINES + 2))" --prompt "$FAUCET_PROMPT" "$@""#,
+    },
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminalPicker {
+    /// fzf when installed, else the built-in list
+    #[default]
+    Auto,
+    Fzf,
+    Builtin,
+    /// Always the configured picker, display or not
+    Off,
+}
+
+impl TerminalPicker {
+    /// The terminal picker to use in place of the configured one: only when there is no display
+    /// but there is a terminal. Never `Auto` or `Off`.
+    pub fn resolve(self) -> Option<TerminalPicker> {
+        let has_display = ["DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()));
+        if self == TerminalPicker::Off || has_display || std::fs::File::open("/dev/tty").is_err() {
+            return None;
+        }
+        Some(match self {
+            TerminalPicker::Auto if on_path("fzf") => TerminalPicker::Fzf,
+            TerminalPicker::Auto => TerminalPicker::Builtin,
+            picker => picker,
+        })
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// The built-in terminal picker: lists `entries` on the tty and reads back a number, or text
+/// that only one entry contains
+pub fn pick_on_tty(entries: &str, prompt: &str) -> Result<String> {
+    let mut tty = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let entries: Vec<&str> = entries.lines().collect();
+    for (index, entry) in entries.iter().enumerate() {
+        writeln!(tty, "{:>3}) {entry}", index + 1)?;
+    }
+    write!(tty, "{prompt}")?;
+    tty.flush()?;
+    let mut answer = String::new();
+    BufReader::new(tty.try_clone()?).read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(String::new());
+    }
+    if let Ok(number) = answer.parse::<usize>() {
+        return number
+            .checked_sub(1)
+            .and_then(|index| entries.get(index))
+            .map(|entry| entry.to_string())
+            .ok_or_else(|| anyhow!("No entry {number}"));
+    }
+    let needle = answer.to_lowercase();
+    let mut matching = entries.iter().filter(|entry| entry.to_lowercase().contains(&needle));
+    match (matching.next(), matching.next()) {
+        (Some(entry), None) => Ok(entry.to_string()),
+        (None, _) => Err(anyhow!("No entry matches '{answer}'")),
+        (Some(_), Some(_)) => Err(anyhow!("More than one entry matches '{answer}'")),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Menu {
+    /// One of `BACKENDS`
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// A picker command of one's own, run with `sh -c`
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+impl Menu {
+    fn backend(&self) -> Option<&'static Backend> {
+        let name = self.backend.as_deref()?;
+        BACKENDS.iter().find(|backend| backend.name == name)
+    }
+
+    /// What is wrong with the section, if anything
+    pub fn problem(&self) -> Option<String> {
+        match (&self.backend, &self.command) {
+            (Some(_), Some(_)) => Some("Set either a backend or a command, not both".to_string()),
+            (None, None) => Some("Set a backend or a command".to_string()),
+            (Some(name), None) if self.backend().is_none() => Some(format!(
+                "Unknown menu backend '{name}', expected one of: {}",
+                BACKENDS.iter().map(|backend| backend.name).collect::<Vec<_>>().join(", ")
+            )),
+            _ => None,
+        }
+    }
+
+    /// The picker's shell command
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref().or_else(|| self.backend().map(|backend| backend.command))
+    }
+
+    /// The program that must be installed, unknown for a command of one's own
+    pub fn program(&self) -> Option<&'static str> {
+        self.backend().map(|backend| backend.program)
+    }
+}
@@ -0,0 +1,97 @@
+//! The built-in rewrite pack behind `options.mirrors`: links to a site are rewritten to an
+//! alternative frontend before scoring, so every command sees the mirror's URL. Each site is
+//! enabled on its own, with a frontend by name (its default instance) or an instance's URL:
+//!
+//! ```yaml
+//! mirrors:
+//!   youtube: piped
+//!   reddit: https://teddit.example.org
+//! ```
+
+use crate::Rewrite;
+use anyhow::Result;
+use indexmap::IndexMap;
+
+struct Site {
+    name: &'static str,
+    /// Frontends by name, with the instance each name stands for
+    frontends: &'static [(&'static str, &'static str)],
+    /// Patterns and replacements, with `{instance}` standing for the chosen instance
+    rules: &'static [(&'static str, &'static str)],
+}
+
+const SITES: &[Site] = &[
+    Site {
+        name: "youtube",
+        frontends: &[("invidious", "https://yewtu.be"), ("piped", "https://piped.video")],
+        rules: &[
+            (r"^https?://(?:www\.|m\.)?youtube\.com/", "{instance}/"),
+            (r"^https?://youtu\.be/([\w-]+)\?", "{instance}/watch?v=${1}&"),
+            (r"^https?://youtu\.be/([\w-]+)", "{instance}/watch?v=$1"),
+        ],
+    },
+    Site {
+        name: "twitter",
+        frontends: &[("nitter", "https://nitter.net")],
+        rules: &[(r"^https?://(?:www\.|mobile\.)?(?:twitter|x)\.com/", "{instance}/")],
+    },
+    Site {
+        name: "reddit",
+        frontends: &[("teddit", "https://teddit.net")],
+        rules: &[(r"^https?://(?:www\.|old\.)?reddit\.com/", "{instance}/")],
+    },
+    Site {
+        name: "medium",
+        frontends: &[("scribe", "https://scribe.rip")],
+        rules: &[(r"^https?://(?:[\w-]+\.)?medium\.com/", "{instance}/")],
+    },
+];
+
+/// The instance a `mirrors` value names, or what is wrong with the entry
+fn instance(site: &str, frontend: &str) -> Result<String, String> {
+    let Some(site) = SITES.iter().find(|known| known.name == site) else {
+        return Err(format!(
+            "Unknown mirror site '{site}', expected one of: {}",
+            SITES.iter().map(|site| site.name).collect::<Vec<_>>().join(", ")
+        ));
+    };
+    if frontend.starts_with("https://") || frontend.starts_with("http://") {
+        return Ok(frontend.trim_end_matches('/').to_string());
+    }
+    site.frontends
+        .iter()
+        .find(|(name, _)| *name == frontend)
+        .map(|(_, instance)| instance.to_string())
+        .ok_or_else(|| {
+            format!(
+                "Unknown {} frontend '{frontend}', expected an instance URL or one of: {}",
+                site.name,
+                site.frontends.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            )
+        })
+}
+
+/// What is wrong with each bad `mirrors` entry, by site
+pub fn problems(mirrors: &IndexMap<String, String>) -> Vec<(&str, String)> {
+    mirrors
+        .iter()
+        .filter_map(|(site, frontend)| instance(site, frontend).err().map(|problem| (site.as_str(), problem)))
+        .collect()
+}
+
+/// `text` with the enabled sites' links rewritten
+pub fn rewrite(mirrors: &IndexMap<String, String>, text: &str) -> Result<String> {
+    let mut text = text.to_string();
+    for (site_name, frontend) in mirrors {
+        let instance = instance(site_name, frontend).map_err(anyhow::Error::msg)?;
+        let site = SITES.iter().find(|site| site.name == site_name).into_iter();
+        for (pattern, replacement) in site.flat_map(|site| site.rules) {
+            let rewrite = Rewrite {
+                pattern: pattern.to_string(),
+                replacement: replacement.replace("{instance}", &instance),
+            };
+            text = rewrite.apply(&text)?;
+        }
+    }
+    Ok(text)
+}
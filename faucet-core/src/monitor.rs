@@ -0,0 +1,170 @@
+//! Which monitor the picker opens on (`menu_monitor`), for multi-head setups where the picker's
+//! own guess is the wrong screen. The monitor is found through xrandr and xdotool on X11 or
+//! hyprctl on Hyprland, and handed to the picker as `$FAUCET_MENU_MONITOR` (its index, as
+//! dmenu's and rofi's `-m` take it) and `$FAUCET_MENU_OUTPUT` (its name, e.g. `DP-1`).
+
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Follow {
+    /// The monitor under the mouse pointer
+    Pointer,
+    /// The monitor showing the focused window
+    Focused,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MenuMonitor {
+    Follow(Follow),
+    /// A fixed monitor index
+    Index(u32),
+}
+
+struct Monitor {
+    index: u32,
+    name: String,
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+impl Monitor {
+    fn contains(&self, (x, y): (i64, i64)) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// The `FAUCET_MENU_MONITOR` and `FAUCET_MENU_OUTPUT` vars, empty when the monitor can't be found
+pub fn vars(placement: MenuMonitor) -> Vec<(&'static str, String)> {
+    let monitors = monitors();
+    let found = match placement {
+        MenuMonitor::Index(index) => monitors.iter().find(|monitor| monitor.index == index),
+        MenuMonitor::Follow(follow) => {
+            let point = match follow {
+                Follow::Pointer => pointer(),
+                Follow::Focused => focused_window(),
+            };
+            point.and_then(|point| monitors.iter().find(|monitor| monitor.contains(point)))
+        }
+    };
+    match (found, placement) {
+        (Some(monitor), _) => vec![
+            ("FAUCET_MENU_MONITOR", monitor.index.to_string()),
+            ("FAUCET_MENU_OUTPUT", monitor.name.clone()),
+        ],
+        // Without xrandr a fixed index is still worth passing on
+        (None, MenuMonitor::Index(index)) => vec![("FAUCET_MENU_MONITOR", index.to_string())],
+        (None, MenuMonitor::Follow(_)) => {
+            debug!("Could not tell which monitor to open the picker on");
+            Vec::new()
+        }
+    }
+}
+
+/// Trimmed stdout of a successful run of `program`
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn hyprland() -> bool {
+    std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some()
+}
+
+fn monitors() -> Vec<Monitor> {
+    if hyprland() {
+        // "Monitor DP-1 (ID 0):" followed by an indented "2560x1440@60.00000 at 0x0"
+        let Some(listing) = output("hyprctl", &["monitors"]) else {
+            return Vec::new();
+        };
+        let mut monitors = Vec::new();
+        let mut header = None;
+        for line in listing.lines() {
+            if let Some(rest) = line.strip_prefix("Monitor ")
+                && let Some((name, id)) = rest.split_once(" (ID ")
+                && let Ok(index) = id.trim_end_matches("):").parse()
+            {
+                header = Some((index, name.to_string()));
+            } else if let Some((index, name)) = header.take() {
+                let geometry = line.trim().split_once(" at ").and_then(|(size, position)| {
+                    let (width, height) = size.split('@').next()?.split_once('x')?;
+                    let (x, y) = position.split_once('x')?;
+                    Some((width.parse().ok()?, height.parse().ok()?, x.parse().ok()?, y.parse().ok()?))
+                });
+                if let Some((width, height, x, y)) = geometry {
+                    monitors.push(Monitor { index, name, x, y, width, height });
+                }
+            }
+        }
+        return monitors;
+    }
+    // " 0: +*DP-1 2560/597x1440/336+0+0  DP-1"
+    let Some(listing) = output("xrandr", &["--listactivemonitors"]) else {
+        return Vec::new();
+    };
+    listing
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let index = fields.next()?.trim_end_matches(':').parse().ok()?;
+            let geometry = fields.nth(1)?;
+            let name = fields.next_back()?.to_string();
+            let (width, rest) = geometry.split_once('x')?;
+            let mut rest = rest.split('+');
+            let height = rest.next()?;
+            let number = |field: &str| field.split('/').next()?.parse().ok();
+            Some(Monitor {
+                index,
+                name,
+                width: number(width)?,
+                height: number(height)?,
+                x: rest.next()?.parse().ok()?,
+                y: rest.next()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn pointer() -> Option<(i64, i64)> {
+    if hyprland() {
+        // "1234, 567"
+        let position = output("hyprctl", &["cursorpos"])?;
+        let (x, y) = position.split_once(',')?;
+        return Some((x.trim().parse().ok()?, y.trim().parse().ok()?));
+    }
+    let location = output("xdotool", &["getmouselocation", "--shell"])?;
+    Some((shell_var(&location, "X")?, shell_var(&location, "Y")?))
+}
+
+/// The centre of the focused window
+fn focused_window() -> Option<(i64, i64)> {
+    if hyprland() {
+        // "at: 10,40" and "size: 1900,1000"
+        let window = output("hyprctl", &["activewindow"])?;
+        let pair = |name: &str| -> Option<(i64, i64)> {
+            let value = window.lines().find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(": "))?;
+            let (a, b) = value.split_once(',')?;
+            Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+        };
+        let ((x, y), (width, height)) = (pair("at")?, pair("size")?);
+        return Some((x + width / 2, y + height / 2));
+    }
+    let geometry = output("xdotool", &["getactivewindow", "getwindowgeometry", "--shell"])?;
+    let field = |name| shell_var(&geometry, name);
+    Some((field("X")? + field("WIDTH")? / 2, field("Y")? + field("HEIGHT")? / 2))
+}
+
+/// `NAME=value` from xdotool's `--shell` output
+fn shell_var(output: &str, name: &str) -> Option<i64> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))?
+        .parse()
+        .ok()
+}
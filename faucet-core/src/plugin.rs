@@ -0,0 +1,181 @@
+//! Scorer plugins: WASI command modules (e.g. Rust built for `wasm32-wasip1`) in `plugins/` next
+//! to the config, `~/.config/faucet/plugins/*.wasm` by default, for detectors too involved for a
+//! `regex` or `lua` scorer that are shared as a single file.
+//!
+//! A plugin gets the data on stdin and the variables commands get (`DATA_SIZE`, `IS_BINARY`,
+//! `DETECTED`, detector variables, ...) in its environment, besides `MIME` and `SOURCE`, but not
+//! `TEXT` or any file path: it sees no files and no network. It prints one `<label> <delta>` line
+//! per command it scores, and exits 0; any other exit scores nothing. A plugin running past `FUEL`
+//! (about as many instructions) or `MEMORY` is stopped. `faucet validate` compiles every plugin,
+//! reporting those that don't.
+
+use crate::Plumb;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Instructions, roughly, a plugin may run for one plumb
+#[cfg(feature = "wasm")]
+const FUEL: u64 = 2_000_000_000;
+/// Bytes of linear memory a plugin may grow to
+#[cfg(feature = "wasm")]
+const MEMORY: usize = 256 << 20;
+/// Bytes of stdout kept; a plugin printing more fails
+#[cfg(feature = "wasm")]
+const OUTPUT: usize = 64 << 10;
+
+pub struct Plugin {
+    /// The file name without `.wasm`, as scores are attributed to it
+    pub name: String,
+    path: PathBuf,
+    #[cfg(feature = "wasm")]
+    module: std::sync::OnceLock<Result<wasmtime::Module, String>>,
+}
+
+/// The plugins in `config_dir/plugins`, by name
+pub fn discover(config_dir: &Path) -> Vec<Plugin> {
+    let Ok(entries) = std::fs::read_dir(config_dir.join("plugins")) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<Plugin> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "wasm"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(Plugin {
+                name,
+                path,
+                #[cfg(feature = "wasm")]
+                module: Default::default(),
+            })
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+impl Plugin {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Why the plugin can't run, compiling it to find out
+    pub fn problem(&self) -> Option<String> {
+        #[cfg(feature = "wasm")]
+        return self.module().err();
+        #[cfg(not(feature = "wasm"))]
+        Some("This faucet was built without the 'wasm' feature".to_string())
+    }
+
+    /// The deltas the plugin prints for the data; a plugin that fails is logged and scores nothing
+    pub fn deltas(&self, plumb: &Plumb) -> Vec<(String, i32)> {
+        #[cfg(feature = "wasm")]
+        let output = self.run(plumb);
+        #[cfg(not(feature = "wasm"))]
+        let output: Result<Vec<u8>, String> = {
+            let _ = plumb;
+            Err("this faucet was built without the 'wasm' feature".to_string())
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Plugin '{}' failed: {e}", self.name);
+                return Vec::new();
+            }
+        };
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let parsed = line
+                    .rsplit_once(char::is_whitespace)
+                    .and_then(|(label, delta)| Some((label.trim().to_string(), delta.parse().ok()?)));
+                if parsed.is_none() {
+                    debug!("Plugin '{}' printed '{line}', expected '<label> <delta>'", self.name);
+                }
+                parsed
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "wasm")]
+    fn module(&self) -> Result<&wasmtime::Module, String> {
+        self.module
+            .get_or_init(|| {
+                wasmtime::Module::from_file(engine()?, &self.path).map_err(|e| format!("Failed to compile {}: {e:#}", self.path.display()))
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn run(&self, plumb: &Plumb) -> Result<Vec<u8>, String> {
+        use wasmtime::{Linker, Store, StoreLimits, StoreLimitsBuilder};
+        use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+        use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+        use wasmtime_wasi::{I32Exit, WasiCtxBuilder};
+
+        struct State {
+            wasi: WasiP1Ctx,
+            limits: StoreLimits,
+        }
+
+        let module = self.module()?;
+        let bytes = match plumb.data {
+            crate::Data::Text(text) => text.as_bytes().to_vec(),
+            crate::Data::Binary(bytes) => bytes.clone(),
+        };
+        let mut vars: Vec<(String, String)> = plumb
+            .vars()
+            .into_iter()
+            .filter(|(name, _)| !matches!(name.as_str(), "TEXT" | "DATA_FILE" | "DERIVED_FILE"))
+            .collect();
+        vars.push(("MIME".to_string(), plumb.mime().to_string()));
+        vars.push(("SOURCE".to_string(), plumb.source.to_string()));
+        let stdout = MemoryOutputPipe::new(OUTPUT);
+        let wasi = WasiCtxBuilder::new()
+            .stdin(MemoryInputPipe::new(bytes))
+            .stdout(stdout.clone())
+            .inherit_stderr()
+            .envs(&vars)
+            .arg(&self.name)
+            .build_p1();
+        let mut store = Store::new(module.engine(), State { wasi, limits: StoreLimitsBuilder::new().memory_size(MEMORY).build() });
+        store.limiter(|state| &mut state.limits);
+        let outcome = (|| {
+            store.set_fuel(FUEL)?;
+            let mut linker = Linker::new(module.engine());
+            preview1::add_to_linker_sync(&mut linker, |state: &mut State| &mut state.wasi)?;
+            let instance = linker.instantiate(&mut store, module)?;
+            instance.get_typed_func::<(), ()>(&mut store, "_start")?.call(&mut store, ())
+        })();
+        match outcome {
+            Ok(()) => {}
+            Err(e) => match e.downcast_ref::<I32Exit>() {
+                Some(I32Exit(0)) => {}
+                Some(I32Exit(code)) => return Err(format!("exited with {code}")),
+                None => return Err(format!("{e:#}")),
+            },
+        }
+        Ok(stdout.contents().to_vec())
+    }
+}
+
+/// Compiled modules are cached on disk (in wasmtime's cache dir), so only a new or changed
+/// plugin is compiled on a keypress
+#[cfg(feature = "wasm")]
+fn engine() -> Result<&'static wasmtime::Engine, String> {
+    static ENGINE: std::sync::OnceLock<Result<wasmtime::Engine, String>> = std::sync::OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut config = wasmtime::Config::new();
+            config.consume_fuel(true);
+            if let Err(e) = config.cache_config_load_default() {
+                debug!("Compiling plugins without a cache: {e}");
+            }
+            wasmtime::Engine::new(&config).map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
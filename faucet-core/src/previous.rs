@@ -0,0 +1,44 @@
+//! The previous plumb, for two-step flows ("copied a title, then a URL: offer to bookmark it
+//! with the title"). Each launch is recorded in the runtime dir's `previous` as a
+//! `<label> <unix secs>` line followed by the text (empty for binary data, its secrets masked as
+//! in the logs), and exposed to the next plumb's scorers and commands as `FAUCET_PREV_LABEL`,
+//! `FAUCET_PREV_TEXT` and `FAUCET_PREV_AGE` (seconds since, empty when there was no previous
+//! plumb).
+
+use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Texts are cut to this many bytes: every later plumb's commands see it, and the titles and
+/// links two-step flows pass on are short
+const MAX_TEXT: usize = 4 * 1024;
+
+pub fn record(label: &str, text: &str) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let text = crate::redact::redact(text);
+    let text = &text[..text.floor_char_boundary(MAX_TEXT)];
+    // Written aside and renamed, so a plumb reading it never sees half a record
+    let mut file = crate::runtime::temp_file("faucet_previous_")?;
+    std::io::Write::write_all(&mut file, format!("{label} {now}\n{text}").as_bytes())?;
+    file.persist(crate::runtime::dir()?.join("previous"))?;
+    Ok(())
+}
+
+/// The `FAUCET_PREV_*` vars
+pub fn vars() -> Vec<(String, String)> {
+    let (mut label, mut text, mut age) = Default::default();
+    if let Ok(content) = crate::runtime::dir().and_then(|dir| Ok(std::fs::read_to_string(dir.join("previous"))?))
+        && let Some((header, recorded_text)) = content.split_once('\n')
+        && let Some((recorded_label, secs)) = header.rsplit_once(' ')
+        && let Ok(secs) = secs.parse::<u64>()
+    {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        label = recorded_label.to_string();
+        text = recorded_text.to_string();
+        age = now.saturating_sub(secs).to_string();
+    }
+    vec![
+        ("FAUCET_PREV_LABEL".to_string(), label),
+        ("FAUCET_PREV_TEXT".to_string(), text),
+        ("FAUCET_PREV_AGE".to_string(), age),
+    ]
+}
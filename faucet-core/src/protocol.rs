@@ -0,0 +1,369 @@
+//! The wire protocol spoken by `faucet serve`, for clients (bars, editors, launchers) that want
+//! faucet's scoring without spawning it per clip.
+//!
+//! ```sh
+//! faucet serve --stdio
+//! faucet serve --socket [path]
+//! ```
+//!
+//! `--socket` listens on a Unix socket (by default `serve.sock` in the runtime dir) for any number
+//! of sessions, which share the loaded config and the probe cache; their requests are handled side
+//! by side, so a plumb waiting on its picker or command holds up no one. `faucet client` is a thin client for it, see `client`.
+//!
+//! Every message is a frame: a 4-byte big-endian length followed by that many bytes of UTF-8
+//! JSON, an object whose `type` names the message. A session opens with a handshake:
+//!
+//! ```text
+//! -> {"type":"hello","version":1,"capabilities":["score","explain"]}
+//! <- {"type":"welcome","version":1,"capabilities":["score","explain"]}
+//! -> {"type":"score","id":1,"data":"aHR0cHM6Ly9leGFtcGxlLmNvbQ=="}
+//! <- {"type":"ranking","id":1,"decision":"auto_select","mime":"text/plain","detected":[],"candidates":[...]}
+//! ```
+//!
+//! `data` is base64, so binary clips survive. With the `plumb` capability a client can also have
+//! the server plumb the data as `faucet` would, running the command that auto-selects or showing
+//! the picker; without `data` the server reads the clipboard (or, for `"source":"selection"`, the
+//! primary selection) itself:
+//!
+//! ```text
+//! -> {"type":"plumb","id":2}
+//! <- {"type":"plumbed","id":2}
+//! ```
+//!
+//! Compatibility guarantees:
+//! - `VERSION` only changes for incompatible changes; a client on another version gets an
+//!   `error` and the session closes.
+//! - Within a version, messages only ever gain fields and new capabilities. Both sides must
+//!   ignore fields and capabilities they don't know.
+//! - The server only sends what the client's capabilities cover (`explain` adds `matched`).
+//!
+//! `SIGHUP` reloads the config before the next request; see `lifecycle` for the other signals.
+
+use crate::cache::ProbeCache;
+use crate::cli::Flags;
+use crate::clipboard::{self, Selection};
+use crate::lifecycle::Lifecycle;
+use crate::ranking::{ScoredCommand, candidates};
+use crate::{CancelToken, CancellableRunner, Config, Data, Decision, Plumb, SOURCES, decide, rank_commands, score_commands};
+use anyhow::{Result, anyhow, bail};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, info};
+
+pub const VERSION: u32 = 1;
+
+/// Capabilities this server offers
+pub const CAPABILITIES: [&str; 3] = [
+    // `score` requests
+    "score",
+    // Per-scorer attribution in rankings
+    "explain",
+    // `plumb` requests
+    "plumb",
+];
+
+/// Frames larger than this are rejected rather than allocated
+const MAX_FRAME: u32 = 64 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        version: u32,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
+    Score {
+        id: u64,
+        /// Base64 of the data to plumb
+        data: String,
+    },
+    Plumb {
+        id: u64,
+        /// Base64 of the data to plumb; without it the server reads `source`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<String>,
+        /// One of `SOURCES`: where the data came from, or which selection to read. Defaults to
+        /// `stdin` with data, else `clipboard`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        /// Where the `file` data was read from, for `pattern` scorers
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        version: u32,
+        capabilities: Vec<String>,
+    },
+    Ranking {
+        id: u64,
+        /// `auto_select`, `menu` or `none`
+        decision: &'static str,
+        mime: String,
+        detected: Vec<&'static str>,
+        candidates: Vec<ScoredCommand>,
+    },
+    /// A `plumb` request is done with: the command ran, or the picker was closed
+    Plumbed {
+        id: u64,
+    },
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+        message: String,
+    },
+}
+
+pub fn write_frame(writer: &mut impl Write, message: &impl Serialize) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    let len = u32::try_from(body.len()).map_err(|_| anyhow!("Frame too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The next frame's body, or `None` when the peer closed the stream between frames
+pub fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME {
+        bail!("Frame of {len} bytes exceeds the {MAX_FRAME} byte limit");
+    }
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// What every session uses: the config once reloaded, and the probe cache
+struct State {
+    reloaded: Option<Arc<Config>>,
+    cache: Arc<ProbeCache>,
+}
+
+pub fn run(config: &Config, config_path: &Path, flags: &Flags, args: &[String]) -> Result<()> {
+    let lifecycle = || Lifecycle::install("serve", config_path.to_path_buf(), flags.clone());
+    let state = Mutex::new(State { reloaded: None, cache: Arc::new(ProbeCache::new(config.options.probe_cache_size)) });
+    let socket = match args {
+        [flag] if flag == "--stdio" => {
+            let lifecycle = lifecycle()?;
+            return serve(config, flags, &state, &lifecycle, &mut std::io::stdin().lock(), &mut std::io::stdout().lock());
+        }
+        [flag] if flag == "--socket" => socket_path()?,
+        [flag, path] if flag == "--socket" => PathBuf::from(path),
+        _ => bail!("serve requires --stdio or --socket [path]"),
+    };
+    if UnixStream::connect(&socket).is_ok() {
+        bail!("Another faucet is already serving on {}", socket.display());
+    }
+    // Left behind by a server that was killed
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    let lifecycle = lifecycle()?;
+    info!("Serving on {}", socket.display());
+    let (state, lifecycle) = (&state, &lifecycle);
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("Failed to accept a session: {e}");
+                    continue;
+                }
+            };
+            scope.spawn(move || {
+                let session = stream
+                    .try_clone()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|mut reader| serve(config, flags, state, lifecycle, &mut reader, &mut stream));
+                if let Err(e) = session {
+                    error!("Session failed: {e}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Where `faucet serve --socket` listens and `faucet client` connects without a path
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(crate::runtime::dir()?.join("serve.sock"))
+}
+
+/// Runs one session: the handshake, then a reply to each request until the client hangs up
+fn serve(
+    config: &Config,
+    flags: &Flags,
+    state: &Mutex<State>,
+    lifecycle: &Lifecycle,
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let Some(hello) = read_frame(reader)? else {
+        return Ok(());
+    };
+    let capabilities = match serde_json::from_slice(&hello) {
+        Ok(ClientMessage::Hello { version, capabilities }) if version == VERSION => capabilities,
+        Ok(ClientMessage::Hello { version, .. }) => {
+            let message = format!("Unsupported protocol version {version}, this faucet speaks {VERSION}");
+            write_frame(writer, &ServerMessage::Error { id: None, message: message.clone() })?;
+            bail!(message);
+        }
+        _ => {
+            let message = "Expected a hello".to_string();
+            write_frame(writer, &ServerMessage::Error { id: None, message: message.clone() })?;
+            bail!(message);
+        }
+    };
+    write_frame(
+        writer,
+        &ServerMessage::Welcome {
+            version: VERSION,
+            capabilities: CAPABILITIES.iter().map(|capability| capability.to_string()).collect(),
+        },
+    )?;
+    let explain = capabilities.iter().any(|capability| capability == "explain");
+    while let Some(frame) = read_frame(reader)? {
+        let _busy = lifecycle.busy();
+        // Only locked to take up a reload, as a plumb may be at its picker or command for long
+        let (reloaded, cache) = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            if lifecycle.take_reload() {
+                match lifecycle.load_config() {
+                    Ok(config) => {
+                        info!("Reloaded the config");
+                        state.cache = Arc::new(ProbeCache::new(config.options.probe_cache_size));
+                        state.reloaded = Some(Arc::new(config));
+                    }
+                    Err(e) => error!("Keeping the previous config: {e}"),
+                }
+            }
+            (state.reloaded.clone(), state.cache.clone())
+        };
+        let (config, cache) = (reloaded.as_deref().unwrap_or(config), &*cache);
+        lifecycle.handled();
+        // A message this version doesn't know is answered, not fatal
+        let reply = match serde_json::from_slice(&frame) {
+            Err(e) => ServerMessage::Error { id: None, message: format!("Unrecognised message: {e}") },
+            Ok(ClientMessage::Score { id, data }) => score(config, cache, &lifecycle.cancellable(), id, &data, explain)
+                .unwrap_or_else(|e| ServerMessage::Error { id: Some(id), message: e.to_string() }),
+            Ok(ClientMessage::Plumb { id, data, source, path }) => plumb(config, flags, cache, &lifecycle.cancellable(), data, source, path)
+                .map(|()| ServerMessage::Plumbed { id })
+                .unwrap_or_else(|e| ServerMessage::Error { id: Some(id), message: e.to_string() }),
+            Ok(ClientMessage::Hello { .. }) => ServerMessage::Error {
+                id: None,
+                message: "Already greeted".to_string(),
+            },
+        };
+        write_frame(writer, &reply)?;
+    }
+    Ok(())
+}
+
+/// Plumbs the data sent, or the selection named by `source`, as `faucet` would with the server's `flags`
+fn plumb(
+    config: &Config,
+    flags: &Flags,
+    cache: &ProbeCache,
+    cancel: &CancelToken,
+    data: Option<String>,
+    source: Option<String>,
+    path: Option<String>,
+) -> Result<()> {
+    // Before reading anything
+    if crate::disabled_by_env() {
+        debug!("FAUCET_DISABLE is set, not plumbing anything");
+        return Ok(());
+    }
+    if !config.options.enabled {
+        debug!("Faucet is disabled in config, not plumbing anything");
+        return Ok(());
+    }
+    let runner = CancellableRunner::new(cancel.clone());
+    let source = source.unwrap_or_else(|| if data.is_some() { "stdin" } else { "clipboard" }.to_string());
+    let Some(source) = SOURCES.iter().find(|known| **known == source) else {
+        bail!("Unknown source '{source}', expected one of: {}", SOURCES.join(", "));
+    };
+    let data = match (data, *source) {
+        (Some(data), _) => Data::from_bytes(base64::engine::general_purpose::STANDARD.decode(data)?),
+        (None, "clipboard") => Data::from_bytes(clipboard::read(&config.options.display_server, Selection::Clipboard, &runner)?.0),
+        (None, "selection") => match clipboard::read(&config.options.display_server, Selection::Primary, &runner)? {
+            (bytes, Some(_)) => Data::Binary(bytes),
+            (bytes, None) => Data::from_bytes(bytes),
+        },
+        (None, source) => bail!("Reading from {source} needs the data sent along"),
+    };
+    let path = path.map(std::path::PathBuf::from);
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    // A report would go to the server's output, which may be the protocol's
+    let flags = Flags { no_exec: false, explain: false, json: false, headless: false, ..flags.clone() };
+    crate::plumb_data(config, &flags, cache, cancel, data, source, path.as_deref(), temp_file_handle)?;
+    Ok(())
+}
+
+fn score(
+    config: &Config,
+    cache: &ProbeCache,
+    cancel: &CancelToken,
+    id: u64,
+    data: &str,
+    explain: bool,
+) -> Result<ServerMessage> {
+    let runner = CancellableRunner::new(cancel.clone());
+    let data = Data::from_bytes(base64::engine::general_purpose::STANDARD.decode(data)?);
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    let temp_file_path = temp_file_handle
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+    data.write_to_temp_file(temp_file_path)?;
+
+    let mut plumb = Plumb::with_runner(
+        &config.options,
+        &data,
+        temp_file_path,
+        "",
+        data.get_text_for_matching(&runner, temp_file_path)?,
+        &runner,
+    );
+    if let Some(derive_command) = &config.options.derive_command {
+        plumb.derive(derive_command)?;
+    }
+
+    let scored_commands = score_commands(config, &plumb, cache, false)?;
+    if cancel.is_cancelled() {
+        bail!("The request was cancelled");
+    }
+    let ranked = rank_commands(&scored_commands);
+    // Candidates are listed even on auto-select, so a frontend can offer the runners-up
+    // Clients don't say where the data came from, so only source-less overrides can apply
+    let mut options = config.options.clone();
+    options.apply_threshold_overrides(plumb.mime(), "");
+    let decision = match decide(&options, ranked.clone()) {
+        Decision::NoMatch => "none",
+        Decision::AutoSelect(_) => "auto_select",
+        Decision::Menu(_) => "menu",
+    };
+    let candidates = candidates(&plumb, &ranked, explain);
+    Ok(ServerMessage::Ranking {
+        id,
+        decision,
+        mime: plumb.mime().to_string(),
+        detected: plumb.detections.iter().map(|detection| detection.name).collect(),
+        candidates,
+    })
+}
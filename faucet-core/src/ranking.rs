@@ -0,0 +1,62 @@
+//! Rankings as JSON, for `faucet serve` clients, `--headless` callers and `--json` scripts.
+
+use crate::{Plumb, RankedCommand};
+use serde::{Deserialize, Serialize};
+
+/// A command as ranked for the data
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoredCommand {
+    pub label: String,
+    pub display: String,
+    pub action: String,
+    pub score: i32,
+    /// The scorers behind the score, when asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched: Option<Vec<Match>>,
+}
+
+/// `--json` output: the ranking along with what was plumbed
+#[derive(Serialize)]
+pub struct Ranking<'a> {
+    pub source: &'a str,
+    pub mime: &'a str,
+    /// Names of the detectors that recognised the data
+    pub detected: Vec<&'a str>,
+    pub candidates: Vec<ScoredCommand>,
+}
+
+/// A scorer that fired for a command, and by how much it changed the score
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+    pub scorer: String,
+    pub delta: i32,
+}
+
+/// What `--json` prints for `ranked`
+pub fn ranking<'a>(plumb: &'a Plumb, ranked: &[RankedCommand]) -> Ranking<'a> {
+    Ranking {
+        source: plumb.source,
+        mime: plumb.mime(),
+        detected: plumb.detections.iter().map(|detection| detection.name).collect(),
+        candidates: candidates(plumb, ranked, true),
+    }
+}
+
+/// Ranked commands, best first; `explain` adds what each was matched by
+pub fn candidates(plumb: &Plumb, ranked: &[RankedCommand], explain: bool) -> Vec<ScoredCommand> {
+    ranked
+        .iter()
+        .map(|(_, (label, (command, score, reasons)))| ScoredCommand {
+            label: label.to_string(),
+            display: plumb.display(command),
+            action: command.action.to_string(),
+            score: *score,
+            matched: explain.then(|| {
+                reasons
+                    .iter()
+                    .map(|(scorer, delta)| Match { scorer: scorer.clone(), delta: *delta })
+                    .collect()
+            }),
+        })
+        .collect()
+}
@@ -0,0 +1,266 @@
+//! Where scoring and launching start processes: `SystemRunner` runs them, `MockRunner` answers
+//! from canned rules and records every call, so the pipeline can be tested without the programs
+//! it would otherwise run (`file`, command scorers, the derive command, hooks, ...), and
+//! `PureRunner` runs none at all. `CancellableRunner` runs them so that cancelling the plumb
+//! kills them, see `cancel`.
+
+use crate::cancel::CancelToken;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often a command run with a timeout is checked on
+const POLL: Duration = Duration::from_millis(10);
+
+pub trait Runner: Send + Sync {
+    /// Runs `command` to completion, capturing its stdout and stderr
+    fn output(&self, command: &mut Command) -> std::io::Result<Output>;
+    /// Runs `command` to completion with `input` on its stdin, capturing its stdout and stderr
+    fn output_with_input(&self, command: &mut Command, input: &[u8]) -> std::io::Result<Output>;
+    /// Runs `command` to completion with the stdio it was given
+    fn status(&self, command: &mut Command) -> std::io::Result<ExitStatus>;
+    /// As `status`, but kills the command (and whatever it started) after `timeout`, giving `None`
+    fn status_within(&self, command: &mut Command, timeout: Duration) -> std::io::Result<Option<ExitStatus>>;
+    /// Starts `command`, leaving the caller to wait on it
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child>;
+    /// Whether the plumb was cancelled, so what its commands did says nothing about the data
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Runs commands for real
+pub struct SystemRunner;
+
+impl Runner for SystemRunner {
+    fn output(&self, command: &mut Command) -> std::io::Result<Output> {
+        command.output()
+    }
+
+    fn output_with_input(&self, command: &mut Command, input: &[u8]) -> std::io::Result<Output> {
+        command.stdin(input_file(input)?).output()
+    }
+
+    fn status(&self, command: &mut Command) -> std::io::Result<ExitStatus> {
+        command.status()
+    }
+
+    fn status_within(&self, command: &mut Command, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+        wait_within(command.process_group(0).spawn()?, timeout)
+    }
+
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        command.spawn()
+    }
+}
+
+/// Runs commands for real, each in a process group of its own that cancelling `token` kills.
+/// Only started commands outlive it: they are what the plumb was for.
+pub struct CancellableRunner(CancelToken);
+
+impl CancellableRunner {
+    pub fn new(token: CancelToken) -> Self {
+        Self(token)
+    }
+}
+
+impl Runner for CancellableRunner {
+    /// Runs with no stdin, as `Command::output` does by default
+    fn output(&self, command: &mut Command) -> std::io::Result<Output> {
+        self.0
+            .run(command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()), Child::wait_with_output)
+    }
+
+    fn output_with_input(&self, command: &mut Command, input: &[u8]) -> std::io::Result<Output> {
+        self.0.run(
+            command.stdin(input_file(input)?).stdout(Stdio::piped()).stderr(Stdio::piped()),
+            Child::wait_with_output,
+        )
+    }
+
+    fn status(&self, command: &mut Command) -> std::io::Result<ExitStatus> {
+        self.0.run(command, |mut child| child.wait())
+    }
+
+    fn status_within(&self, command: &mut Command, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+        self.0.run(command, |child| wait_within(child, timeout))
+    }
+
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        command.spawn()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+}
+
+/// Runs nothing: every program is as good as missing, so scoring depends on the config and data
+/// alone. Programs asked for output exit 127 with none, and nothing can be started.
+pub struct PureRunner;
+
+impl Runner for PureRunner {
+    fn output(&self, _command: &mut Command) -> std::io::Result<Output> {
+        Ok(Output { status: ExitStatus::from_raw(127 << 8), stdout: Vec::new(), stderr: Vec::new() })
+    }
+
+    fn output_with_input(&self, command: &mut Command, _input: &[u8]) -> std::io::Result<Output> {
+        self.output(command)
+    }
+
+    fn status(&self, _command: &mut Command) -> std::io::Result<ExitStatus> {
+        Ok(ExitStatus::from_raw(127 << 8))
+    }
+
+    fn status_within(&self, command: &mut Command, _timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+        self.status(command).map(Some)
+    }
+
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        Err(std::io::Error::other(format!("Pure scoring starts no processes ({:?})", command.get_program())))
+    }
+}
+
+/// A command a `MockRunner` was asked to run
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Variables set on the command itself, not those inherited from faucet's environment
+    pub envs: Vec<(String, String)>,
+    /// What it was given on stdin, if anything
+    pub input: Option<Vec<u8>>,
+}
+
+impl Call {
+    /// The program and its arguments, space separated
+    pub fn line(&self) -> String {
+        std::iter::once(&self.program).chain(&self.args).cloned().collect::<Vec<_>>().join(" ")
+    }
+
+    pub fn env(&self, name: &str) -> Option<&str> {
+        self.envs.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Answers each command from the first rule whose needle occurs in its command line (for a
+/// `sh -c` command, the script); anything else exits 127 with no output, as if not installed
+#[derive(Default)]
+pub struct MockRunner {
+    rules: Vec<(String, i32, Vec<u8>)>,
+    delay: Duration,
+    calls: Mutex<Vec<Call>>,
+}
+
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Commands containing `needle` exit with `code`, printing `stdout`
+    pub fn on(mut self, needle: &str, code: i32, stdout: &str) -> Self {
+        self.rules.push((needle.to_string(), code, stdout.as_bytes().to_vec()));
+        self
+    }
+
+    /// Every command takes `delay` to answer, as a slow program would
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Every command run so far, oldest first
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn answer(&self, command: &Command, input: Option<&[u8]>) -> (i32, Vec<u8>) {
+        let line = self.record(command, input);
+        std::thread::sleep(self.delay);
+        self.rules
+            .iter()
+            .find(|(needle, _, _)| line.contains(needle.as_str()))
+            .map_or((127, Vec::new()), |(_, code, stdout)| (*code, stdout.clone()))
+    }
+
+    /// Records the call, giving its command line
+    fn record(&self, command: &Command, input: Option<&[u8]>) -> String {
+        let call = Call {
+            program: command.get_program().to_string_lossy().into_owned(),
+            args: command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+            envs: command
+                .get_envs()
+                .filter_map(|(key, value)| Some((key.to_string_lossy().into_owned(), value?.to_string_lossy().into_owned())))
+                .collect(),
+            input: input.map(<[u8]>::to_vec),
+        };
+        let line = call.line();
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).push(call);
+        line
+    }
+}
+
+impl Runner for MockRunner {
+    fn output(&self, command: &mut Command) -> std::io::Result<Output> {
+        let (code, stdout) = self.answer(command, None);
+        Ok(Output { status: ExitStatus::from_raw(code << 8), stdout, stderr: Vec::new() })
+    }
+
+    fn output_with_input(&self, command: &mut Command, input: &[u8]) -> std::io::Result<Output> {
+        let (code, stdout) = self.answer(command, Some(input));
+        Ok(Output { status: ExitStatus::from_raw(code << 8), stdout, stderr: Vec::new() })
+    }
+
+    fn status(&self, command: &mut Command) -> std::io::Result<ExitStatus> {
+        Ok(ExitStatus::from_raw(self.answer(command, None).0 << 8))
+    }
+
+    /// Commands slower than `timeout` are cut off when it passes
+    fn status_within(&self, command: &mut Command, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+        if self.delay <= timeout {
+            return self.status(command).map(Some);
+        }
+        self.record(command, None);
+        std::thread::sleep(timeout);
+        Ok(None)
+    }
+
+    /// A `Child` has to be a real process, so this starts one that exits with the rule's code
+    fn spawn(&self, command: &mut Command) -> std::io::Result<Child> {
+        let (code, _) = self.answer(command, None);
+        Command::new("sh")
+            .args(["-c", &format!("exit {code}")])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+}
+
+/// Waits up to `timeout` for `child`, the leader of its own process group, then kills the group
+fn wait_within(mut child: Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = Command::new("kill").args(["-KILL", "--", &format!("-{}", child.id())]).status();
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(POLL);
+    }
+}
+
+/// `input` in an unlinked file to give a command as its stdin, which unlike a pipe can't fill up
+/// while nobody reads it
+fn input_file(input: &[u8]) -> std::io::Result<std::fs::File> {
+    use std::io::{Seek, Write};
+    let mut file = tempfile::tempfile()?;
+    file.write_all(input)?;
+    file.rewind()?;
+    Ok(file)
+}
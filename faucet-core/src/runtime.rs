@@ -0,0 +1,124 @@
+//! Where faucet keeps what only matters while it runs (data files, the spool, sockets, locks):
+//! `$XDG_RUNTIME_DIR/faucet`, else a private `faucet-<uid>` in the temp dir.
+
+use anyhow::{Result, bail};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// The files this process has made in the runtime dir and spool, for `remove_own`
+static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The runtime dir, created (mode 0700) if missing. A fallback dir someone else owns, or that
+/// others can read, is refused since the temp dir is shared.
+pub fn dir() -> Result<PathBuf> {
+    if let Some(runtime_dir) = dirs::runtime_dir() {
+        let dir = runtime_dir.join("faucet");
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+        return Ok(dir);
+    }
+    let uid = std::fs::metadata("/proc/self")?.uid();
+    let dir = std::env::temp_dir().join(format!("faucet-{uid}"));
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        bail!("'{}' is not a private directory owned by this user", dir.display());
+    }
+    Ok(dir)
+}
+
+/// A file in the runtime dir, removed when dropped
+pub fn temp_file(prefix: &str) -> Result<tempfile::NamedTempFile> {
+    let file = tempfile::Builder::new().prefix(prefix).tempfile_in(dir()?)?;
+    created(file.path());
+    Ok(file)
+}
+
+/// Notes that this process made `path`, forgetting the files already gone
+pub fn created(path: &Path) {
+    let mut created = CREATED.lock().unwrap_or_else(|e| e.into_inner());
+    created.retain(|path| path.exists());
+    created.push(path.to_path_buf());
+}
+
+/// Whether another process has `path` open, or was given it as an argument or in its environment
+/// (a viewer that read the file and closed it is still showing it, a picker may read `DATA_FILE`
+/// later). Without `/proc` the answer is always yes.
+pub fn in_use(path: &Path) -> bool {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return true;
+    };
+    let own_pid = std::process::id().to_string();
+    let needle = path.as_os_str().as_encoded_bytes();
+    processes
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|process| {
+            process
+                .file_name()
+                .and_then(|pid| pid.to_str())
+                .is_some_and(|pid| pid != own_pid && pid.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .any(|process| {
+            let named = ["cmdline", "environ"].into_iter().any(|list| {
+                std::fs::read(process.join(list))
+                    .is_ok_and(|list| list.windows(needle.len()).any(|window| window == needle))
+            });
+            named
+                || std::fs::read_dir(process.join("fd")).is_ok_and(|fds| {
+                    fds.filter_map(|fd| fd.ok())
+                        .any(|fd| std::fs::read_link(fd.path()).is_ok_and(|target| target == path))
+                })
+        })
+}
+
+/// `faucet clean`: removes files left behind by runs that crashed or were killed, skipping any
+/// that a process is still using or that are younger than `max_age` (a plumb may still be at them)
+pub fn clean(args: &[String], max_age: Duration) -> Result<()> {
+    if let Some(arg) = args.first() {
+        bail!("Unknown clean argument '{arg}'");
+    }
+    let dir = dir()?;
+    let spool = dir.join("spool");
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for dir in [dir.as_path(), spool.as_path()] {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let recent = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_or(true, |modified| now.duration_since(modified).unwrap_or_default() < max_age);
+            if !name.starts_with("faucet_") || !path.is_file() || recent || in_use(&path) {
+                continue;
+            }
+            debug!("Removing stale {}", path.display());
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    println!("Removed {removed} stale file{}", if removed == 1 { "" } else { "s" });
+    Ok(())
+}
+
+/// Removes the files this process made that are left and no process is using, returning how many.
+/// Other plumbs' files are theirs to remove.
+pub fn remove_own() -> Result<usize> {
+    let created = std::mem::take(&mut *CREATED.lock().unwrap_or_else(|e| e.into_inner()));
+    let mut removed = 0;
+    for path in created {
+        if !path.is_file() || in_use(&path) {
+            continue;
+        }
+        debug!("Removing leftover {}", path.display());
+        std::fs::remove_file(&path)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
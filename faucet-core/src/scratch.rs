@@ -0,0 +1,100 @@
+//! The `scratch` builtin: keeps the plumbed data in a dated file (`~/scratch/2024-05-01-1.py`)
+//! that outlives faucet, so a snippet can be opened, edited and found again later.
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+pub const DEFAULT_DIR: &str = "~/scratch";
+
+/// Copies `data_file` to the next free `<date>-<n>.<extension>` in `dir` and returns its path
+pub fn save(dir: &str, data_file: &str, extension: &str) -> Result<PathBuf> {
+    let dir = crate::expand_home(dir)?;
+    std::fs::create_dir_all(&dir)?;
+    let date = today()?;
+    let path = (1..)
+        .map(|n| dir.join(format!("{date}-{n}.{extension}")))
+        .find(|path| !path.exists())
+        .expect("unbounded range");
+    std::fs::copy(data_file, &path)?;
+    Ok(path)
+}
+
+/// Local date as `YYYY-MM-DD`
+fn today() -> Result<String> {
+    let output = std::process::Command::new("date").arg("+%F").output()?;
+    if !output.status.success() {
+        anyhow::bail!("'date' failed with {}", output.status);
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Guesses a file extension for text from its first lines, falling back to `txt`
+pub fn text_extension(text: &str) -> &'static str {
+    let trimmed = text.trim_start();
+    let first_line = trimmed.lines().next().unwrap_or_default();
+    if let Some(interpreter) = first_line.strip_prefix("#!") {
+        return match interpreter.rsplit(['/', ' ']).find(|word| !word.starts_with('-')) {
+            Some(word) if word.starts_with("python") => "py",
+            Some("node") => "js",
+            Some("ruby") => "rb",
+            Some("perl") => "pl",
+            Some("lua") => "lua",
+            _ => "sh",
+        };
+    }
+    let starts_with = |prefixes: &[&str]| prefixes.iter().any(|prefix| trimmed.starts_with(prefix));
+    let has_line = |prefixes: &[&str]| {
+        trimmed
+            .lines()
+            .take(50)
+            .any(|line| prefixes.iter().any(|prefix| line.trim_start().starts_with(prefix)))
+    };
+    if (trimmed.starts_with('{') && trimmed.trim_end().ends_with('}'))
+        || (trimmed.starts_with('[') && trimmed.trim_end().ends_with(']'))
+    {
+        "json"
+    } else if starts_with(&["<!DOCTYPE html", "<!doctype html", "<html"]) {
+        "html"
+    } else if starts_with(&["<?xml"]) {
+        "xml"
+    } else if starts_with(&["diff --git", "--- a/"]) {
+        "diff"
+    } else if has_line(&["fn ", "pub fn ", "use std::", "impl ", "#[derive"]) {
+        "rs"
+    } else if has_line(&["package main", "func "]) {
+        "go"
+    } else if has_line(&["#include", "int main("]) {
+        "c"
+    } else if has_line(&["def ", "from ", "import "]) && trimmed.contains(':') {
+        "py"
+    } else if has_line(&["function ", "const ", "let ", "export ", "=> "]) {
+        "js"
+    } else if has_line(&["SELECT ", "select ", "INSERT ", "CREATE TABLE"]) {
+        "sql"
+    } else if starts_with(&["---\n"]) {
+        "yaml"
+    } else if starts_with(&["["]) && has_line(&["name =", "version ="]) {
+        "toml"
+    } else if has_line(&["# ", "## ", "```", "- [ ] "]) {
+        "md"
+    } else {
+        "txt"
+    }
+}
+
+/// Extension for binary data from its detected MIME type (`image/png` -> `png`)
+pub fn mime_extension(mime: &str) -> &str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        "application/gzip" => "gz",
+        "application/x-bzip2" => "bz2",
+        "application/x-xz" => "xz",
+        "application/octet-stream" => "bin",
+        _ => mime
+            .rsplit_once('/')
+            .map(|(_, subtype)| subtype.trim_start_matches("x-"))
+            .filter(|subtype| !subtype.is_empty() && subtype.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("bin"),
+    }
+}
@@ -0,0 +1,88 @@
+//! `faucet sync-selections`: copies PRIMARY into CLIPBOARD (or back with `--to primary`), since the
+//! app a plumb hands off to often only reads the other selection.
+//!
+//! ```sh
+//! faucet sync-selections
+//! faucet sync-selections --to primary
+//! ```
+
+use crate::clipboard::{self, Selection};
+use crate::{DisplayServer, Options};
+use anyhow::{Result, anyhow, bail};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub fn run(options: &Options, args: &[String]) -> Result<()> {
+    let mut to = Selection::Clipboard;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => {
+                to = match args.next().map(String::as_str) {
+                    Some("clipboard") => Selection::Clipboard,
+                    Some("primary") => Selection::Primary,
+                    Some(other) => bail!("Unknown selection '{other}', expected clipboard or primary"),
+                    None => bail!("Missing value for '--to'"),
+                }
+            }
+            _ => bail!("Unknown sync-selections argument '{arg}'"),
+        }
+    }
+    if matches!(options.display_server, DisplayServer::Windows) {
+        bail!("Windows has only the clipboard, no primary selection to sync with");
+    }
+    let from = match to {
+        Selection::Clipboard => Selection::Primary,
+        Selection::Primary => Selection::Clipboard,
+    };
+    let (bytes, mime) = clipboard::read(&options.display_server, from, &crate::SystemRunner)?;
+    if bytes.is_empty() {
+        bail!("The source selection is empty");
+    }
+    write(options, to, &bytes, mime.as_deref())
+}
+
+fn write(options: &Options, selection: Selection, bytes: &[u8], mime: Option<&str>) -> Result<()> {
+    let mut cmd = copy_command(options, selection);
+    if let Some(mime) = mime {
+        cmd.args(["-t", mime]);
+    }
+    // xclip keeps serving the selection in the background, so don't hold its stdout open
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open clipboard tool stdin"))?
+        .write_all(bytes)?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("Writing the selection failed with {status}");
+    }
+    Ok(())
+}
+
+/// The tool that puts its stdin on `selection`, `xclip` or `wl-copy`
+pub fn copy_command(options: &Options, selection: Selection) -> Command {
+    match options.display_server {
+        DisplayServer::X11 => {
+            let name = if selection == Selection::Primary { "primary" } else { "clipboard" };
+            xclip(&["-selection", name, "-i"])
+        }
+        DisplayServer::Wayland => {
+            let mut cmd = Command::new("wl-copy");
+            if selection == Selection::Primary {
+                cmd.arg("--primary");
+            }
+            cmd
+        }
+        DisplayServer::Windows => crate::clipboard::powershell(
+            "[Console]::InputEncoding = [Text.Encoding]::UTF8; Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+        ),
+    }
+}
+
+fn xclip(args: &[&str]) -> Command {
+    let mut cmd = Command::new("xclip");
+    cmd.args(args);
+    cmd
+}
@@ -0,0 +1,137 @@
+//! `faucet simulate`: runs the scoring pipeline over fixture files without executing anything.
+//!
+//! ```sh
+//! faucet simulate --input tests/sample.html --source clipboard --expect browser
+//! faucet simulate --input tests/fixtures/
+//! ```
+//!
+//! `--expect` takes a command label (which must auto-select), `menu` (the picker would be
+//! shown) or `none` (nothing scored). Given a directory, every fixture in it is simulated and
+//! its expectation is read from a sibling `<fixture>.expect` file. Exits non-zero if any
+//! expectation fails, so it can run in dotfile CI.
+
+use crate::cache::ProbeCache;
+use crate::{Config, Data, Decision, Plumb, SOURCES, decide, rank_commands, score_commands};
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+
+pub fn run(config: &Config, args: &[String]) -> Result<()> {
+    let mut input = None;
+    let mut source = "clipboard".to_string();
+    let mut expect = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing value for '{arg}'"))
+        };
+        match arg.as_str() {
+            "--input" => input = Some(PathBuf::from(value()?)),
+            "--source" => source = value()?,
+            "--expect" => expect = Some(value()?),
+            _ => bail!("Unknown simulate argument '{arg}'"),
+        }
+    }
+    let input = input.ok_or_else(|| anyhow!("simulate requires --input <file or directory>"))?;
+    if !SOURCES.contains(&source.as_str()) {
+        bail!("Unknown source '{source}', expected one of: {}", SOURCES.join(", "));
+    }
+
+    let fixtures: Vec<(PathBuf, Option<String>)> = if input.is_dir() {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&input)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| path.is_file() && path.extension().is_none_or(|ext| ext != "expect"));
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let expect = std::fs::read_to_string(expectation_path(&path))
+                    .ok()
+                    .map(|expect| expect.trim().to_string())
+                    .or_else(|| expect.clone());
+                (path, expect)
+            })
+            .collect()
+    } else {
+        vec![(input, expect)]
+    };
+
+    let cache = ProbeCache::new(config.options.probe_cache_size);
+    let mut failures = 0;
+    for (path, expect) in &fixtures {
+        if !simulate_fixture(config, &cache, path, &source, expect.as_deref())? {
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} of {} fixtures did not meet their expectation", fixtures.len());
+    }
+    Ok(())
+}
+
+fn expectation_path(fixture: &Path) -> PathBuf {
+    let mut name = fixture.file_name().unwrap_or_default().to_os_string();
+    name.push(".expect");
+    fixture.with_file_name(name)
+}
+
+/// Prints the decision for one fixture, returning whether it met the expectation
+fn simulate_fixture(
+    config: &Config,
+    cache: &ProbeCache,
+    path: &Path,
+    source: &str,
+    expect: Option<&str>,
+) -> Result<bool> {
+    let data = Data::from_bytes(std::fs::read(path)?);
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    let temp_file_path = temp_file_handle
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+    data.write_to_temp_file(temp_file_path)?;
+
+    let mut plumb = Plumb::new(
+        &config.options,
+        &data,
+        temp_file_path,
+        source,
+        data.get_text_for_matching(&crate::SystemRunner, temp_file_path)?,
+    );
+    // As `faucet file <fixture>` would, so `pattern` scorers see the fixture's name
+    if source == "file" {
+        plumb.source_path = Some(std::path::absolute(path)?.display().to_string());
+    }
+    if let Some(derive_command) = &config.options.derive_command {
+        plumb.derive(derive_command)?;
+    }
+
+    let scored_commands = score_commands(config, &plumb, cache, false)?;
+    let mut options = config.options.clone();
+    options.apply_threshold_overrides(plumb.mime(), source);
+    let (outcome, summary) = match decide(&options, rank_commands(&scored_commands)) {
+        Decision::NoMatch => ("none", "no scorers matched".to_string()),
+        Decision::AutoSelect((_, (label, (_, score, _)))) => {
+            (label.as_str(), format!("auto-selects '{label}' ({score})"))
+        }
+        Decision::Menu(sorted_commands) => {
+            let candidates = sorted_commands
+                .iter()
+                .map(|(_, (label, (_, score, _)))| format!("{label} ({score})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ("menu", format!("menu: {candidates}"))
+        }
+    };
+
+    let passed = expect.is_none_or(|expect| expect == outcome);
+    let verdict = match expect {
+        None => String::new(),
+        Some(_) if passed => "PASS ".to_string(),
+        Some(expect) => format!("FAIL (expected {expect}) "),
+    };
+    println!("{verdict}{} [{source}]: {summary}", path.display());
+    Ok(passed)
+}
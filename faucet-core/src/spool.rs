@@ -0,0 +1,123 @@
+//! Keeps binary payloads under unique names in the runtime dir's `spool/`, so a viewer that
+//! is still open doesn't lose its file when faucet exits or the next plumb arrives. Entries are
+//! removed by a background `faucet spool-reap` once no process has them open or names them in
+//! its arguments or environment, and garbage-collected once they are older than `spool_max_age_secs`.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, trace, warn};
+
+/// Time the launched command gets to open the file before the reaper first looks
+const GRACE: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_secs(2);
+
+fn dir() -> Result<PathBuf> {
+    Ok(crate::runtime::dir()?.join("spool"))
+}
+
+/// Copies `data_file` into the spool, first removing entries older than `max_age`
+pub fn keep(data_file: &Path, max_age: Duration) -> Result<PathBuf> {
+    let extension = data_file.extension().map(|extension| extension.to_string_lossy().into_owned());
+    let path = create(extension.as_deref(), max_age)?;
+    std::fs::copy(data_file, &path)?;
+    debug!("Spooled data to {}", path.display());
+    Ok(path)
+}
+
+/// A new empty spool file, first removing entries older than `max_age`
+pub fn create(extension: Option<&str>, max_age: Duration) -> Result<PathBuf> {
+    let dir = dir()?;
+    std::fs::create_dir_all(&dir)?;
+    collect_garbage(&dir, max_age);
+    let suffix = extension.map(|extension| format!(".{extension}"));
+    let path = tempfile::Builder::new()
+        .prefix("faucet_")
+        .suffix(suffix.as_deref().unwrap_or_default())
+        .tempfile_in(&dir)?
+        .into_temp_path()
+        .keep()?;
+    crate::runtime::created(&path);
+    Ok(path)
+}
+
+/// Best effort: a file that can't be inspected or removed is left for the next run
+fn collect_garbage(dir: &Path, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > max_age);
+        if expired && std::fs::remove_file(entry.path()).is_ok() {
+            trace!("Removed expired spool file {}", entry.path().display());
+        }
+    }
+}
+
+/// A spool file whose reaper only starts once it is dropped, so it outlasts whatever is still
+/// to happen in this process (a picker or scorer may only have it by `DATA_FILE`)
+pub struct Reaped {
+    path: PathBuf,
+    max_age: Duration,
+}
+
+impl Reaped {
+    /// `keep`s `data_file`, to be reaped once dropped
+    pub fn keep(data_file: &Path, max_age: Duration) -> Result<Self> {
+        Ok(Self { path: keep(data_file, max_age)?, max_age })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Reaped {
+    fn drop(&mut self) {
+        if let Err(e) = spawn_reaper(&self.path, self.max_age) {
+            warn!("Failed to start the reaper for {}, leaving it to garbage collection: {e}", self.path.display());
+        }
+    }
+}
+
+/// Starts a detached `faucet spool-reap` for `path`, which outlives this process
+pub fn spawn_reaper(path: &Path, max_age: Duration) -> Result<()> {
+    std::process::Command::new(std::env::current_exe()?)
+        .arg("spool-reap")
+        .arg(path)
+        .arg(max_age.as_secs().to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// `faucet spool-reap <path> <max age secs>`: waits until `path` is unused, then removes it.
+/// Gives up after the max age, when garbage collection takes over.
+pub fn run(args: &[String]) -> Result<()> {
+    let [path, max_age] = args else {
+        anyhow::bail!("spool-reap requires a path and a max age");
+    };
+    let path = Path::new(path);
+    let max_age = Duration::from_secs(max_age.parse()?);
+    let started = SystemTime::now();
+    std::thread::sleep(GRACE);
+    while started.elapsed().unwrap_or_default() < max_age {
+        if !path.exists() {
+            return Ok(());
+        }
+        if !crate::runtime::in_use(path) {
+            debug!("Removing unused spool file {}", path.display());
+            std::fs::remove_file(path)?;
+            return Ok(());
+        }
+        std::thread::sleep(POLL);
+    }
+    Ok(())
+}
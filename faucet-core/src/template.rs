@@ -0,0 +1,76 @@
+//! `{name|filter}` placeholder expansion for builtin commands.
+//!
+//! Placeholders name a plumb variable case-insensitively (`{text}`, `{data_file}`, ...)
+//! and may pipe the value through filters (`{text|trim|urlencode}`). `{{` and `}}`
+//! produce literal braces.
+
+use anyhow::{Result, anyhow, bail};
+use std::cell::RefCell;
+
+pub fn expand(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                expanded.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                expanded.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => bail!("Unclosed placeholder in '{template}'"),
+                    }
+                }
+                let mut parts = placeholder.split('|').map(str::trim);
+                let name = parts.next().unwrap_or_default();
+                let mut value = lookup(name)
+                    .ok_or_else(|| anyhow!("Placeholder '{{{name}}}' has no value in '{template}'"))?;
+                for filter in parts {
+                    value = apply_filter(filter, &value)?;
+                }
+                expanded.push_str(&value);
+            }
+            '}' => bail!("Unmatched '}}' in '{template}'"),
+            c => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The placeholder names `template` uses, or why it would fail to expand whatever their values
+pub fn placeholders(template: &str) -> Result<Vec<String>> {
+    let names = RefCell::new(Vec::new());
+    expand(template, |name| {
+        names.borrow_mut().push(name.to_string());
+        Some(String::new())
+    })?;
+    Ok(names.into_inner())
+}
+
+fn apply_filter(filter: &str, value: &str) -> Result<String> {
+    match filter {
+        "urlencode" => Ok(urlencode(value)),
+        "trim" => Ok(value.trim().to_string()),
+        _ => bail!("Unknown template filter '{filter}'"),
+    }
+}
+
+fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,873 @@
+//! Scoring, thresholds and tie-breaking, run hermetically: every process the pipeline would start
+//! goes to a `MockRunner`. The configs name whichever display server the build has, see
+//! `DISPLAY_SERVER`.
+
+use crate::cache::ProbeCache;
+use crate::runner::{MockRunner, Runner};
+use crate::*;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// X11 when it is built in, else the Windows backend every build has (its powershell.exe is
+/// only ever the `MockRunner`'s)
+const DISPLAY_SERVER: &str = if cfg!(feature = "x11") {
+    "X11"
+} else if cfg!(feature = "wayland") {
+    "Wayland"
+} else {
+    "Windows"
+};
+
+/// Parses and checks `yaml`, with `DISPLAY_SERVER` options unless it has its own
+fn config(yaml: &str) -> Config {
+    let yaml = if yaml.contains("options:") {
+        yaml.to_string()
+    } else {
+        format!("options:\n  display_server: {DISPLAY_SERVER}\n{yaml}")
+    };
+    let mut config: Config = diagnostic::parse_config(&yaml, Path::new("test.yaml")).unwrap();
+    check_config(&mut config, &yaml, Path::new("test.yaml"), Validation::Full).unwrap();
+    config
+}
+
+fn plumb<'a>(config: &'a Config, data: &'a Data, source: &'a str, runner: &'a dyn Runner) -> Plumb<'a> {
+    let text = data.get_text_for_matching(runner, "/dev/null").unwrap();
+    Plumb::with_runner(&config.options, data, "/dev/null", source, text, runner)
+}
+
+/// `none`, the auto-selected `label (score)`, or `menu: ` and the ranked candidates
+fn outcome(config: &Config, plumb: &Plumb) -> String {
+    outcome_cached(config, plumb, &ProbeCache::new(0))
+}
+
+fn outcome_cached(config: &Config, plumb: &Plumb, cache: &ProbeCache) -> String {
+    let scored_commands = score_commands(config, plumb, cache, false).unwrap();
+    let mut options = config.options.clone();
+    options.apply_threshold_overrides(plumb.mime(), plumb.source);
+    let describe = |(_, (label, (_, score, _))): &RankedCommand| format!("{label} ({score})");
+    match decide(&options, rank_commands(&scored_commands)) {
+        Decision::NoMatch => "none".to_string(),
+        Decision::AutoSelect(ranked) => describe(&ranked),
+        Decision::Menu(ranked) => format!("menu: {}", ranked.iter().map(describe).join(", ")),
+    }
+}
+
+fn text_outcome(yaml: &str, text: &str) -> String {
+    let config = config(yaml);
+    let data = Data::Text(text.to_string());
+    let runner = MockRunner::new();
+    outcome(&config, &plumb(&config, &data, "clipboard", &runner))
+}
+
+const TWO_COMMANDS: &str = "
+commands:
+  browser: {display: Browser, command: 'true browser'}
+  search: {display: Search, command: 'true search'}
+";
+
+#[test]
+fn regex_scorer_scores_only_matching_text() {
+    let yaml = format!("{TWO_COMMANDS}scorers:\n  - {{regex: '^https?://', command_label: browser, score_change: 20}}\n");
+    assert_eq!(text_outcome(&yaml, "https://example.com"), "browser (20)");
+    assert_eq!(text_outcome(&yaml, "example"), "none");
+}
+
+#[test]
+fn multi_scorer_scores_every_listed_command() {
+    let yaml = format!("{TWO_COMMANDS}scorers:\n  - {{regex: '.', scores: [[browser, 5], [search, 3]]}}\n");
+    assert_eq!(text_outcome(&yaml, "x"), "menu: browser (5), search (3)");
+}
+
+#[test]
+fn scores_add_up_across_scorers_and_resolve_constants() {
+    let yaml = format!(
+        "constants: {{STRONG: 20}}\n{TWO_COMMANDS}scorers:
+  - {{regex: '.', command_label: browser, score_change: $STRONG + 5}}
+  - {{regex: 'x', command_label: browser, score_change: -10}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, "x"), "browser (15)");
+    assert_eq!(text_outcome(&yaml, "y"), "browser (25)");
+}
+
+#[test]
+fn veto_removes_a_command_whatever_it_scored() {
+    let yaml = format!(
+        "{TWO_COMMANDS}scorers:
+  - {{regex: '.', scores: [[browser, 50], [search, 15]]}}
+  - {{regex: 'secret', veto: browser}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, "secret"), "search (15)");
+    assert_eq!(text_outcome(&yaml, "public"), "menu: browser (50), search (15)");
+}
+
+#[test]
+fn a_delta_at_the_veto_threshold_vetoes_whatever_comes_after() {
+    let yaml = format!(
+        "options: {{display_server: {DISPLAY_SERVER}, veto_threshold: -100}}\n{TWO_COMMANDS}scorers:
+  - {{regex: 'secret', command_label: browser, score_change: -100}}
+  - {{regex: '.', scores: [[browser, 500], [search, -99]]}}
+  - {{regex: '.', command_label: search, score_change: 115}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, "secret"), "search (16)");
+    assert_eq!(text_outcome(&yaml, "public"), "browser (500)");
+
+    let yaml = format!("options: {{display_server: {DISPLAY_SERVER}, veto_threshold: 0}}\n{TWO_COMMANDS}scorers: []\n");
+    let error = ScoreEngine::parse(&yaml).err().unwrap();
+    assert!(error.to_string().contains("veto_threshold"), "{error}");
+}
+
+#[test]
+fn zero_and_negative_scores_are_not_offered() {
+    let yaml = format!("{TWO_COMMANDS}scorers:\n  - {{regex: '.', scores: [[browser, 0], [search, -5]]}}\n");
+    assert_eq!(text_outcome(&yaml, "x"), "none");
+}
+
+#[test]
+fn min_score_holds_a_command_back() {
+    let yaml = "
+commands:
+  browser: {display: Browser, command: 'true browser', min_score: 30}
+scorers:
+  - {regex: '.', command_label: browser, score_change: 20}
+  - {regex: 'x', command_label: browser, score_change: 20}
+";
+    assert_eq!(text_outcome(yaml, "y"), "none");
+    assert_eq!(text_outcome(yaml, "x"), "browser (40)");
+}
+
+#[test]
+fn detected_scorer_follows_the_detector() {
+    let yaml = format!("{TWO_COMMANDS}scorers:\n  - {{detected: mailto, command_label: search, score_change: 20}}\n");
+    assert_eq!(text_outcome(&yaml, "mailto:someone@example.com"), "search (20)");
+    assert_eq!(text_outcome(&yaml, "someone at example"), "none");
+}
+
+#[test]
+fn size_scorer_bounds_the_data_length() {
+    let yaml = format!(
+        "{TWO_COMMANDS}scorers:
+  - {{min_bytes: 1K, command_label: browser, score_change: 20}}
+  - {{max_bytes: 4, command_label: search, score_change: 20}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, &"x".repeat(1024)), "browser (20)");
+    assert_eq!(text_outcome(&yaml, &"x".repeat(1023)), "none");
+    assert_eq!(text_outcome(&yaml, "xxxx"), "search (20)");
+    assert_eq!(text_outcome(&yaml, "ü"), "search (20)");
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn lua_scorer_returns_deltas_for_the_data() {
+    let yaml = format!(
+        "{TWO_COMMANDS}scorers:
+  - lua: |
+      if text:find('^https://') then
+        return {{browser = 20, search = size}}
+      elseif mime == 'text/plain' then
+        return {{search = 17.0}}
+      end
+  - {{regex: '^loop', lua: 'while true do end'}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, "https://a.b"), "menu: browser (20), search (11)");
+    assert_eq!(text_outcome(&yaml, "plain words"), "search (17)");
+    // Stopped, not hung, and scoring nothing
+    assert_eq!(text_outcome(&yaml, "loop"), "search (17)");
+
+    let yaml = format!("options: {{display_server: {DISPLAY_SERVER}}}\n{TWO_COMMANDS}scorers:\n  - {{lua: 'return {{'}}\n");
+    let mut broken: Config = diagnostic::parse_config(&yaml, Path::new("test.yaml")).unwrap();
+    let error = check_config(&mut broken, &yaml, Path::new("test.yaml"), Validation::Full).unwrap_err();
+    assert!(error.to_string().contains("Bad lua script"), "{error}");
+    // Only the pure libraries are loaded
+    let config = config(&format!("{TWO_COMMANDS}scorers:\n  - {{lua: 'return os.exit()'}}\n"));
+    let data = Data::Text("text".to_string());
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &MockRunner::new())), "none");
+}
+
+#[test]
+fn external_scorer_reads_the_description_and_prints_deltas() {
+    let config = config(&format!("{TWO_COMMANDS}scorers:\n  - {{external: 'score-links', name: links}}\n"));
+    let data = Data::Text("https://a.b".to_string());
+
+    let runner = MockRunner::new().on("score-links", 0, r#"[["browser", 20], ["search", -3], ["nope", 5]]"#);
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "browser (20)");
+    let call = runner.calls().into_iter().find(|call| call.line() == "sh -c score-links").unwrap();
+    let description: serde_json::Value = serde_json::from_slice(call.input.as_deref().unwrap()).unwrap();
+    assert_eq!(description["text"], "https://a.b");
+    assert_eq!(description["mime"], "text/plain");
+    assert_eq!(description["source"], "clipboard");
+    assert_eq!(description["vars"]["DATA_SIZE"], "11");
+    assert!(description["detected"].as_array().unwrap().iter().any(|name| name == "url"));
+
+    // Failing, or printing something else, scores nothing
+    let runner = MockRunner::new().on("score-links", 1, r#"[["browser", 20]]"#);
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "none");
+    let runner = MockRunner::new().on("score-links", 0, "browser 20");
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "none");
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn plugins_print_deltas_and_broken_ones_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("plugins")).unwrap();
+    // Modules may be given as text, which wasmtime compiles the same
+    std::fs::write(
+        dir.path().join("plugins/links.wasm"),
+        r#"(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "browser 20\nsearch -3\nnot a delta\n")
+  (func (export "_start")
+    (i32.store (i32.const 0) (i32.const 16))
+    (i32.store (i32.const 4) (i32.const 33))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#,
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("plugins/notes.txt"), "not a plugin").unwrap();
+
+    let mut config = config(&format!("{TWO_COMMANDS}scorers:\n  - {{regex: '.', command_label: search, score_change: 10}}\n"));
+    config.plugins = plugin::discover(dir.path());
+    assert_eq!(config.plugins.iter().map(|plugin| plugin.name.as_str()).collect::<Vec<_>>(), ["links"]);
+    let data = Data::Text("https://a.b".to_string());
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &MockRunner::new())), "menu: browser (20), search (7)");
+
+    std::fs::write(dir.path().join("plugins/broken.wasm"), "(module").unwrap();
+    config.plugins = plugin::discover(dir.path());
+    assert!(config.plugins[0].problem().is_some_and(|problem| problem.contains("broken.wasm")));
+    // A plugin that can't run scores nothing, leaving the rest to score
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &MockRunner::new())), "menu: browser (20), search (7)");
+}
+
+#[test]
+fn windows_clipboard_is_read_through_powershell_as_unix_text() {
+    let runner = MockRunner::new().on("Get-Clipboard", 0, "line one\r\nline two\r\n");
+    let (bytes, mime) = clipboard::read(&DisplayServer::Windows, Selection::Clipboard, &runner).unwrap();
+    assert_eq!((String::from_utf8(bytes).unwrap().as_str(), mime), ("line one\nline two", None));
+    assert_eq!(runner.calls()[0].program, "powershell.exe");
+
+    let runner = MockRunner::new().on("Get-Clipboard", 0, "\r\n");
+    assert!(clipboard::read(&DisplayServer::Windows, Selection::Primary, &runner).is_err());
+}
+
+#[cfg(feature = "wayland")]
+#[test]
+fn only_the_primary_selection_takes_an_image_over_text() {
+    let runner = || {
+        MockRunner::new()
+            .on("--list-types", 0, "image/png\ntext/plain;charset=utf-8\n")
+            .on("wl-paste", 0, "bytes")
+    };
+    let clipboard = runner();
+    assert_eq!(clipboard::read(&DisplayServer::Wayland, Selection::Clipboard, &clipboard).unwrap().1, None);
+    assert!(!clipboard.calls().last().unwrap().line().contains("-t"));
+    let primary = runner();
+    let (_, mime) = clipboard::read(&DisplayServer::Wayland, Selection::Primary, &primary).unwrap();
+    assert_eq!(mime.as_deref(), Some("image/png"));
+    assert!(primary.calls().last().unwrap().line().ends_with("--primary --no-newline -t image/png"));
+
+    let image_only = MockRunner::new().on("--list-types", 0, "image/png\n").on("wl-paste", 0, "bytes");
+    let (_, mime) = clipboard::read(&DisplayServer::Wayland, Selection::Clipboard, &image_only).unwrap();
+    assert_eq!(mime.as_deref(), Some("image/png"));
+}
+
+#[test]
+fn data_can_come_through_a_file_descriptor() {
+    let mut args: Vec<String> = ["faucet", "--fd", "3"].map(String::from).to_vec();
+    assert_eq!(cli::take_flags(&mut args).unwrap().fd, Some(3));
+    assert_eq!(args, ["faucet"]);
+    let mut args: Vec<String> = ["faucet", "--fd", "-1"].map(String::from).to_vec();
+    assert!(cli::take_flags(&mut args).is_err());
+
+    assert_eq!(cli::fd_path("/dev/fd/5"), Some(5));
+    assert_eq!(cli::fd_path("/proc/self/fd/0"), Some(0));
+    assert_eq!(cli::fd_path("/dev/fd/x"), None);
+    assert_eq!(cli::fd_path("fd/5"), None);
+}
+
+#[test]
+fn command_scorer_fires_on_success_and_sees_the_plumb() {
+    let config = config(&format!("{TWO_COMMANDS}scorers:\n  - {{command: 'probe-ok', command_label: search, score_change: 20}}\n"));
+    let data = Data::Text("hello".to_string());
+
+    let runner = MockRunner::new().on("probe-ok", 0, "");
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "search (20)");
+    let probe = runner.calls().into_iter().find(|call| call.line() == "sh -c probe-ok").unwrap();
+    assert_eq!(probe.env("TEXT"), Some("hello"));
+    assert_eq!(probe.env("IS_BINARY"), Some("0"));
+    assert_eq!(probe.env("DATA_SIZE"), Some("5"));
+
+    let runner = MockRunner::new().on("probe-ok", 1, "");
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "none");
+}
+
+#[test]
+fn command_scorer_is_probed_once_per_data() {
+    let config = config(&format!("{TWO_COMMANDS}scorers:\n  - {{command: 'probe-ok', command_label: search, score_change: 20}}\n"));
+    let data = Data::Text("hello".to_string());
+    let runner = MockRunner::new().on("probe-ok", 0, "");
+    let plumb = plumb(&config, &data, "clipboard", &runner);
+    let cache = ProbeCache::new(16);
+    score_commands(&config, &plumb, &cache, false).unwrap();
+    score_commands(&config, &plumb, &cache, false).unwrap();
+    let probes = runner.calls().iter().filter(|call| call.line() == "sh -c probe-ok").count();
+    assert_eq!(probes, 1);
+}
+
+#[test]
+fn command_scorers_are_probed_concurrently_and_apply_in_order() {
+    let scorers = (0..5).map(|n| format!("  - {{command: 'probe-{n}', command_label: search, score_change: {}}}\n", 10 + n)).join("");
+    let config = config(&format!(
+        "options: {{display_server: {DISPLAY_SERVER}, veto_threshold: -100}}\n{TWO_COMMANDS}scorers:\n{scorers}  - {{command: 'probe-veto', command_label: search, score_change: -100}}\n  - {{command: 'probe-0', command_label: search, score_change: 100}}\n"
+    ));
+    let data = Data::Text("hello".to_string());
+    let runner = MockRunner::new().on("probe-veto", 1, "").on("probe-", 0, "").delay(std::time::Duration::from_millis(200));
+    let slow = plumb(&config, &data, "clipboard", &runner);
+    let started = std::time::Instant::now();
+    assert_eq!(outcome(&config, &slow), "search (160)");
+    assert!(started.elapsed() < std::time::Duration::from_millis(1000), "took {:?}", started.elapsed());
+
+    // The veto fires before the last scorer, which can't bring the command back
+    let runner = MockRunner::new().on("probe-", 0, "");
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "none");
+}
+
+#[test]
+fn timed_out_command_scorers_count_as_not_matched() {
+    let scorers = "scorers:\n  - {command: 'probe-slow', timeout_ms: 50, command_label: search, score_change: 20}\n  - {command: 'probe-slow too', command_label: browser, score_change: 20}\n";
+    let mocked = config(&format!("{TWO_COMMANDS}{scorers}"));
+    let data = Data::Text("hello".to_string());
+    let runner = MockRunner::new().on("probe-slow", 0, "").delay(std::time::Duration::from_millis(200));
+    assert_eq!(outcome(&mocked, &plumb(&mocked, &data, "clipboard", &runner)), "browser (20)");
+
+    // The time out isn't cached: plumbing the same data again probes again
+    let cache = ProbeCache::new(16);
+    let plumbed = plumb(&mocked, &data, "clipboard", &runner);
+    score_commands(&mocked, &plumbed, &cache, false).unwrap();
+    score_commands(&mocked, &plumbed, &cache, false).unwrap();
+    let probes = runner.calls().iter().filter(|call| call.line() == "sh -c probe-slow").count();
+    assert_eq!(probes, 3);
+
+    // A real one is killed, along with what it started
+    let sleeper = config(&format!("{TWO_COMMANDS}scorers:\n  - {{command: 'sleep 10', timeout_ms: 100, command_label: search, score_change: 20}}\n"));
+    let started = std::time::Instant::now();
+    assert_eq!(outcome(&sleeper, &plumb(&sleeper, &data, "clipboard", &SystemRunner)), "none");
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn scorer_failure_abort_fails_the_plumb() {
+    let scorer = "scorers:\n  - {command: 'true', command_label: search, score_change: 20}\n";
+    let token = CancelToken::new();
+    token.cancel();
+    let runner = CancellableRunner::new(token);
+    let data = Data::Text("hello".to_string());
+    let warn = config(&format!("{TWO_COMMANDS}{scorer}"));
+    assert!(score_commands(&warn, &plumb(&warn, &data, "clipboard", &runner), &ProbeCache::new(0), false).is_ok());
+    let abort = config(&format!("options: {{display_server: {DISPLAY_SERVER}, scorer_failure: abort}}\n{TWO_COMMANDS}{scorer}"));
+    assert!(score_commands(&abort, &plumb(&abort, &data, "clipboard", &runner), &ProbeCache::new(0), false).is_err());
+}
+
+#[test]
+fn scorers_with_unknown_keys_or_no_condition_are_rejected() {
+    let parse = |scorer: &str| {
+        let yaml = format!("options: {{display_server: {DISPLAY_SERVER}}}\n{TWO_COMMANDS}scorers:\n  - {scorer}\n");
+        let mut config: Config = diagnostic::parse_config(&yaml, Path::new("test.yaml"))?;
+        check_config(&mut config, &yaml, Path::new("test.yaml"), Validation::Full)?;
+        anyhow::Ok(config)
+    };
+    assert!(parse("{regx: '^a', command_label: search, score_change: 5}").is_err());
+    assert!(parse("{command_label: search, score_change: 5}").is_err());
+    let size = parse("{max_bytes: 10, command_label: search, score_change: 5}").unwrap();
+    assert_eq!(size.scorers[0].describe(), "size '<= 10'");
+}
+
+#[test]
+fn mime_wildcards_match_alike_in_accepts_and_xdg_default() {
+    for pattern in ["*", "*/*", "image/*", "image/png", "IMAGE/PNG"] {
+        assert!(is_mime_pattern(pattern), "{pattern}");
+        assert!(xdg::mime_matches(pattern, "image/png"), "{pattern}");
+    }
+    assert!(!xdg::mime_matches("text/*", "image/png"));
+}
+
+#[test]
+fn binary_data_is_matched_on_the_type_file_reports() {
+    let config = config(
+        "
+commands:
+  viewer: {display: View, command: 'true view', accepts: ['image/*']}
+  editor: {display: Edit, command: 'true edit', accepts: ['text/*']}
+scorers:
+  - {regex: '^image/', scores: [[viewer, 20], [editor, 20]]}
+",
+    );
+    let data = Data::Binary(vec![0x89, b'P', b'N', b'G', 0, 0xff]);
+    let runner = MockRunner::new().on("file --mime-type", 0, "image/png\n");
+    let plumb = plumb(&config, &data, "clipboard", &runner);
+    assert_eq!(plumb.mime(), "image/png");
+    assert_eq!(outcome(&config, &plumb), "viewer (20)");
+}
+
+#[cfg(feature = "ocr")]
+#[test]
+fn derived_text_reaches_later_probes() {
+    let config = config(&format!(
+        "options:
+  display_server: {DISPLAY_SERVER}
+  derive_command: ocr
+{TWO_COMMANDS}scorers:
+  - {{command: 'probe-ok', command_label: search, score_change: 20}}
+"
+    ));
+    let data = Data::Text("hello".to_string());
+    let runner = MockRunner::new().on("ocr", 0, "recognised\n").on("probe-ok", 0, "");
+    let mut plumb = plumb(&config, &data, "clipboard", &runner);
+    plumb.derive(config.options.derive_command.as_deref().unwrap()).unwrap();
+    score_commands(&config, &plumb, &ProbeCache::new(0), false).unwrap();
+    let probe = runner.calls().into_iter().find(|call| call.line() == "sh -c probe-ok").unwrap();
+    assert_eq!(probe.env("DERIVED_TEXT"), Some("recognised"));
+}
+
+#[test]
+fn a_lone_candidate_auto_selects_above_the_min_threshold() {
+    let yaml = |score: i32| format!("{TWO_COMMANDS}scorers:\n  - {{regex: '.', command_label: browser, score_change: {score}}}\n");
+    // The default min threshold is 10, and has to be exceeded
+    assert_eq!(text_outcome(&yaml(11), "x"), "browser (11)");
+    assert_eq!(text_outcome(&yaml(10), "x"), "menu: browser (10)");
+}
+
+#[test]
+fn a_leader_auto_selects_only_past_the_max_threshold() {
+    let yaml = |lead: i32| {
+        format!(
+            "options:
+  display_server: {DISPLAY_SERVER}
+  auto_select_max_threshold: 30
+{TWO_COMMANDS}scorers:
+  - {{regex: '.', scores: [[browser, {}], [search, 20]]}}
+",
+            20 + lead
+        )
+    };
+    assert_eq!(text_outcome(&yaml(31), "x"), "browser (51)");
+    assert_eq!(text_outcome(&yaml(30), "x"), "menu: browser (50), search (20)");
+}
+
+#[test]
+fn auto_select_off_always_shows_the_menu() {
+    let yaml = format!(
+        "options:
+  display_server: {DISPLAY_SERVER}
+  auto_select: false
+{TWO_COMMANDS}scorers:
+  - {{regex: '.', command_label: browser, score_change: 500}}
+"
+    );
+    assert_eq!(text_outcome(&yaml, "x"), "menu: browser (500)");
+}
+
+#[test]
+fn threshold_overrides_apply_to_matching_sources() {
+    let config = config(&format!(
+        "options:
+  display_server: {DISPLAY_SERVER}
+  threshold_overrides:
+    - {{source: selection, auto_select_min_threshold: 50}}
+{TWO_COMMANDS}scorers:
+  - {{regex: '.', command_label: browser, score_change: 20}}
+"
+    ));
+    let data = Data::Text("x".to_string());
+    let runner = MockRunner::new();
+    assert_eq!(outcome(&config, &plumb(&config, &data, "clipboard", &runner)), "browser (20)");
+    assert_eq!(outcome(&config, &plumb(&config, &data, "selection", &runner)), "menu: browser (20)");
+}
+
+#[test]
+fn ties_keep_config_order_and_never_auto_select() {
+    let yaml = format!("{TWO_COMMANDS}scorers:\n  - {{regex: '.', scores: [[search, 40], [browser, 40]]}}\n");
+    assert_eq!(text_outcome(&yaml, "x"), "menu: browser (40), search (40)");
+}
+
+#[test]
+fn commands_running_the_same_action_merge_into_the_first() {
+    let yaml = "
+commands:
+  open: {display: Open, command: 'xdg-open \"$DATA_FILE\"'}
+  view: {display: View, command: 'xdg-open \"$DATA_FILE\"'}
+scorers:
+  - {regex: '.', scores: [[open, 10], [view, 5]]}
+";
+    assert_eq!(text_outcome(yaml, "x"), "open (15)");
+}
+
+#[test]
+fn hooks_run_after_the_command_with_its_exit_code() {
+    let config = config(
+        "
+commands:
+  open: {display: Open, command: 'true', on_success: 'report ok', on_failure: 'report failed'}
+scorers: []
+",
+    );
+    let data = Data::Text("x".to_string());
+    let runner = MockRunner::new();
+    let plumb = plumb(&config, &data, "clipboard", &runner);
+    let command = &config.commands["open"];
+    plumb.run_hooks(command, std::process::ExitStatus::from_raw(0));
+    plumb.run_hooks(command, std::process::ExitStatus::from_raw(3 << 8));
+    let hooks: Vec<_> = runner.calls().iter().map(|call| (call.line(), call.env("EXIT_CODE").map(String::from))).collect();
+    assert_eq!(
+        hooks,
+        [
+            ("sh -c report ok".to_string(), Some("0".to_string())),
+            ("sh -c report failed".to_string(), Some("3".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn builtins_resolve_to_the_configured_program() {
+    let config = config(&format!(
+        "
+options:
+  display_server: {DISPLAY_SERVER}
+  browser: 'firefox --new-tab'
+commands:
+  web: {{display: Web, builtin: open_url, url: 'https://example.com/?q={{text|urlencode}}'}}
+  browse: {{display: Browse, builtin: browser}}
+scorers: []
+"
+    ));
+    let data = Data::Text("a b".to_string());
+    let runner = MockRunner::new();
+    let plumbed = plumb(&config, &data, "clipboard", &runner);
+    let command = plumbed.process(&config.commands["web"].action).unwrap();
+    let args: Vec<_> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    assert_eq!(command.get_program(), "firefox");
+    assert_eq!(args, ["--new-tab", "https://example.com/?q=a%20b"]);
+
+    // Text the browser would take for an option isn't given to it
+    let data = Data::Text("--remote-debugging-port=9222".to_string());
+    let plumbed = plumb(&config, &data, "clipboard", &runner);
+    assert!(plumbed.process(&config.commands["browse"].action).is_err());
+}
+
+#[test]
+fn builtins_without_a_display_are_shown_by_name() {
+    let config = config(
+        "
+commands:
+  browse: {builtin: browser}
+  calc: {display: 'Sum {text}', builtin: calculate}
+scorers: []
+",
+    );
+    let data = Data::Text("1 + 2".to_string());
+    let runner = MockRunner::new();
+    let plumbed = plumb(&config, &data, "clipboard", &runner);
+    assert_eq!(plumbed.display(&config.commands["browse"]), "Open in browser");
+    assert_eq!(plumbed.display(&config.commands["calc"]), "Sum 1 + 2");
+
+    let yaml = format!("options: {{display_server: {DISPLAY_SERVER}}}\ncommands:\n  shell: {{command: 'true'}}\nscorers: []\n");
+    let mut config: Config = diagnostic::parse_config(&yaml, Path::new("test.yaml")).unwrap();
+    assert!(check_config(&mut config, &yaml, Path::new("test.yaml"), Validation::Full).is_err());
+}
+
+#[test]
+fn regex_captures_reach_the_command_they_scored() {
+    let config = config(
+        "
+commands:
+  torrent: {display: Torrent, builtin: open_url, url: 'https://example.com/{MATCH_hash}'}
+  search: {display: Search, command: 'true search'}
+scorers:
+  - {regex: 'magnet:\\?xt=urn:btih:(?P<hash>[0-9a-f]+)', command_label: torrent, score_change: 20}
+  - {regex: '(\\w+)', scores: [[torrent, 5], [search, 5]]}
+  - {regex: '(nothing)?here', command_label: search, score_change: -5}
+",
+    );
+    let data = Data::Text("see magnet:?xt=urn:btih:c0ffee here".to_string());
+    let runner = MockRunner::new();
+    let plumb = plumb(&config, &data, "clipboard", &runner);
+    let captures = capture_vars(&config, &plumb, "torrent");
+    let capture = |name: &str| captures.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    assert_eq!(capture("MATCH_0"), Some("magnet:?xt=urn:btih:c0ffee"));
+    assert_eq!(capture("MATCH_1"), Some("c0ffee"));
+    assert_eq!(capture("MATCH_hash"), Some("c0ffee"));
+    // Only scorers that scored the command up count, and groups that didn't take part are left out
+    assert_eq!(capture_vars(&config, &plumb, "search"), [("MATCH_0".to_string(), "see".to_string()), ("MATCH_1".to_string(), "see".to_string())]);
+
+    let mut plumb = plumb;
+    plumb.captures = captures;
+    let command = plumb.process(&config.commands["torrent"].action).unwrap();
+    assert!(command.get_args().any(|arg| arg == "https://example.com/c0ffee"));
+    assert!(command.get_envs().any(|(key, value)| key == "MATCH_hash" && value.is_some_and(|value| value == "c0ffee")));
+}
+
+#[test]
+fn placeholders_must_name_a_group_the_commands_regexes_capture() {
+    let check = |url: &str| {
+        let yaml = format!(
+            "options: {{display_server: {DISPLAY_SERVER}}}
+commands:
+  torrent: {{display: Torrent, builtin: open_url, url: '{url}'}}
+  search: {{display: Search, command: 'true search'}}
+scorers:
+  - {{regex: 'btih:(?P<hash>[0-9a-f]+)', command_label: torrent, score_change: 20}}
+  - {{regex: '(\\w+) (\\w+)', scores: [[torrent, -5], [search, 5]]}}
+"
+        );
+        let mut config: Config = diagnostic::parse_config(&yaml, Path::new("test.yaml"))?;
+        check_config(&mut config, &yaml, Path::new("test.yaml"), Validation::Full)
+    };
+    for url in ["https://a.b/{MATCH_0}", "https://a.b/{match_1}", "https://a.b/{MATCH_hash}"] {
+        assert!(check(url).is_ok(), "{url}");
+    }
+    // The second regex scores the command down, so its groups never reach it
+    for url in ["https://a.b/{MATCH_2}", "https://a.b/{MATCH_name}", "https://a.b/{MATCH_}"] {
+        assert!(check(url).is_err(), "{url}");
+    }
+}
+
+#[test]
+fn score_engine_runs_everything_through_its_runner() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("faucet.yaml");
+    std::fs::write(
+        &path,
+        format!(
+            "
+options:
+  display_server: {DISPLAY_SERVER}
+commands:
+  viewer: {{display: View, command: 'true view', accepts: ['image/*']}}
+scorers:
+  - {{command: 'is-photo', command_label: viewer, score_change: 20}}
+"
+        ),
+    )
+    .unwrap();
+    let runner = Arc::new(MockRunner::new().on("file --mime-type", 0, "image/jpeg\n").on("is-photo", 0, ""));
+    let engine = ScoreEngine::load(&path).unwrap().with_runner(runner.clone());
+    let scored = engine.evaluate(&Data::Binary(vec![0xff, 0xd8, 0xff, 0xe0])).unwrap();
+    assert_eq!(scored.iter().map(|command| (command.label.as_str(), command.score)).collect::<Vec<_>>(), [("viewer", 20)]);
+    let lines: Vec<String> = runner.calls().iter().map(|call| call.line()).collect();
+    assert!(lines[0].starts_with("file --mime-type -b "));
+    assert!(lines.contains(&"sh -c is-photo".to_string()));
+}
+
+#[test]
+fn cancelling_kills_running_probes_and_fails_later_ones() {
+    // The slow probe is quick once the marker exists
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("done");
+    let config = config(&format!(
+        "
+commands:
+  slow: {{display: Slow, command: 'true slow'}}
+  fast: {{display: Fast, command: 'true fast'}}
+scorers:
+  - {{command: 'test -e {} || sleep 30', command_label: slow, score_change: 20}}
+  - {{command: 'true', command_label: fast, score_change: 20}}
+",
+        marker.display()
+    ));
+    let cache = ProbeCache::new(16);
+    let cancel = CancelToken::new();
+    let runner = CancellableRunner::new(cancel.clone());
+    let data = Data::Text("text".to_string());
+    let canceller = cancel.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        canceller.cancel();
+    });
+    let started = std::time::Instant::now();
+    // The fast probe runs alongside the slow one, before the cancel
+    assert_eq!(outcome_cached(&config, &plumb(&config, &data, "stdin", &runner), &cache), "fast (20)");
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    assert!(cancel.is_cancelled());
+    assert!(runner.status(std::process::Command::new("true").arg("again")).is_err());
+
+    // The killed probe wasn't cached as failing, so plumbing the same data again runs it again
+    std::fs::File::create(&marker).unwrap();
+    let runner = CancellableRunner::new(CancelToken::new());
+    assert_eq!(outcome_cached(&config, &plumb(&config, &data, "stdin", &runner), &cache), "menu: slow (20), fast (20)");
+}
+
+/// Deterministic xorshift, so a failing case can be replayed from its seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Text and bytes of the kinds that trip parsers: invalid and surrogate UTF-8, control and
+    /// combining characters, emoji, operators, and very long lines
+    fn data(&mut self) -> Vec<u8> {
+        const PIECES: &[&str] = &[
+            "https://", "example.com", "/", "?q=", "%", "%zz", "@", "mailto:", "file://", "~/", ".mp4", " ", "\n", "\r\n", "\t",
+            "\0", "\u{1b}[31m", "é", "e\u{301}", "🇺🇳", "👩\u{200d}💻", "1\u{fe0f}\u{20e3}", "\u{202e}", "\u{fffd}", "(", ")",
+            "^", "**", "/", "-", "1e308", "0.", "√", "=", "BEGIN:VCALENDAR", "---", "diff --git", "\\ud800", "'", "\"", ",",
+            "sha256:", "otpauth://totp/", "user@host:", "SELECT", "kind: Deployment", "E", "a",
+        ];
+        let mut bytes = Vec::new();
+        for _ in 0..self.below(40) {
+            match self.below(10) {
+                // A surrogate, an overlong sequence, or a lone continuation byte
+                0 => {
+                    let invalid: [&[u8]; 4] = [b"\xed\xa0\x80", b"\xc0\xaf", b"\x80", b"\xf4\x90\x80\x80"];
+                    let piece = *self.pick(&invalid[..]);
+                    bytes.extend_from_slice(piece);
+                }
+                1 => bytes.push(self.next() as u8),
+                _ => bytes.extend_from_slice(self.pick(PIECES).as_bytes()),
+            }
+        }
+        if self.below(20) == 0 {
+            let piece = *self.pick(&["a", "9", "(", "é", "🙂", "%41", "+"]);
+            bytes.extend(piece.repeat(200_000).into_bytes());
+        }
+        bytes
+    }
+
+    /// A valid config of random commands, scorers and thresholds
+    fn config(&mut self) -> String {
+        const CONDITIONS: &[&str] = &[
+            "regex: '^https?://'", "regex: '.'", "regex: '(?i)^[a-z]+$'", "regex: '\\d'", "regex: '^$'", "detected: url",
+            "detected: math", "detected: word", "detected: emoji", "detected: unicode", "pattern: '*.pdf'",
+            "command: 'true'",
+        ];
+        let labels = ["a", "b", "c", "d"];
+        let min_threshold = self.below(40) as i32 - 10;
+        let mut yaml = format!(
+            "options:\n  display_server: {DISPLAY_SERVER}\n  auto_select: {}\n  auto_select_min_threshold: {min_threshold}\n  auto_select_max_threshold: {}\ncommands:\n",
+            self.below(4) != 0,
+            min_threshold + 1 + self.below(60) as i32,
+        );
+        for label in labels {
+            yaml.push_str(&format!("  {label}: {{display: '{label} {{text}}', command: 'true {label}'}}\n"));
+        }
+        yaml.push_str("scorers:\n");
+        for _ in 0..self.below(8) {
+            let condition = self.pick(CONDITIONS);
+            let label = self.pick(&labels);
+            let effect = match self.below(4) {
+                0 => format!("veto: {label}"),
+                1 => format!("scores: [[{label}, {}], [{}, {}]]", self.below(50), self.pick(&labels), self.below(50) as i32 - 20),
+                _ => format!("command_label: {label}, score_change: {}", self.below(80) as i32 - 20),
+            };
+            yaml.push_str(&format!("  - {{{condition}, {effect}}}\n"));
+        }
+        yaml
+    }
+}
+
+fn check_outcome(outcome: &Outcome, min_threshold: Option<i32>) {
+    match outcome {
+        Outcome::NoMatch => {}
+        Outcome::AutoSelect { command } => {
+            assert!(command.score > 0);
+            if let Some(min_threshold) = min_threshold {
+                assert!(command.score > min_threshold);
+            }
+        }
+        Outcome::Menu { candidates } => {
+            assert!(!candidates.is_empty());
+            assert!(candidates.iter().all(|command| command.score > 0));
+            assert!(candidates.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        }
+    }
+}
+
+#[test]
+fn pure_decisions_hold_up_on_arbitrary_configs_and_data() {
+    for seed in 1..=150u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let yaml = rng.config();
+        let engine = ScoreEngine::parse(&yaml).unwrap_or_else(|e| panic!("seed {seed}: {e}\n{yaml}")).pure();
+        for _ in 0..4 {
+            let data = rng.data();
+            let outcome = engine.decide(&data).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+            check_outcome(&outcome, Some(engine_min_threshold(&yaml)));
+            assert_eq!(engine.decide(&data).unwrap(), outcome, "seed {seed}: not deterministic");
+        }
+    }
+}
+
+fn engine_min_threshold(yaml: &str) -> i32 {
+    yaml.lines().find_map(|line| line.trim().strip_prefix("auto_select_min_threshold: ")?.parse().ok()).unwrap()
+}
+
+// The example config has a lua scorer
+#[cfg(feature = "lua")]
+#[test]
+fn the_example_config_scores_arbitrary_data_purely() {
+    let engine = ScoreEngine::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../example.faucet.yaml")).unwrap().pure();
+    for seed in 1..=200u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x2545_f491_4f6c_dd1d));
+        let data = rng.data();
+        let outcome = engine.decide(&data).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+        check_outcome(&outcome, None);
+    }
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn compiled_config_round_trips_and_misses_once_edited() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../example.faucet.yaml");
+    let content = std::fs::read_to_string(&path).unwrap();
+    let config = config(&content);
+    let dir = tempfile::tempdir().unwrap();
+    compiled::store_in(dir.path(), &path, &content, &config).unwrap();
+
+    let cached = compiled::load_from(dir.path(), &path, &content).unwrap();
+    assert_eq!(serde_json::to_value(&cached).unwrap(), serde_json::to_value(&config).unwrap());
+    let data = Data::Text("https://example.com/a.pdf".to_string());
+    let runner = MockRunner::new();
+    assert_eq!(outcome(&cached, &plumb(&cached, &data, "clipboard", &runner)), outcome(&config, &plumb(&config, &data, "clipboard", &runner)));
+
+    assert!(compiled::load_from(dir.path(), &path, &format!("{content}\n# edited")).is_none());
+    assert!(compiled::load_from(dir.path(), Path::new("other.yaml"), &content).is_none());
+}
+
+#[test]
+fn mangled_configs_are_rejected_not_panicked_on() {
+    for seed in 1..=300u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let mut yaml = rng.config().into_bytes();
+        for _ in 0..1 + rng.below(4) {
+            if yaml.is_empty() {
+                break;
+            }
+            let at = rng.below(yaml.len());
+            match rng.below(3) {
+                0 => drop(yaml.remove(at)),
+                1 => yaml.insert(at, *rng.pick(b"{}[]:-'\"\\*$ \n\t#&!|>%@`")),
+                _ => yaml.truncate(at),
+            }
+        }
+        let yaml = String::from_utf8_lossy(&yaml);
+        if let Ok(engine) = ScoreEngine::parse(&yaml) {
+            engine.pure().decide(b"https://example.com/a.pdf").unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+        }
+    }
+}
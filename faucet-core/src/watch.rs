@@ -0,0 +1,104 @@
+//! `faucet watch`: stays resident and re-ranks a sample input whenever the config (or the sample)
+//! changes, for a live feedback loop while tuning scorers.
+//!
+//! ```sh
+//! faucet watch --input tests/sample.html --source clipboard
+//! ```
+//!
+//! `faucet watch --clipboard` keeps a clipboard history instead, see `history`.
+//!
+//! A config that fails to load is reported in place of the ranking; watching carries on. `SIGHUP`
+//! re-ranks at once, see `lifecycle` for the other signals.
+
+use crate::cache::ProbeCache;
+use crate::cli::Flags;
+use crate::lifecycle::Lifecycle;
+use crate::{CancellableRunner, Data, Plumb, SOURCES, decide, decision_report, rank_commands, score_commands};
+use anyhow::{Result, anyhow, bail};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn run(config_path: &Path, flags: &Flags, args: &[String]) -> Result<()> {
+    if args.first().is_some_and(|arg| arg == "--clipboard") {
+        return crate::history::watch(config_path, flags, &args[1..]);
+    }
+    let mut input = None;
+    let mut source = "clipboard".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing value for '{arg}'"))
+        };
+        match arg.as_str() {
+            "--input" => input = Some(PathBuf::from(value()?)),
+            "--source" => source = value()?,
+            _ => bail!("Unknown watch argument '{arg}'"),
+        }
+    }
+    let input = input.ok_or_else(|| anyhow!("watch requires --input <file>"))?;
+    if !SOURCES.contains(&source.as_str()) {
+        bail!("Unknown source '{source}', expected one of: {}", SOURCES.join(", "));
+    }
+
+    let lifecycle = Lifecycle::install("watch", config_path.to_path_buf(), flags.clone())?;
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut last_seen: Option<(Option<SystemTime>, Option<SystemTime>)> = None;
+    let mut evaluation = 0;
+    loop {
+        let seen = (modified(config_path), modified(&input));
+        let reload = lifecycle.take_reload();
+        if reload || last_seen != Some(seen) {
+            let _busy = lifecycle.busy();
+            last_seen = Some(seen);
+            evaluation += 1;
+            lifecycle.handled();
+            println!("--- #{evaluation}: {} [{source}] ---", input.display());
+            match rank(&lifecycle, &input, &source) {
+                Ok(report) => print!("{report}"),
+                Err(e) => println!("{e}"),
+            }
+            std::io::stdout().flush()?;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Loads the config afresh and reports what it would do with `input`
+fn rank(lifecycle: &Lifecycle, input: &Path, source: &str) -> Result<String> {
+    let config = lifecycle.load_config()?;
+    let cancel = lifecycle.cancellable();
+    let runner = CancellableRunner::new(cancel.clone());
+    let data = Data::from_bytes(std::fs::read(input)?);
+    let temp_file_handle = crate::runtime::temp_file("faucet_data_")?;
+    let temp_file_path = temp_file_handle
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow!("Failed to convert temp file path to string"))?;
+    data.write_to_temp_file(temp_file_path)?;
+
+    let mut plumb = Plumb::with_runner(
+        &config.options,
+        &data,
+        temp_file_path,
+        source,
+        data.get_text_for_matching(&runner, temp_file_path)?,
+        &runner,
+    );
+    if let Some(derive_command) = &config.options.derive_command {
+        plumb.derive(derive_command)?;
+    }
+
+    let cache = ProbeCache::new(0);
+    let scored_commands = score_commands(&config, &plumb, &cache, false)?;
+    if cancel.is_cancelled() {
+        bail!("Cancelled");
+    }
+    let mut options = config.options.clone();
+    options.apply_threshold_overrides(plumb.mime(), source);
+    Ok(decision_report(&plumb, &decide(&options, rank_commands(&scored_commands))))
+}
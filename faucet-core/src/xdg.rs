@@ -0,0 +1,118 @@
+//! Default application lookups against the XDG `mimeapps.list` database.
+
+use std::path::PathBuf;
+
+/// Parsed `xdg_default` condition: `"<mime glob> == <desktop id>"` (or `!=`)
+pub struct DefaultCondition<'a> {
+    pub mime_pattern: &'a str,
+    pub desktop: &'a str,
+    pub negated: bool,
+}
+
+impl<'a> DefaultCondition<'a> {
+    pub fn parse(expression: &'a str) -> Option<Self> {
+        let (lhs, rhs, negated) = if let Some((lhs, rhs)) = expression.split_once("!=") {
+            (lhs, rhs, true)
+        } else {
+            let (lhs, rhs) = expression.split_once("==")?;
+            (lhs, rhs, false)
+        };
+        let unquote = |s: &'a str| s.trim().trim_matches(|c| c == '"' || c == '\'');
+        let (mime_pattern, desktop) = (unquote(lhs), unquote(rhs));
+        if mime_pattern.is_empty() || desktop.is_empty() {
+            return None;
+        }
+        Some(Self {
+            mime_pattern,
+            desktop,
+            negated,
+        })
+    }
+
+    /// True when `mime` matches the pattern and its default application agrees with the condition
+    pub fn matches(&self, mime: &str) -> bool {
+        if !mime_matches(self.mime_pattern, mime) {
+            return false;
+        }
+        let is_default = default_application(mime).is_some_and(|app| app == self.desktop);
+        is_default != self.negated
+    }
+}
+
+/// Whether `mime` fits `pattern`, which may wildcard the subtype (`text/*`) or everything (`*/*`
+/// or `*`); commands' `accepts`, `xdg_default` and `http_head` scorers all match with this
+pub(crate) fn mime_matches(pattern: &str, mime: &str) -> bool {
+    pattern == "*"
+        || pattern == "*/*"
+        || pattern.eq_ignore_ascii_case(mime)
+        || pattern.strip_suffix("/*").is_some_and(|major| {
+            mime.split('/')
+                .next()
+                .is_some_and(|mime_major| mime_major.eq_ignore_ascii_case(major))
+        })
+}
+
+/// `mimeapps.list` files in precedence order, as laid out by the XDG mime-apps spec
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let desktops: Vec<String> = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| format!("{}-mimeapps.list", desktop.to_lowercase()))
+        .collect();
+    let names = || {
+        desktops
+            .iter()
+            .cloned()
+            .chain(std::iter::once("mimeapps.list".to_string()))
+    };
+    let env_dirs = |var: &str, fallback: &str| -> Vec<PathBuf> {
+        std::env::var(var)
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| fallback.to_string())
+            .split(':')
+            .map(PathBuf::from)
+            .collect()
+    };
+
+    let config_dirs = dirs::config_dir()
+        .into_iter()
+        .chain(env_dirs("XDG_CONFIG_DIRS", "/etc/xdg"));
+    let data_dirs: Vec<PathBuf> = dirs::data_dir()
+        .into_iter()
+        .chain(env_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+        .map(|dir| dir.join("applications"))
+        .collect();
+
+    let mut lists = Vec::new();
+    for dir in config_dirs.chain(data_dirs.iter().cloned()) {
+        lists.extend(names().map(|name| dir.join(name)));
+    }
+    // Legacy location still written by some desktops
+    lists.extend(data_dirs.iter().map(|dir| dir.join("defaults.list")));
+    lists
+}
+
+/// The desktop id registered as the default handler for `mime`, if any
+pub fn default_application(mime: &str) -> Option<String> {
+    mimeapps_lists().into_iter().find_map(|path| {
+        let content = std::fs::read_to_string(&path).ok()?;
+        let mut in_defaults = false;
+        content.lines().map(str::trim).find_map(|line| {
+            if line.starts_with('[') {
+                in_defaults = line == "[Default Applications]";
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            if !in_defaults || !key.trim().eq_ignore_ascii_case(mime) {
+                return None;
+            }
+            value
+                .split(';')
+                .map(str::trim)
+                .find(|app| !app.is_empty())
+                .map(str::to_string)
+        })
+    })
+}
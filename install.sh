@@ -4,6 +4,8 @@ if [ -z "$bin" ]; then
     exit 1
 fi
 echo "Building faucet..."
-cargo build --release || exit 1
+# features="x11 gui" builds only those; the default is all of x11, wayland, wsl (display servers), gui (the dmenu
+# picker), ocr (derive_command), daemon (faucet daemon, serve and client), lua and wasm (scorers)
+cargo build --release ${features:+--no-default-features --features "$features"} || exit 1
 cp -f "$(pwd)/target/release/faucet" "$bin/faucet"
 echo "Copied $(realpath target/release/faucet) to $bin"